@@ -34,19 +34,24 @@ int dmx_open(const char* port) {
   return (int)(intptr_t)handle;
 }
 
-void dmx_send_break(int fd) {
+int dmx_send_break(int fd) {
   HANDLE handle = (HANDLE)(intptr_t)fd;
 
   // Send BREAK condition (minimum 88μs, we use 100μs)
-  SetCommBreak(handle);
+  if (!SetCommBreak(handle)) {
+    return -1;
+  }
   Sleep(1);  // 1ms = 1000μs (much longer than minimum, but safe)
-  ClearCommBreak(handle);
+  if (!ClearCommBreak(handle)) {
+    return -1;
+  }
 
   // Send MAB (Mark After Break) - minimum 8μs, we use 12μs
   // Line is already in MARK state after ClearCommBreak
   // Windows Sleep() minimum is 1ms, so we can't get precise μs timing
   // For better timing, you'd need QueryPerformanceCounter or multimedia timers
   // For now, the 1ms break includes adequate MAB time
+  return 0;
 }
 
 int dmx_read_frame(int fd, uint8_t* buffer, int length) {
@@ -97,15 +102,19 @@ int dmx_open(const char* port) {
   return fd;
 }
 
-void dmx_send_break(int fd) {
+int dmx_send_break(int fd) {
   // Send BREAK condition
-  tcsendbreak(fd, 0);  // duration 0 = default break time (usually 250-500ms)
+  // duration 0 = default break time (usually 250-500ms)
+  if (tcsendbreak(fd, 0) < 0) {
+    return -1;
+  }
 
   // Send MAB (Mark After Break) - minimum 8μs, we use 12μs
   usleep(12);  // 12 microseconds MAB
 
   // Note: tcsendbreak() timing varies by system
   // For precise DMX timing, you might need custom break generation
+  return 0;
 }
 
 int dmx_write(int fd, const uint8_t* data, int length) {
@@ -22,8 +22,9 @@ int dmx_open(const char* port);
  * This signals the start of a new DMX frame to all connected devices.
  *
  * @param fd File descriptor returned by dmx_open()
+ * @return 0 on success, -1 if the interface failed to assert the break
  */
-void dmx_send_break(int fd);
+int dmx_send_break(int fd);
 
 /**
  * @brief Writes DMX data to the serial port
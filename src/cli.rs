@@ -1,9 +1,27 @@
 use std::{
+    collections::{BTreeMap, HashMap, HashSet},
     io::{self, Write},
     str::FromStr,
+    sync::Mutex,
+    time::Duration,
 };
 
-use crate::universe::cue::CueEngine;
+use crate::fixture::{
+    patch::{ChannelType, DimmerCurve, PatchedFixture},
+    registry::{FixtureRegistry, MatchField},
+};
+use crate::universe::{
+    clock::{format_clock, ShowClock},
+    cue::{
+        clamp_fade_time, diff_buffers, validate_cue_name, CueEngine, CueTime, RecordSource,
+        MAIN_PLAYBACK,
+    },
+    from_percent,
+    session::{load_session, save_session, SessionPlayer},
+    show::{load_show, save_show, Show},
+    to_percent, ChannelCapabilities, FixtureInfo, Neutral, UniverseCommand, UniverseStatus,
+    MAX_FRAME_RATE, MAX_KELVIN, MIN_FRAME_RATE, MIN_KELVIN,
+};
 use anyhow::{anyhow, Context, Result};
 
 /// Helper function to parse arguments with better error handling
@@ -24,43 +42,414 @@ where
     })
 }
 
+/// Parse a level given as 0-255, as a percent such as `50%`, or as 'f'/'full'.
+/// Percents above 100 are taken as full.
 fn parse_intensity(value: &str) -> Result<u8> {
-    if value.contains('f') || value.contains("full") {
+    if let Some(percent) = value.strip_suffix('%') {
+        let percent: u32 = percent
+            .parse()
+            .with_context(|| format!("'{}' is not a whole percent, e.g. 50%", value))?;
+        if percent > 100 {
+            eprintln!("Warning: {}% is above 100%, using full", percent);
+        }
+        Ok(from_percent(percent.min(100) as u8))
+    } else if value.contains('f') || value.contains("full") {
         Ok(255)
     } else {
         value
             .parse()
-            .with_context(|| "Intensity must be a number or 'f'/'full'".to_string())
+            .with_context(|| "Intensity must be a number, a percent or 'f'/'full'".to_string())
     }
 }
 
 #[derive(Debug)]
 enum Command {
+    /// Run a command on one universe, from `a 2.15 @ 128` or `2/c 5 @ 100`
+    OnUniverse {
+        universe: u8,
+        command: Box<Command>,
+    },
     Channel {
-        channel: usize,
+        fixture: FixtureRef,
         action: ChannelAction,
     },
+    /// Set one fixture channel by its OFL channel name
+    NamedChannel {
+        fixture: FixtureRef,
+        name: String,
+        value: u8,
+    },
+    /// Set the Nth (0-based) channel of a type on fixtures that repeat it
+    IndexedChannel {
+        fixture: FixtureRef,
+        ctype: ChannelType,
+        instance: usize,
+        value: u8,
+    },
     Address {
         address: usize,
         value: u8,
     },
+    /// Hold an address at a value until unparked
+    Park {
+        address: usize,
+        value: u8,
+    },
+    Unpark(usize),
     Blackout,
+    /// Drop hand-set levels, handing the output back to the playbacks
+    ClearManual,
+    /// Latch or release a blackout that holds at the output
+    BlackoutLatch(bool),
+    /// Blackout that also latches, so nothing can come back on until released
+    Panic,
     GetChannels(usize),
-    Go,
-    Back,
+    Query(usize),
+    Patch,
+    /// List manufacturers, optionally only those starting with a prefix
+    Manufacturers(Option<String>),
+    /// Parse every fixture in the library and list the ones that fail
+    CheckLibrary,
+    /// Search the fixture library, optionally by file name only
+    Search {
+        term: String,
+        name_only: bool,
+    },
+    /// Compare two library fixtures, each given as (manufacturer, fixture)
+    Compare {
+        a: (String, String),
+        b: (String, String),
+    },
+    /// Name the manufacturer of an RDM UID, by its ESTA manufacturer ID
+    Rdm(u32),
+    FreeAddress(u16),
+    Mute {
+        channel: usize,
+        muted: bool,
+    },
+    /// Add a fixture to the solo set, or clear it with None
+    Solo(Option<usize>),
+    /// Write one fixture's current values onto others, once
+    Copy {
+        source: usize,
+        targets: Vec<usize>,
+    },
+    /// Name a set of channels for `g <name>`, replacing any group of that name
+    DefineGroup {
+        name: String,
+        channels: Vec<usize>,
+    },
+    DeleteGroup(String),
+    ListGroups,
+    /// Make a fixture copy another's output, or stop with None
+    Mirror {
+        channel: usize,
+        source: Option<usize>,
+    },
+    Raw {
+        channel: usize,
+        values: Vec<u8>,
+    },
+    /// Set channel functions on every fixture that has them
+    All(Vec<(ChannelType, u8)>),
+    /// Reset color or position across the rig
+    Neutral(Neutral),
+    /// Record current pan/tilt (and color) as home for one fixture, or all with None
+    SetHome {
+        channel: Option<usize>,
+        with_color: bool,
+    },
+    /// Set channel functions on every fixture in an OFL category, or list it with None
+    Category {
+        category: String,
+        values: Option<Vec<(ChannelType, u8)>>,
+    },
+    ColorLimit {
+        channel: usize,
+        ctype: ChannelType,
+        limit: Option<u8>,
+    },
+    Curve {
+        channel: usize,
+        curve: DimmerCurve,
+    },
+    /// Set a 16-bit function, such as pan, across its coarse and fine channels
+    Fine {
+        channel: usize,
+        ctype: ChannelType,
+        value: u16,
+    },
+    /// Step a playback forward, optionally with a one-shot fade time
+    Go {
+        playback: String,
+        fade: Option<Duration>,
+    },
+    GoToCue {
+        name: String,
+        intensity_percent: u8,
+    },
+    Back(String),
+    /// Move the fader of the cue live on a playback
+    Fader {
+        playback: String,
+        level: u8,
+    },
+    /// Select (creating if needed) the playback cue editing acts on, or list them with None
+    Playback(Option<String>),
     RecordCue {
         name: String,
-        time_in_ms: u32,
+        time_in: CueTime,
+        source: RecordSource,
     },
     DeleteCue(String),
+    RenameCue {
+        old: String,
+        new: String,
+    },
+    UpdateCue,
+    /// Put a cue on stage for editing; `update` then saves the live output into it
+    LoadCue(String),
+    PreviewCue(String),
+    /// List cues with levels on addresses no fixture is patched to
+    Validate,
+    /// Write a printable cue sheet for the selected playback to a file
+    CueSheet(String),
+    /// Write the patch and every playback's cues to a file
+    SaveShow(String),
+    /// Replace the patch and cues with a saved show
+    LoadShow(String),
+    Report(usize),
+    /// List what each channel of a fixture can do, range by range
+    Capabilities(usize),
+    /// Show how intensity percents come out for one fixture
+    Calibrate(usize),
+    Trace(TraceTarget),
+    Stats,
+    /// Change the DMX refresh rate of the running output, in Hz
+    FrameRate(f32),
+    /// List addresses held at full for at least this long
+    Health(Duration),
+    Status,
+    Output {
+        universe: u8,
+        enabled: bool,
+    },
+    /// Change the sACN priority of a universe, or the first one
+    Priority {
+        universe: Option<u8>,
+        level: u8,
+    },
+    RecordSession(String),
+    StopSessionRecording,
+    PlaySession(String),
+    Mark,
+    Diff,
+    /// Start, stop or reset the show clock
+    Clock(ClockAction),
     Help,
     Error(anyhow::Error),
 }
 
+/// A fixture named either by control channel or by its label, or a group of
+/// channels from `1 thru 8` or `1 + 3 + 5`
+#[derive(Debug)]
+enum FixtureRef {
+    Channel(usize),
+    Label(String),
+    /// Channels without a patched fixture are skipped
+    Channels(Vec<usize>),
+    /// A group defined with `group <name> = ...`, from `g <name>`
+    Group(String),
+}
+
+#[derive(Debug)]
+enum TraceTarget {
+    Address(usize),
+    Fixture(usize),
+}
+
+#[derive(Debug)]
+enum ClockAction {
+    Start,
+    Stop,
+    Reset,
+}
+
+/// What a `c` command sets on a fixture. Intensity and color may both be
+/// present, and both go out in the same `SetFixture` so the change lands in
+/// one frame. A color temperature or HSV color stands in for `rgb`.
 #[derive(Debug)]
-enum ChannelAction {
-    Intensity(u8),
-    Rgb(u8, u8, u8),
+struct ChannelAction {
+    intensity: Option<u8>,
+    color: Option<(u8, u8, u8)>,
+    kelvin: Option<u16>,
+    hsv: Option<(f32, f32, f32)>,
+    strobe: Option<u8>,
+    position: Option<(u16, u16)>,
+    gobo: Option<usize>,
+}
+
+/// Split a command line on whitespace, keeping double-quoted text together
+/// as one argument so names like "Act One" survive. Whitespace just inside
+/// the quotes is trimmed, so `" Act One "` and `"Act One"` name the same thing.
+fn tokenize(input: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut in_quotes = false;
+    let mut quoted = String::new();
+
+    for ch in input.chars() {
+        if in_quotes {
+            if ch == '"' {
+                current.push_str(quoted.trim());
+                quoted.clear();
+                in_quotes = false;
+            } else {
+                quoted.push(ch);
+            }
+        } else if ch == '"' {
+            in_quotes = true;
+            in_token = true;
+        } else if ch.is_whitespace() {
+            if in_token {
+                tokens.push(std::mem::take(&mut current));
+                in_token = false;
+            }
+        } else {
+            current.push(ch);
+            in_token = true;
+        }
+    }
+
+    if in_quotes {
+        return Err(anyhow!("Unterminated quote"));
+    }
+    if in_token {
+        tokens.push(current);
+    }
+
+    Ok(tokens)
+}
+
+/// Parse the fixture reference starting at `index`, returning it and the index
+/// of the first argument after it. Channels can be grouped with `thru` ranges
+/// and `+` lists, e.g. `1 thru 4 + 7`; a backwards range is turned around.
+fn parse_fixture_ref(args: &[&str], index: usize) -> Result<(FixtureRef, usize)> {
+    let arg = args
+        .get(index)
+        .with_context(|| "Missing channel or label argument")?;
+    let first = match arg.parse::<usize>() {
+        Ok(channel) => channel,
+        Err(_) => return Ok((FixtureRef::Label(arg.to_string()), index + 1)),
+    };
+
+    let mut channels = vec![first];
+    let mut next = index + 1;
+    while let Some(&operator) = args.get(next).filter(|arg| matches!(**arg, "thru" | "+")) {
+        let channel = parse_arg::<usize>(args, next + 1, "channel")
+            .with_context(|| format!("'{}' must be followed by a channel number", operator))?;
+        if operator == "thru" {
+            let from = channels.pop().unwrap_or(channel);
+            channels.extend(from.min(channel)..=from.max(channel));
+        } else {
+            channels.push(channel);
+        }
+        next += 2;
+    }
+
+    if next == index + 1 {
+        return Ok((FixtureRef::Channel(first), next));
+    }
+    let mut seen = HashSet::new();
+    channels.retain(|channel| seen.insert(*channel));
+    Ok((FixtureRef::Channels(channels), next))
+}
+
+/// Control channels a reference resolves to. Labels must match exactly one
+/// fixture; a group keeps only the channels that have one patched.
+fn resolve_fixture_ref(
+    fixture: &FixtureRef,
+    show: &CueEngine,
+    command_tx: &std::sync::mpsc::Sender<UniverseCommand>,
+) -> Result<Vec<usize>> {
+    let channels = match fixture {
+        FixtureRef::Channel(channel) => return Ok(vec![*channel]),
+        FixtureRef::Label(label) => {
+            return Ok(vec![find_fixture_by_label(
+                &query_fixtures(command_tx)?,
+                label,
+            )?])
+        }
+        FixtureRef::Channels(channels) => channels.as_slice(),
+        FixtureRef::Group(name) => show.group(name)?,
+    };
+
+    let patched: HashSet<usize> = query_fixtures(command_tx)?
+        .iter()
+        .map(|fixture| fixture.channel)
+        .collect();
+    let found: Vec<usize> = channels
+        .iter()
+        .copied()
+        .filter(|channel| patched.contains(channel))
+        .collect();
+    if found.is_empty() {
+        return Err(anyhow!("No fixtures patched on those channels"));
+    }
+    Ok(found)
+}
+
+/// Find the control channel of the one fixture whose label matches, ignoring case
+fn find_fixture_by_label(fixtures: &[FixtureInfo], label: &str) -> Result<usize> {
+    let wanted = label.to_lowercase();
+    let matches: Vec<&FixtureInfo> = fixtures
+        .iter()
+        .filter(|fixture| fixture.label.to_lowercase() == wanted)
+        .collect();
+
+    match matches.as_slice() {
+        [fixture] => Ok(fixture.channel),
+        [] => Err(anyhow!("No fixture labelled \"{}\"", label)),
+        _ => Err(anyhow!(
+            "Label \"{}\" matches several fixtures: {}",
+            label,
+            matches
+                .iter()
+                .map(|fixture| format!("{} ({})", fixture.channel, fixture.label))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Split a `<universe>.<address>` or `<universe>/<address>` global address.
+/// A bare address names no universe and goes to the first one.
+fn parse_global_address(arg: &str) -> Result<(Option<u8>, usize)> {
+    let (universe, address) = match arg.split_once(['.', '/']) {
+        Some((universe, address)) => {
+            let universe = universe
+                .parse()
+                .with_context(|| "Universe must be a number from 0 to 255")?;
+            (Some(universe), address)
+        }
+        None => (None, arg),
+    };
+    let address = address
+        .parse()
+        .with_context(|| "Address must be a number")?;
+    Ok((universe, address))
+}
+
+/// Wrap a parsed command so it runs on `universe`, passing errors through
+fn on_universe(universe: u8, command: Command) -> Command {
+    match command {
+        Command::Error(e) => Command::Error(e),
+        command => Command::OnUniverse {
+            universe,
+            command: Box::new(command),
+        },
+    }
 }
 
 fn parse_command(args: &[&str]) -> Command {
@@ -68,52 +457,67 @@ fn parse_command(args: &[&str]) -> Command {
         return Command::Error(anyhow!("Empty command"));
     }
 
+    // `2/c 5 @ 100` addresses fixture 5 on universe 2
+    if let Some((universe, "c")) = args[0].split_once('/') {
+        let mut fixture_args = args.to_vec();
+        fixture_args[0] = "c";
+        return match universe.parse::<u8>() {
+            Ok(universe) => on_universe(universe, parse_command(&fixture_args)),
+            Err(_) => Command::Error(anyhow!("Universe must be a number from 0 to 255")),
+        };
+    }
+
     match args[0] {
         "c" => {
-            let channel = match parse_arg::<usize>(args, 1, "channel") {
+            let (fixture, next) = match parse_fixture_ref(args, 1) {
                 Ok(val) => val,
                 Err(e) => return Command::Error(e),
             };
-
-            if args.get(2).map_or(false, |s| s.contains("@")) {
-                match args
-                    .get(3)
-                    .ok_or_else(|| anyhow!("Missing intensity"))
-                    .and_then(|s| parse_intensity(s))
-                {
-                    Ok(intensity) => Command::Channel {
-                        channel,
-                        action: ChannelAction::Intensity(intensity),
-                    },
-                    Err(e) => Command::Error(e),
-                }
-            } else if args.get(2).map_or(false, |s| s.contains("rgb")) {
-                match (|| -> Result<(u8, u8, u8)> {
-                    let r = parse_arg::<u8>(args, 3, "red")?;
-                    let g = parse_arg::<u8>(args, 4, "green")?;
-                    let b = parse_arg::<u8>(args, 5, "blue")?;
-                    Ok((r, g, b))
-                })() {
-                    Ok((r, g, b)) => Command::Channel {
-                        channel,
-                        action: ChannelAction::Rgb(r, g, b),
-                    },
-                    Err(e) => Command::Error(e),
-                }
-            } else {
-                Command::Error(anyhow::anyhow!(
-                    "Use: c <channel> @ <intensity> or c <channel> rgb <r> <g> <b>"
-                ))
-            }
+            // Everything after the fixture reference, starting at the selector
+            parse_fixture_command(fixture, &args[next..])
         }
+        "g" => match args.get(1) {
+            Some(name) => parse_fixture_command(FixtureRef::Group(name.to_string()), &args[2..]),
+            None => Command::Error(anyhow!("Use: g <group> @ <intensity>")),
+        },
+        "group" => match args {
+            [_] => Command::ListGroups,
+            [_, name, "=", ..] => match parse_fixture_ref(args, 3) {
+                Ok((FixtureRef::Channel(channel), next)) if next == args.len() => {
+                    Command::DefineGroup {
+                        name: name.to_string(),
+                        channels: vec![channel],
+                    }
+                }
+                Ok((FixtureRef::Channels(channels), next)) if next == args.len() => {
+                    Command::DefineGroup {
+                        name: name.to_string(),
+                        channels,
+                    }
+                }
+                Err(e) => Command::Error(e),
+                Ok(_) => Command::Error(anyhow!(
+                    "Groups are made of channel numbers, e.g. group front = 1 thru 8 + 10"
+                )),
+            },
+            _ => Command::Error(anyhow!("Use: group <name> = <ch> thru <ch> + <ch>")),
+        },
+        "ungroup" => match parse_arg::<String>(args, 1, "group name") {
+            Ok(name) => Command::DeleteGroup(name),
+            Err(e) => Command::Error(e),
+        },
         "a" => {
             match (
-                parse_arg::<usize>(args, 1, "address"),
+                parse_arg::<String>(args, 1, "address")
+                    .and_then(|address| parse_global_address(&address)),
                 args.get(3)
                     .ok_or(anyhow!("Missing value"))
                     .and_then(|s| parse_intensity(s)),
             ) {
-                (Ok(address), Ok(value)) => Command::Address { address, value },
+                (Ok((None, address)), Ok(value)) => Command::Address { address, value },
+                (Ok((Some(universe), address)), Ok(value)) => {
+                    on_universe(universe, Command::Address { address, value })
+                }
                 (Err(e), _) | (_, Err(e)) => Command::Error(e),
             }
         }
@@ -121,113 +525,1114 @@ fn parse_command(args: &[&str]) -> Command {
             Ok(channel) => Command::GetChannels(channel),
             Err(e) => Command::Error(e),
         },
-        "blackout" => Command::Blackout,
-        "rc" => match parse_arg::<String>(args, 1, "cue_name") {
-            Ok(name) => match parse_arg::<u32>(args, 2, "time_in") {
-                Ok(time_in) => Command::RecordCue {
-                    name: name,
-                    time_in_ms: time_in,
+        "report" => match parse_arg::<usize>(args, 1, "channel") {
+            Ok(channel) => Command::Report(channel),
+            Err(e) => Command::Error(e),
+        },
+        "capabilities" | "caps" => match parse_arg::<usize>(args, 1, "channel") {
+            Ok(channel) => Command::Capabilities(channel),
+            Err(e) => Command::Error(e),
+        },
+        "calibrate" => match parse_arg::<usize>(args, 1, "channel") {
+            Ok(channel) => Command::Calibrate(channel),
+            Err(e) => Command::Error(e),
+        },
+        "query" => match parse_arg::<usize>(args, 1, "address") {
+            Ok(address) => Command::Query(address),
+            Err(e) => Command::Error(e),
+        },
+        "blackout" => match args.get(1) {
+            None => Command::Blackout,
+            Some(&"latch") => Command::BlackoutLatch(true),
+            Some(&"release") => Command::BlackoutLatch(false),
+            Some(other) => Command::Error(anyhow!(
+                "Expected 'latch' or 'release' after blackout, got '{}'",
+                other
+            )),
+        },
+        "panic" => Command::Panic,
+        "manual" => match args.get(1) {
+            Some(&"clear") => Command::ClearManual,
+            _ => Command::Error(anyhow!("Use: manual clear")),
+        },
+        "patch" => Command::Patch,
+        "manufacturers" => Command::Manufacturers(args.get(1).map(|prefix| prefix.to_string())),
+        "checklib" => Command::CheckLibrary,
+        "search" => match parse_arg::<String>(args, 1, "term") {
+            Ok(term) => Command::Search {
+                term,
+                name_only: args.get(2) == Some(&"names"),
+            },
+            Err(e) => Command::Error(e),
+        },
+        "compare" => parse_compare(args).unwrap_or_else(Command::Error),
+        "rdm" => parse_rdm(args).unwrap_or_else(Command::Error),
+        "priority" => parse_priority(args).unwrap_or_else(Command::Error),
+        "freeaddr" => match parse_arg::<u16>(args, 1, "footprint") {
+            Ok(footprint) => Command::FreeAddress(footprint),
+            Err(e) => Command::Error(e),
+        },
+        "raw" => parse_raw(args).unwrap_or_else(Command::Error),
+        "colorlimit" => parse_color_limit(args).unwrap_or_else(Command::Error),
+        "curve" => match (parse_arg::<usize>(args, 1, "channel"), args.get(2)) {
+            (Ok(channel), Some(name)) => match DimmerCurve::from_name(name) {
+                Some(curve) => Command::Curve { channel, curve },
+                None => Command::Error(anyhow!(
+                    "Unknown curve '{}', use linear, square, invsquare or s",
+                    name
+                )),
+            },
+            (Err(e), _) => Command::Error(e),
+            _ => Command::Error(anyhow!("Use: curve <channel> linear|square|invsquare|s")),
+        },
+        "fine" => match (
+            parse_arg::<usize>(args, 1, "channel"),
+            args.get(2),
+            parse_arg::<u16>(args, 3, "16-bit value"),
+        ) {
+            (Ok(channel), Some(function), Ok(value)) => Command::Fine {
+                channel,
+                ctype: ChannelType::from_ofl_channel_name(function),
+                value,
+            },
+            (Err(e), _, _) | (_, Some(_), Err(e)) => Command::Error(e),
+            _ => Command::Error(anyhow!("Use: fine <channel> <type> <0-65535>")),
+        },
+        "all" => parse_function_values(args, 1).map_or_else(Command::Error, Command::All),
+        "category" => parse_category(args).unwrap_or_else(Command::Error),
+        "neutral" => match (args.get(1).copied(), args.get(2).copied()) {
+            (Some("color"), None | Some("white")) => {
+                Command::Neutral(Neutral::Color { white: true })
+            }
+            (Some("color"), Some("off")) => Command::Neutral(Neutral::Color { white: false }),
+            (Some("position"), None) => Command::Neutral(Neutral::Position),
+            (Some("home"), None) => Command::Neutral(Neutral::Home),
+            _ => Command::Error(anyhow!(
+                "Use: neutral color [white|off], neutral position or neutral home"
+            )),
+        },
+        "sethome" => {
+            let channel = match args.get(1) {
+                Some(&"all") => Ok(None),
+                _ => parse_arg::<usize>(args, 1, "channel").map(Some),
+            };
+            match (channel, args.get(2)) {
+                (Ok(channel), None) => Command::SetHome {
+                    channel,
+                    with_color: false,
                 },
+                (Ok(channel), Some(&"color")) => Command::SetHome {
+                    channel,
+                    with_color: true,
+                },
+                (Err(e), _) => Command::Error(e),
+                (Ok(_), Some(_)) => Command::Error(anyhow!("Use: sethome <channel>|all [color]")),
+            }
+        }
+        "solo" => match args.get(1) {
+            Some(&"clear") => Command::Solo(None),
+            _ => match parse_arg::<usize>(args, 1, "channel") {
+                Ok(channel) => Command::Solo(Some(channel)),
                 Err(e) => Command::Error(e),
             },
+        },
+        "copy" => parse_copy(args).unwrap_or_else(Command::Error),
+        "mirror" => match (
+            parse_arg::<usize>(args, 1, "channel"),
+            args.get(2),
+            parse_arg::<usize>(args, 3, "source channel"),
+        ) {
+            (Ok(channel), Some(&"="), Ok(source)) => Command::Mirror {
+                channel,
+                source: Some(source),
+            },
+            (Err(e), _, _) | (_, Some(&"="), Err(e)) => Command::Error(e),
+            _ => Command::Error(anyhow!("Use: mirror <channel> = <source channel>")),
+        },
+        "unmirror" => match parse_arg::<usize>(args, 1, "channel") {
+            Ok(channel) => Command::Mirror {
+                channel,
+                source: None,
+            },
+            Err(e) => Command::Error(e),
+        },
+        "park" => {
+            match (
+                parse_arg::<String>(args, 1, "address")
+                    .and_then(|address| parse_global_address(&address)),
+                args.get(3)
+                    .ok_or(anyhow!("Missing value"))
+                    .and_then(|s| parse_intensity(s)),
+            ) {
+                (Ok((None, address)), Ok(value)) => Command::Park { address, value },
+                (Ok((Some(universe), address)), Ok(value)) => {
+                    on_universe(universe, Command::Park { address, value })
+                }
+                (Err(e), _) | (_, Err(e)) => Command::Error(e),
+            }
+        }
+        "unpark" => match parse_arg::<String>(args, 1, "address")
+            .and_then(|address| parse_global_address(&address))
+        {
+            Ok((None, address)) => Command::Unpark(address),
+            Ok((Some(universe), address)) => on_universe(universe, Command::Unpark(address)),
+            Err(e) => Command::Error(e),
+        },
+        "mute" | "unmute" => match parse_arg::<usize>(args, 1, "channel") {
+            Ok(channel) => Command::Mute {
+                channel,
+                muted: args[0] == "mute",
+            },
+            Err(e) => Command::Error(e),
+        },
+        "rc" => match args
+            .get(1)
+            .ok_or_else(|| anyhow!("Missing cue name: rc <cue> <ms>|follow [from <playback>]"))
+            .and_then(|name| validate_cue_name(name))
+        {
+            Ok(name) => match (parse_cue_time(args, 2), args.get(3..)) {
+                (Ok(time_in), None | Some([])) => Command::RecordCue {
+                    name: name.to_string(),
+                    time_in,
+                    source: RecordSource::Live,
+                },
+                (Ok(time_in), Some(["from", playback])) => Command::RecordCue {
+                    name: name.to_string(),
+                    time_in,
+                    source: RecordSource::Playback(playback.to_string()),
+                },
+                (Ok(_), _) => {
+                    Command::Error(anyhow!("Usage: rc <name> <time>|follow [from <playback>]"))
+                }
+                (Err(e), _) => Command::Error(e),
+            },
             Err(e) => Command::Error(e),
         },
         "dc" => match parse_arg::<String>(args, 1, "cue_name") {
             Ok(name) => Command::DeleteCue(name),
             Err(e) => Command::Error(e),
         },
-        "go" => Command::Go,
-        "back" => Command::Back,
+        "trace" => {
+            let target = if args.get(1) == Some(&"c") {
+                parse_arg::<usize>(args, 2, "channel").map(TraceTarget::Fixture)
+            } else {
+                parse_arg::<usize>(args, 1, "address").map(TraceTarget::Address)
+            };
+            match target {
+                Ok(target) => Command::Trace(target),
+                Err(e) => Command::Error(e),
+            }
+        }
+        "stats" => Command::Stats,
+        "rate" => match (args.get(1), parse_arg::<f32>(args, 2, "hz")) {
+            (Some(&"dmx"), Ok(hz)) => Command::FrameRate(hz),
+            (Some(&"dmx"), Err(e)) => Command::Error(e),
+            _ => Command::Error(anyhow!("Use: rate dmx <hz>")),
+        },
+        "health" => match args.get(1) {
+            None => Command::Health(DEFAULT_PINNED_TIME),
+            Some(_) => match parse_arg::<u64>(args, 1, "minutes") {
+                Ok(minutes) => Command::Health(Duration::from_secs(minutes * 60)),
+                Err(e) => Command::Error(e),
+            },
+        },
+        "status" => Command::Status,
+        "output" => match (
+            parse_arg::<u8>(args, 1, "universe"),
+            args.get(2).copied(),
+        ) {
+            (Ok(universe), Some("on")) => Command::Output {
+                universe,
+                enabled: true,
+            },
+            (Ok(universe), Some("off")) => Command::Output {
+                universe,
+                enabled: false,
+            },
+            (Ok(_), _) => Command::Error(anyhow!("Usage: output <universe> on|off")),
+            (Err(e), _) => Command::Error(e),
+        },
+        "record" => match args.get(1).copied() {
+            Some("session") => match parse_arg::<String>(args, 2, "path") {
+                Ok(path) => Command::RecordSession(path),
+                Err(e) => Command::Error(e),
+            },
+            Some("stop") => Command::StopSessionRecording,
+            _ => Command::Error(anyhow!("Use: record session <path> or record stop")),
+        },
+        "play" => match (args.get(1).copied(), parse_arg::<String>(args, 2, "path")) {
+            (Some("session"), Ok(path)) => Command::PlaySession(path),
+            (Some("session"), Err(e)) => Command::Error(e),
+            _ => Command::Error(anyhow!("Use: play session <path>")),
+        },
+        "mark" => Command::Mark,
+        "clock" => match args.get(1).copied() {
+            Some("start") => Command::Clock(ClockAction::Start),
+            Some("stop") => Command::Clock(ClockAction::Stop),
+            Some("reset") => Command::Clock(ClockAction::Reset),
+            _ => Command::Error(anyhow!("Use: clock start|stop|reset")),
+        },
+        "diff" => Command::Diff,
+        "update" => Command::UpdateCue,
+        "loadcue" => match parse_arg::<String>(args, 1, "cue_name") {
+            Ok(name) => Command::LoadCue(name),
+            Err(e) => Command::Error(e),
+        },
+        "validate" => Command::Validate,
+        "cuesheet" => match parse_arg::<String>(args, 1, "path") {
+            Ok(path) => Command::CueSheet(path),
+            Err(e) => Command::Error(e),
+        },
+        "save" => match parse_arg::<String>(args, 1, "path") {
+            Ok(path) => Command::SaveShow(path),
+            Err(e) => Command::Error(e),
+        },
+        "load" => match parse_arg::<String>(args, 1, "path") {
+            Ok(path) => Command::LoadShow(path),
+            Err(e) => Command::Error(e),
+        },
+        "preview" => match parse_arg::<String>(args, 1, "cue_name") {
+            Ok(name) => Command::PreviewCue(name),
+            Err(e) => Command::Error(e),
+        },
+        "rename" => match (
+            parse_arg::<String>(args, 1, "old_name"),
+            parse_arg::<String>(args, 2, "new_name"),
+        ) {
+            (Ok(old), Ok(new)) => Command::RenameCue { old, new },
+            (Err(e), _) | (_, Err(e)) => Command::Error(e),
+        },
+        "go" => parse_go(args).unwrap_or_else(Command::Error),
+        "goto" => parse_goto(args).unwrap_or_else(Command::Error),
+        "back" => Command::Back(playback_arg(args)),
+        "fader" => {
+            let (playback, level) = match args {
+                [_, playback, level] => (playback.to_string(), Some(level)),
+                [_, level] => (MAIN_PLAYBACK.to_string(), Some(level)),
+                _ => (String::new(), None),
+            };
+            match level.map(|level| parse_intensity(level)) {
+                Some(Ok(level)) => Command::Fader { playback, level },
+                Some(Err(e)) => Command::Error(e),
+                None => Command::Error(anyhow!("Use: fader [playback] <level>")),
+            }
+        }
+        "playback" => Command::Playback(args.get(1).map(|name| name.to_string())),
         "help" => Command::Help,
         _ => Command::Error(anyhow!("Unknown command: {}", args[0])),
     }
 }
 
-/// CLI that uses command channels instead of direct universe access
-pub fn run_cli(
-    command_tx: std::sync::mpsc::Sender<crate::universe::UniverseCommand>,
-    show: &mut CueEngine,
-) {
-    println!("DMX Controller CLI - Command Mode");
-    println!("Commands:");
-    println!("  c <num> @ <intensity>         - Set fixture intensity");
-    println!("  c <num> rgb <r> <g> <b>       - Set fixture RGB color");
-    println!("  a <addr> @ <value>            - Set DMX address directly");
-    println!("  channels <fixture>            - List channels for fixture");
-    println!("  query <channel>               - Get current DMX value");
-    println!("  blackout                      - Turn off all fixtures");
-    println!("  quit/exit                     - Exit program");
-    println!("  help                          - Show this help");
-    println!();
+/// Parse what follows the fixture in `c <fixture> ...` or `g <group> ...`
+fn parse_fixture_command(fixture: FixtureRef, rest: &[&str]) -> Command {
+    match rest {
+        // `c 5 dimmer#2 @ 200` picks one of a repeated function, counting from 1
+        [function, "@", level] if function.contains('#') => {
+            parse_indexed_channel(fixture, function, level).unwrap_or_else(Command::Error)
+        }
+        // `c 5 "Shutter / Strobe" @ 200` names an OFL channel directly
+        [name, "@", level] if !matches!(*name, "@" | "rgb") => match parse_intensity(level) {
+            Ok(value) => Command::NamedChannel {
+                fixture,
+                name: name.to_string(),
+                value,
+            },
+            Err(e) => Command::Error(e),
+        },
+        _ => match parse_channel_action(rest) {
+            Ok(action) => Command::Channel { fixture, action },
+            Err(e) => Command::Error(e),
+        },
+    }
+}
 
-    loop {
-        print!("> ");
-        io::stdout().flush().unwrap();
+/// Parse `go [playback] [[in] <seconds>]`
+fn parse_go(args: &[&str]) -> Result<Command> {
+    let mut playback = MAIN_PLAYBACK.to_string();
+    let mut fade = None;
 
-        let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_err() {
-            println!("Failed to read input");
-            continue;
+    for arg in args.iter().skip(1).filter(|&&arg| arg != "in") {
+        match arg.parse::<f64>() {
+            Ok(seconds) if seconds >= 0.0 && seconds.is_finite() => {
+                fade = Some(clamp_fade_time((seconds * 1000.0).round() as u64));
+            }
+            Ok(_) => return Err(anyhow!("Fade time must be zero or more seconds")),
+            Err(_) => playback = arg.to_string(),
         }
+    }
 
-        let args: Vec<&str> = input.trim().split_whitespace().collect();
-        if args.is_empty() {
-            continue;
-        }
+    Ok(Command::Go { playback, fade })
+}
 
-        // Check for quit commands first
-        if matches!(args[0], "quit" | "exit" | "q") {
-            break;
-        }
+/// The playback named after `back`, or the main one
+fn playback_arg(args: &[&str]) -> String {
+    args.get(1).copied().unwrap_or(MAIN_PLAYBACK).to_string()
+}
 
-        let command = parse_command(&args);
+/// Parse `goto <cue> [at <percent>]`
+fn parse_goto(args: &[&str]) -> Result<Command> {
+    let name = parse_arg::<String>(args, 1, "cue_name")?;
+    let intensity_percent = match args.get(2) {
+        None => 100,
+        Some(&"at") => parse_arg::<u8>(args, 3, "percent")?,
+        Some(other) => return Err(anyhow!("Expected 'at <percent>', got '{}'", other)),
+    };
+    if intensity_percent > 100 {
+        return Err(anyhow!("percent must be between 0 and 100"));
+    }
 
-        match execute_command(&command, &command_tx, show) {
-            Ok(should_quit) => {
-                if should_quit {
-                    break;
-                }
-            }
-            Err(err) => {
-                println!("Error: {}", err);
-            }
-        }
+    Ok(Command::GoToCue {
+        name,
+        intensity_percent,
+    })
+}
+
+/// A cue fade time in milliseconds, or `follow` to inherit the previous cue's
+fn parse_cue_time(args: &[&str], index: usize) -> Result<CueTime> {
+    match args.get(index) {
+        Some(&"follow") => Ok(CueTime::Inherit),
+        _ => parse_arg::<u32>(args, index, "time_in").map(|ms| CueTime::from_millis(ms as u64)),
     }
+}
 
-    println!("CLI exiting...");
+/// Parse `compare <manufacturer>/<fixture> <manufacturer>/<fixture>`
+fn parse_compare(args: &[&str]) -> Result<Command> {
+    let fixture_key = |index: usize| -> Result<(String, String)> {
+        let key = args
+            .get(index)
+            .context("Usage: compare <manufacturer>/<fixture> <manufacturer>/<fixture>")?;
+        let (manufacturer, fixture) = key
+            .split_once('/')
+            .with_context(|| format!("Expected <manufacturer>/<fixture>, got '{}'", key))?;
+        Ok((manufacturer.to_string(), fixture.to_string()))
+    };
+
+    Ok(Command::Compare {
+        a: fixture_key(1)?,
+        b: fixture_key(2)?,
+    })
 }
 
-fn execute_command(
-    command: &Command,
-    command_tx: &std::sync::mpsc::Sender<crate::universe::UniverseCommand>,
-    show: &mut CueEngine,
-) -> Result<bool> {
-    use crate::universe::UniverseCommand;
+/// Parse `rdm <uid>`, a UID in hex as discovery tools print it
+/// (`0526:1A2B3C4D`) or just its manufacturer part
+fn parse_rdm(args: &[&str]) -> Result<Command> {
+    let uid = args.get(1).context("Usage: rdm <manufacturer>:<device>")?;
+    let manufacturer = uid
+        .split_once(':')
+        .map_or(*uid, |(manufacturer, _)| manufacturer);
+    let id = u16::from_str_radix(manufacturer.trim_start_matches("0x"), 16)
+        .with_context(|| format!("Expected a hex RDM UID, got '{}'", uid))?;
+    Ok(Command::Rdm(id.into()))
+}
 
-    match command {
-        Command::Channel { channel, action } => {
-            match action {
-                ChannelAction::Intensity(intensity) => {
-                    command_tx
-                        .send(UniverseCommand::SetFixture {
-                            fixture_channel: *channel,
-                            intensity: Some(*intensity),
-                            color: None,
-                        })
-                        .with_context(|| "Failed to send fixture command")?;
-                    println!("Set channel {} intensity to {}", channel, intensity);
+/// Parse `priority [universe] <level>`
+fn parse_priority(args: &[&str]) -> Result<Command> {
+    let (universe, level) = match args.len() {
+        2 => (None, parse_arg::<u8>(args, 1, "priority")?),
+        3 => (
+            Some(parse_arg::<u8>(args, 1, "universe")?),
+            parse_arg::<u8>(args, 2, "priority")?,
+        ),
+        _ => return Err(anyhow!("Usage: priority [universe] <0-200>")),
+    };
+    Ok(Command::Priority { universe, level })
+}
+
+/// Parse `copy <channel> to <channel> [+ <channel> ...]`
+fn parse_copy(args: &[&str]) -> Result<Command> {
+    let usage = || anyhow!("Use: copy <channel> to <channel> [+ <channel> ...]");
+    let source = parse_arg::<usize>(args, 1, "source channel")?;
+    if args.get(2) != Some(&"to") {
+        return Err(usage());
+    }
+
+    let targets = args[3..]
+        .split(|arg| *arg == "+")
+        .map(|target| match target {
+            [channel] => channel
+                .parse()
+                .with_context(|| "target channel must be a valid usize"),
+            _ => Err(usage()),
+        })
+        .collect::<Result<Vec<usize>>>()?;
+    Ok(Command::Copy { source, targets })
+}
+
+/// Parse `raw <channel> <b0> <b1> ...`
+fn parse_raw(args: &[&str]) -> Result<Command> {
+    let channel = parse_arg::<usize>(args, 1, "channel")?;
+    if args.len() < 3 {
+        return Err(anyhow!("Missing value arguments"));
+    }
+
+    let values = (2..args.len())
+        .map(|index| parse_arg::<u8>(args, index, "value"))
+        .collect::<Result<Vec<u8>>>()?;
+
+    Ok(Command::Raw { channel, values })
+}
+
+/// Parse the selectors after `c <fixture>`: `@ <level>` and one of
+/// `rgb <r> <g> <b>`, `cct <kelvin>` or `hsv <h> <s> <v>`, plus
+/// `strobe <rate>`, `pos <pan> <tilt>` and `gobo <slot>`, in any order
+fn parse_channel_action(args: &[&str]) -> Result<ChannelAction> {
+    let mut action = ChannelAction {
+        intensity: None,
+        color: None,
+        kelvin: None,
+        hsv: None,
+        strobe: None,
+        position: None,
+        gobo: None,
+    };
+    let mut i = 0;
+
+    while i < args.len() {
+        match args[i] {
+            "@" if action.intensity.is_none() => {
+                let level = args
+                    .get(i + 1)
+                    .ok_or_else(|| anyhow!("Missing intensity"))?;
+                action.intensity = Some(parse_intensity(level)?);
+                i += 2;
+            }
+            "rgb" if action.color.is_none() => {
+                let r = parse_arg::<u8>(args, i + 1, "red")?;
+                let g = parse_arg::<u8>(args, i + 2, "green")?;
+                let b = parse_arg::<u8>(args, i + 3, "blue")?;
+                action.color = Some((r, g, b));
+                i += 4;
+            }
+            "cct" if action.kelvin.is_none() => {
+                let kelvin = args
+                    .get(i + 1)
+                    .ok_or_else(|| anyhow!("Missing color temperature"))?;
+                action.kelvin = Some(
+                    kelvin
+                        .strip_suffix(['K', 'k'])
+                        .unwrap_or(kelvin)
+                        .parse()
+                        .with_context(|| format!("'{}' is not a color temperature", kelvin))?,
+                );
+                i += 2;
+            }
+            "hsv" if action.hsv.is_none() => {
+                let h = parse_arg::<f32>(args, i + 1, "hue")?;
+                let s = parse_arg::<f32>(args, i + 2, "saturation")?;
+                let v = parse_arg::<f32>(args, i + 3, "value")?;
+                action.hsv = Some((h, s, v));
+                i += 4;
+            }
+            "strobe" if action.strobe.is_none() => {
+                action.strobe = Some(parse_arg::<u8>(args, i + 1, "strobe rate")?);
+                i += 2;
+            }
+            "pos" if action.position.is_none() => {
+                let pan = parse_arg::<u16>(args, i + 1, "pan")?;
+                let tilt = parse_arg::<u16>(args, i + 2, "tilt")?;
+                action.position = Some((pan, tilt));
+                i += 3;
+            }
+            "gobo" if action.gobo.is_none() => {
+                action.gobo = Some(parse_arg::<usize>(args, i + 1, "gobo slot")?);
+                i += 2;
+            }
+            "@" | "rgb" | "cct" | "hsv" | "strobe" | "pos" | "gobo" => {
+                return Err(anyhow!("'{}' given more than once", args[i]))
+            }
+            other => return Err(anyhow!("Unexpected '{}'", other)),
+        }
+    }
+
+    let colors = [
+        action.color.is_some(),
+        action.kelvin.is_some(),
+        action.hsv.is_some(),
+    ];
+    match colors.iter().filter(|&&given| given).count() {
+        0 if action.intensity.is_none()
+            && action.strobe.is_none()
+            && action.position.is_none()
+            && action.gobo.is_none() =>
+        {
+            Err(anyhow!(
+                "Use: c <channel|label> [@ <intensity>] [rgb <r> <g> <b> | cct <kelvin> | hsv <h> <s> <v>] [strobe <rate>] [pos <pan> <tilt>] [gobo <slot>]"
+            ))
+        }
+        0 | 1 => Ok(action),
+        _ => Err(anyhow!("Give only one of rgb, cct or hsv")),
+    }
+}
+
+/// Parse `@ <level>`, `rgb <r> <g> <b>` or `<channeltype> <value>` starting at `index`
+fn parse_function_values(args: &[&str], index: usize) -> Result<Vec<(ChannelType, u8)>> {
+    let function = args
+        .get(index)
+        .with_context(|| "Missing channel type argument")?;
+
+    Ok(match *function {
+        "@" => {
+            let level = args.get(index + 1).with_context(|| "Missing intensity")?;
+            vec![(ChannelType::Intensity, parse_intensity(level)?)]
+        }
+        "rgb" => vec![
+            (ChannelType::Red, parse_arg::<u8>(args, index + 1, "red")?),
+            (ChannelType::Green, parse_arg::<u8>(args, index + 2, "green")?),
+            (ChannelType::Blue, parse_arg::<u8>(args, index + 3, "blue")?),
+        ],
+        name => vec![(
+            ChannelType::from_ofl_channel_name(name),
+            parse_arg::<u8>(args, index + 1, "value")?,
+        )],
+    })
+}
+
+/// Parse `<function>#<n> @ <level>`, the nth channel of a function counting
+/// from 1, into a command with the 0-based instance
+fn parse_indexed_channel(fixture: FixtureRef, function: &str, level: &str) -> Result<Command> {
+    let (name, number) = function
+        .rsplit_once('#')
+        .with_context(|| format!("Expected <function>#<n>, got '{}'", function))?;
+    let number = number
+        .parse::<usize>()
+        .ok()
+        .filter(|&number| number >= 1)
+        .ok_or_else(|| anyhow!("Channel number in '{}' must be 1 or more", function))?;
+
+    Ok(Command::IndexedChannel {
+        fixture,
+        ctype: ChannelType::from_ofl_channel_name(name),
+        instance: number - 1,
+        value: parse_intensity(level)?,
+    })
+}
+
+/// Parse `category <name> [<values>]`; without values it lists the category
+fn parse_category(args: &[&str]) -> Result<Command> {
+    let category = parse_arg::<String>(args, 1, "category")?;
+    let values = if args.len() > 2 {
+        Some(parse_function_values(args, 2)?)
+    } else {
+        None
+    };
+
+    Ok(Command::Category { category, values })
+}
+
+/// Parse `colorlimit <channel> <color> <max|off>`
+fn parse_color_limit(args: &[&str]) -> Result<Command> {
+    let channel = parse_arg::<usize>(args, 1, "channel")?;
+    let color = args.get(2).with_context(|| "Missing color argument")?;
+    let ctype = ChannelType::from_ofl_channel_name(color);
+    if !ctype.is_color() {
+        return Err(anyhow!("'{}' is not a color channel", color));
+    }
+
+    let limit = match args.get(3).copied() {
+        Some("off" | "none") => None,
+        _ => Some(parse_arg::<u8>(args, 3, "limit")?),
+    };
+
+    Ok(Command::ColorLimit {
+        channel,
+        ctype,
+        limit,
+    })
+}
+
+/// State the CLI keeps between commands
+#[derive(Default)]
+struct CliState {
+    /// DMX buffer captured by `mark`, compared against by `diff`
+    mark: Option<[u8; 513]>,
+    /// Show intensity levels as percent first, raw DMX second
+    percent_mode: bool,
+    /// Where the session being recorded will be saved
+    session_path: Option<String>,
+    /// Rehearsal clock shown by `status`
+    clock: ShowClock,
+}
+
+/// Fetch the current DMX buffer from the DMX thread
+fn query_dmx_state(command_tx: &std::sync::mpsc::Sender<UniverseCommand>) -> Result<[u8; 513]> {
+    let (response_tx, response_rx) = std::sync::mpsc::channel();
+
+    command_tx
+        .send(UniverseCommand::GetDMXState(response_tx))
+        .with_context(|| "Failed to get DMX state")?;
+
+    response_rx
+        .recv_timeout(std::time::Duration::from_millis(100))
+        .with_context(|| "Timeout receiving DMX state")
+}
+
+/// DMX addresses as a comma-separated list
+fn join_addresses(addresses: &[usize]) -> String {
+    addresses
+        .iter()
+        .map(|address| address.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Fetch the status of every universe the DMX thread drives
+fn query_universes(
+    command_tx: &std::sync::mpsc::Sender<UniverseCommand>,
+) -> Result<Vec<UniverseStatus>> {
+    let (response_tx, response_rx) = std::sync::mpsc::channel();
+
+    command_tx
+        .send(UniverseCommand::GetUniverses(response_tx))
+        .with_context(|| "Failed to send GetUniverses command")?;
+
+    response_rx
+        .recv_timeout(std::time::Duration::from_millis(100))
+        .with_context(|| "Timeout receiving universes")
+}
+
+/// Fetch the patch from the DMX thread
+fn query_fixtures(command_tx: &std::sync::mpsc::Sender<UniverseCommand>) -> Result<Vec<FixtureInfo>> {
+    let (response_tx, response_rx) = std::sync::mpsc::channel();
+
+    command_tx
+        .send(UniverseCommand::GetFixtures(response_tx))
+        .with_context(|| "Failed to send GetFixtures command")?;
+
+    response_rx
+        .recv_timeout(std::time::Duration::from_millis(100))
+        .with_context(|| "Timeout receiving fixtures")
+}
+
+/// Fetch the patch of the universe with this id
+fn query_universe_fixtures(
+    command_tx: &std::sync::mpsc::Sender<UniverseCommand>,
+    universe: u8,
+) -> Result<Vec<FixtureInfo>> {
+    let (response_tx, response_rx) = std::sync::mpsc::channel();
+
+    command_tx
+        .send(UniverseCommand::OnUniverse {
+            universe,
+            command: Box::new(UniverseCommand::GetFixtures(response_tx)),
+        })
+        .with_context(|| "Failed to send GetFixtures command")?;
+
+    response_rx
+        .recv_timeout(std::time::Duration::from_millis(100))
+        .with_context(|| format!("Timeout receiving fixtures on universe {}", universe))
+}
+
+/// Fetch the full patch of the universe with this id, as a show saves it
+fn query_universe_patch(
+    command_tx: &std::sync::mpsc::Sender<UniverseCommand>,
+    universe: u8,
+) -> Result<Vec<PatchedFixture>> {
+    let (response_tx, response_rx) = std::sync::mpsc::channel();
+
+    command_tx
+        .send(UniverseCommand::OnUniverse {
+            universe,
+            command: Box::new(UniverseCommand::GetPatch(response_tx)),
+        })
+        .with_context(|| "Failed to send GetPatch command")?;
+
+    response_rx
+        .recv_timeout(std::time::Duration::from_millis(100))
+        .with_context(|| format!("Timeout receiving patch on universe {}", universe))
+}
+
+/// Fetch capability details for each channel of a fixture
+fn query_capabilities(
+    command_tx: &std::sync::mpsc::Sender<UniverseCommand>,
+    fixture_channel: usize,
+) -> Result<Vec<ChannelCapabilities>> {
+    let (response_tx, response_rx) = std::sync::mpsc::channel();
+
+    command_tx
+        .send(UniverseCommand::GetFixtureCapabilities {
+            fixture_channel,
+            response: response_tx,
+        })
+        .with_context(|| "Failed to send GetFixtureCapabilities command")?;
+
+    response_rx
+        .recv_timeout(std::time::Duration::from_millis(100))
+        .with_context(|| "Timeout receiving fixture capabilities")?
+        .ok_or_else(|| anyhow!("No fixture found at channel {}", fixture_channel))
+}
+
+/// The channel that selects what `channel` does, for OFL switching channels
+fn switch_controller<'a>(
+    channels: &'a [ChannelCapabilities],
+    channel: &ChannelCapabilities,
+) -> Option<&'a ChannelCapabilities> {
+    let offset = channel.depends_on?;
+    channels.iter().find(|candidate| candidate.offset == offset)
+}
+
+/// Explain a live value: the angle for pan/tilt style ranges, otherwise the
+/// active capability when the channel has more than one or it is named
+fn describe_capability(channel: &ChannelCapabilities, value: u8) -> Option<String> {
+    let capability = channel
+        .capabilities
+        .iter()
+        .find(|capability| capability.contains(value))?;
+
+    if let Some(angle) = capability.angle_at(value) {
+        return Some(format!("{:.0}°", angle));
+    }
+
+    let named = capability.comment.is_some()
+        || capability.color.is_some()
+        || capability.colors.is_some();
+    (channel.capabilities.len() > 1 || named).then(|| capability.label())
+}
+
+/// How long an address must sit at full before `health` lists it, by default
+const DEFAULT_PINNED_TIME: Duration = Duration::from_secs(10 * 60);
+
+/// Manufacturers listed before truncating, when no prefix is given
+const MANUFACTURER_LIST_LIMIT: usize = 20;
+
+/// Find the fixture and channel type a DMX address drives
+fn fixture_at(fixtures: &[FixtureInfo], address: usize) -> Option<(&FixtureInfo, &str)> {
+    fixtures
+        .iter()
+        .find_map(|fixture| fixture.channel_at(address).map(|channel_type| (fixture, channel_type)))
+}
+
+/// Describe which fixture and channel type a DMX address drives
+fn describe_address(fixtures: &[FixtureInfo], address: usize) -> String {
+    fixture_at(fixtures, address)
+        .map(|(fixture, channel_type)| {
+            format!("{} ({}) {}", fixture.channel, fixture.label, channel_type)
+        })
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Format a DMX value, adding percent for intensity channels since it's meaningless elsewhere
+fn format_level(value: u8, channel_type: Option<&str>, percent_mode: bool) -> String {
+    match channel_type {
+        Some("Intensity" | "Dimmer") if percent_mode => {
+            format!("{}% ({})", to_percent(value), value)
+        }
+        Some("Intensity" | "Dimmer") => format!("{} ({}%)", value, to_percent(value)),
+        _ => value.to_string(),
+    }
+}
+
+/// CLI that uses command channels instead of direct universe access
+pub fn run_cli(
+    command_tx: std::sync::mpsc::Sender<crate::universe::UniverseCommand>,
+    show: &Mutex<CueEngine>,
+    registry: &mut FixtureRegistry,
+    percent_mode: bool,
+) {
+    println!("DMX Controller CLI - Command Mode");
+    println!("Commands:");
+    println!("  c <num> @ <intensity>         - Set fixture intensity");
+    println!("  c <num> rgb <r> <g> <b>       - Set fixture RGB color");
+    println!("  c <num> @ <int> rgb <r> <g> <b> - Set intensity and color together");
+    println!("  c <num> cct <kelvin>          - Set a white point, e.g. cct 3200");
+    println!("  c <num> hsv <h> <s> <v>       - Set color by hue, saturation and value");
+    println!("  c <num> strobe <0-255>        - Set the strobe rate");
+    println!("  c <num> pos <pan> <tilt>      - Aim a moving light");
+    println!("  c <num> gobo <slot>           - Put the gobo wheel on a slot, counting from 1");
+    println!("  c <num> \"<OFL name>\" @ <value> - Set a channel by its OFL name");
+    println!("  c <num> dimmer#2 @ <value>    - Set one of a repeated function, counting from 1");
+    println!("  c \"<label>\" @ <intensity>     - Address a fixture by its label instead");
+    println!("  c 1 thru 8 + 10 @ <intensity> - Address a group of channels");
+    println!("  group <name> = 1 thru 8 + 10  - Name a group; group alone lists them");
+    println!("  g <name> @ <int> / rgb ...    - Control a named group; ungroup <name> deletes");
+    println!("  a <addr> @ <value>            - Set DMX address directly");
+    println!("  a <univ>.<addr> @ <value>     - Same, on a given universe (2.15 or 2/15)");
+    println!("  <univ>/c <num> ...            - Run a fixture command on a given universe");
+    println!("  get <fixture>                 - List channels for fixture");
+    println!("  report <fixture>              - Show live values by function");
+    println!("  capabilities <fixture>        - List what each channel can do");
+    println!("  calibrate <fixture>           - Show percent-to-DMX output table");
+    println!("  query <address>               - Get current DMX value");
+    println!("  blackout                      - Turn off all fixtures");
+    println!("  blackout latch|release / panic - Hold everything dark until released");
+    println!("  manual clear                  - Drop hand-set levels so cues alone drive output");
+    println!("  patch                         - List patched fixtures");
+    println!("  manufacturers [prefix]        - List fixture manufacturers");
+    println!("  search <term> [names]         - Find fixtures by name, short name or category");
+    println!("  checklib                      - Check every fixture file parses");
+    println!("  compare <mfr/fix> <mfr/fix>   - Show how two fixture definitions differ");
+    println!("  rdm <uid>                     - Name the manufacturer of an RDM UID");
+    println!("  freeaddr <footprint>          - Suggest a free DMX address");
+    println!("  park <addr> @ <value> / unpark <addr> - Hold an address whatever else happens");
+    println!("  mute/unmute <channel>         - Hold a fixture dark without unpatching");
+    println!("  solo <channel> / solo clear   - Show only soloed fixtures");
+    println!("  copy <num> to <num> [+ <num>] - Copy a fixture's current values once");
+    println!("  mirror <num> = <src> / unmirror <num> - Make a fixture copy another live");
+    println!("  raw <num> <b0> <b1> ...       - Write raw bytes across a fixture's slots");
+    println!("  colorlimit <num> <color> <n>  - Never drive a color channel above n");
+    println!("  fine <num> <type> <0-65535>   - Set pan, tilt or dimmer at 16-bit resolution");
+    println!("  curve <num> linear|square|invsquare|s - Set a fixture's dimmer curve");
+    println!("  all <type> <value>            - Set a function on every fixture");
+    println!("  category <name> [<type> <v>]  - List or set fixtures by OFL category");
+    println!("  neutral color|position|home   - Reset color, center movers or go home");
+    println!("  sethome <num>|all [color]     - Make current pan/tilt the new home");
+    println!("  rename <old> <new>            - Rename or renumber a cue");
+    println!("  update                        - Save touched channels into the current cue");
+    println!("  loadcue <cue>                 - Load a cue for editing; update saves it");
+    println!("  preview <cue>                 - Show a cue's levels without playing it");
+    println!("  validate                      - Find cue levels on unpatched addresses");
+    println!("  cuesheet <file>               - Write a printable cue sheet");
+    println!("  save/load <file>              - Save or load the patch and every cue");
+    println!("  rc <cue> <ms> [from <playback>] - Record a cue from live or one playback");
+    println!("  rc <cue> follow               - Record a cue that takes the previous cue's time");
+    println!("  goto <cue> [at <percent>]     - Jump to a cue, optionally dimmed");
+    println!("  go/back [playback]            - Step a playback (default main)");
+    println!("  go [playback] <seconds>       - Go once with a different fade time");
+    println!("  fader [playback] <level>      - Scale the live cue's intensity");
+    println!("  playback [name]               - Select or list playbacks");
+    println!("  trace <addr> | trace c <num>  - List cues that use an address or fixture");
+    println!("  stats                         - Show achieved DMX frame rate");
+    println!("  health [minutes]              - List addresses stuck at full");
+    println!("  rate dmx <hz>                 - Change the DMX refresh rate live");
+    println!("  status                        - Show whether each universe is transmitting");
+    println!("  output <universe> on|off      - Stop or resume sending a universe");
+    println!("  priority [universe] <level>   - Set the sACN priority (0-200)");
+    println!("  record session <path> / stop  - Record every command with timing");
+    println!("  play session <path>           - Replay a recorded session");
+    println!("  mark / diff                   - Snapshot output / show changes since");
+    println!("  clock start|stop|reset        - Run the show clock shown in status");
+    println!("  quit/exit                     - Exit program");
+    println!("  help                          - Show this help");
+    println!();
+
+    let mut state = CliState {
+        percent_mode,
+        ..Default::default()
+    };
+
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input).is_err() {
+            println!("Failed to read input");
+            continue;
+        }
+
+        let tokens = match tokenize(&input) {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                println!("Error: {}", e);
+                continue;
+            }
+        };
+        let args: Vec<&str> = tokens.iter().map(String::as_str).collect();
+        if args.is_empty() {
+            continue;
+        }
+
+        // Check for quit commands first
+        if matches!(args[0], "quit" | "exit" | "q") {
+            break;
+        }
+
+        let command = parse_command(&args);
+
+        let mut engine = show.lock().unwrap();
+        match execute_command(&command, &command_tx, &mut engine, registry, &mut state) {
+            Ok(should_quit) => {
+                if should_quit {
+                    break;
+                }
+            }
+            Err(err) => {
+                println!("Error: {}", err);
+            }
+        }
+    }
+
+    println!("CLI exiting...");
+}
+
+fn execute_command(
+    command: &Command,
+    command_tx: &std::sync::mpsc::Sender<crate::universe::UniverseCommand>,
+    show: &mut CueEngine,
+    registry: &mut FixtureRegistry,
+    state: &mut CliState,
+) -> Result<bool> {
+    match command {
+        Command::OnUniverse { universe, command } => {
+            let universes = query_universes(command_tx)?;
+            if !universes.iter().any(|status| status.id == *universe) {
+                let ids: Vec<usize> = universes.iter().map(|status| status.id.into()).collect();
+                return Err(anyhow!(
+                    "No universe {}; this board drives universe {}",
+                    universe,
+                    join_addresses(&ids)
+                ));
+            }
+
+            // Everything the inner command sends is wrapped for the target
+            // universe on the way through; replies come straight back on
+            // their own channels
+            let (routed_tx, routed_rx) = std::sync::mpsc::channel();
+            let forward_tx = command_tx.clone();
+            let target = *universe;
+            let forwarder = std::thread::spawn(move || {
+                for command in routed_rx {
+                    let routed = UniverseCommand::OnUniverse {
+                        universe: target,
+                        command: Box::new(command),
+                    };
+                    if forward_tx.send(routed).is_err() {
+                        break;
+                    }
                 }
-                ChannelAction::Rgb(r, g, b) => {
+            });
+
+            let result = execute_command(command, &routed_tx, show, registry, state);
+            drop(routed_tx);
+            forwarder.join().ok();
+            result
+        }
+        Command::Channel { fixture, action } => {
+            let channels = resolve_fixture_ref(fixture, show, command_tx)?;
+            for &channel in &channels {
+                if action.intensity.is_some() || action.color.is_some() {
                     command_tx
                         .send(UniverseCommand::SetFixture {
-                            fixture_channel: *channel,
-                            intensity: None,
-                            color: Some((*r, *g, *b)),
+                            fixture_channel: channel,
+                            intensity: action.intensity,
+                            color: action.color,
+                        })
+                        .with_context(|| "Failed to send fixture command")?;
+                }
+                if let Some(kelvin) = action.kelvin {
+                    command_tx
+                        .send(UniverseCommand::SetColorTemperature {
+                            fixture_channel: channel,
+                            kelvin,
+                        })
+                        .with_context(|| "Failed to send fixture command")?;
+                }
+                if let Some((hue, saturation, value)) = action.hsv {
+                    command_tx
+                        .send(UniverseCommand::SetHsv {
+                            fixture_channel: channel,
+                            hue,
+                            saturation,
+                            value,
+                        })
+                        .with_context(|| "Failed to send fixture command")?;
+                }
+                if let Some(rate) = action.strobe {
+                    command_tx
+                        .send(UniverseCommand::SetStrobe {
+                            fixture_channel: channel,
+                            rate,
+                        })
+                        .with_context(|| "Failed to send fixture command")?;
+                }
+                if let Some((pan, tilt)) = action.position {
+                    command_tx
+                        .send(UniverseCommand::SetPosition {
+                            fixture_channel: channel,
+                            pan,
+                            tilt,
+                        })
+                        .with_context(|| "Failed to send fixture command")?;
+                }
+                if let Some(slot) = action.gobo {
+                    command_tx
+                        .send(UniverseCommand::SelectGobo {
+                            fixture_channel: channel,
+                            slot,
                         })
                         .with_context(|| "Failed to send fixture command")?;
-                    println!("Set channel {} RGB to ({}, {}, {})", channel, r, g, b);
                 }
             }
+
+            let channels = join_addresses(&channels);
+            if let Some(intensity) = action.intensity {
+                println!("Set channel {} intensity to {}", channels, intensity);
+            }
+            if let Some((r, g, b)) = action.color {
+                println!("Set channel {} RGB to ({}, {}, {})", channels, r, g, b);
+            }
+            if let Some(kelvin) = action.kelvin {
+                let clamped = kelvin.clamp(MIN_KELVIN, MAX_KELVIN);
+                if clamped != kelvin {
+                    println!(
+                        "Warning: {}K is outside {}-{}K, using {}K",
+                        kelvin, MIN_KELVIN, MAX_KELVIN, clamped
+                    );
+                }
+                println!("Set channel {} color temperature to {}K", channels, clamped);
+            }
+            if let Some((h, s, v)) = action.hsv {
+                println!("Set channel {} HSV to ({}, {}, {})", channels, h, s, v);
+            }
+            if let Some(rate) = action.strobe {
+                println!("Set channel {} strobe to {}", channels, rate);
+            }
+            if let Some((pan, tilt)) = action.position {
+                println!("Set channel {} pan {} tilt {}", channels, pan, tilt);
+            }
+            if let Some(slot) = action.gobo {
+                println!("Set channel {} gobo to slot {}", channels, slot);
+            }
+            Ok(false)
+        }
+        Command::NamedChannel {
+            fixture,
+            name,
+            value,
+        } => {
+            let channels = resolve_fixture_ref(fixture, show, command_tx)?;
+            for &channel in &channels {
+                command_tx
+                    .send(UniverseCommand::SetChannelByName {
+                        fixture_channel: channel,
+                        name: name.clone(),
+                        value: *value,
+                    })
+                    .with_context(|| "Failed to send fixture command")?;
+            }
+            println!(
+                "Set channel {} '{}' to {}",
+                join_addresses(&channels),
+                name,
+                value
+            );
+
+            Ok(false)
+        }
+        Command::IndexedChannel {
+            fixture,
+            ctype,
+            instance,
+            value,
+        } => {
+            let channels = resolve_fixture_ref(fixture, show, command_tx)?;
+            for &channel in &channels {
+                command_tx
+                    .send(UniverseCommand::SetFixtureIndexed {
+                        fixture_channel: channel,
+                        ctype: ctype.clone(),
+                        instance: *instance,
+                        value: *value,
+                    })
+                    .with_context(|| "Failed to send fixture command")?;
+            }
+            println!(
+                "Set channel {} {:?} #{} to {}",
+                join_addresses(&channels),
+                ctype,
+                instance + 1,
+                value
+            );
+
             Ok(false)
         }
         Command::Address { address, value } => {
@@ -241,6 +1646,25 @@ fn execute_command(
 
             Ok(false)
         }
+        Command::Park { address, value } => {
+            command_tx
+                .send(UniverseCommand::Park {
+                    address: *address,
+                    value: *value,
+                })
+                .with_context(|| "Failed to send park command")?;
+            println!("Parked DMX address {} at {}", address, value);
+
+            Ok(false)
+        }
+        Command::Unpark(address) => {
+            command_tx
+                .send(UniverseCommand::Unpark { address: *address })
+                .with_context(|| "Failed to send unpark command")?;
+            println!("Unparked DMX address {}", address);
+
+            Ok(false)
+        }
         Command::Blackout => {
             command_tx
                 .send(UniverseCommand::Blackout)
@@ -249,6 +1673,37 @@ fn execute_command(
 
             Ok(false)
         }
+        Command::ClearManual => {
+            command_tx
+                .send(UniverseCommand::ClearManual)
+                .with_context(|| "Failed to send clear manual command")?;
+            println!("Hand-set levels cleared; playbacks have the output");
+
+            Ok(false)
+        }
+        Command::BlackoutLatch(latched) => {
+            command_tx
+                .send(UniverseCommand::SetBlackoutLatch(*latched))
+                .with_context(|| "Failed to send blackout command")?;
+            if *latched {
+                println!("Blackout latched; 'blackout release' to bring levels back");
+            } else {
+                println!("Blackout released");
+            }
+
+            Ok(false)
+        }
+        Command::Panic => {
+            command_tx
+                .send(UniverseCommand::Blackout)
+                .with_context(|| "Failed to send blackout command")?;
+            command_tx
+                .send(UniverseCommand::SetBlackoutLatch(true))
+                .with_context(|| "Failed to send blackout command")?;
+            println!("Panic: everything out and blackout latched; 'blackout release' to resume");
+
+            Ok(false)
+        }
         Command::GetChannels(fixture_channel) => {
             let (response_tx, response_rx) = std::sync::mpsc::channel();
 
@@ -259,15 +1714,35 @@ fn execute_command(
                 })
                 .with_context(|| "Failed to send GetChannels command")?;
 
-            use std::time::Duration;
             match response_rx.recv_timeout(Duration::from_millis(100)) {
                 Ok(Some(channels)) => {
                     println!("Fixture {} channels:", fixture_channel);
                     println!("  Type            DMX Addr  Offset");
                     println!("  --------------- --------- ------");
-                    for (channel_type, dmx_address, offset) in channels {
+                    for (channel_type, dmx_address, offset) in &channels {
                         println!("  {:15} {:9} {:6}", channel_type, dmx_address, offset);
                     }
+
+                    let capabilities = query_capabilities(command_tx, *fixture_channel)?;
+                    for channel in &capabilities {
+                        let Some(controller) = switch_controller(&capabilities, channel) else {
+                            continue;
+                        };
+                        let address_of = |offset: u8| {
+                            channels
+                                .iter()
+                                .find(|(_, _, o)| *o == offset as usize)
+                                .map(|(_, address, _)| *address)
+                                .unwrap_or_default()
+                        };
+                        println!(
+                            "  Note: {} at {} changes function with {} at {}",
+                            channel.channel_type,
+                            address_of(channel.offset),
+                            controller.channel_type,
+                            address_of(controller.offset)
+                        );
+                    }
                 }
                 Ok(None) => {
                     println!("No fixture found at channel {}", fixture_channel);
@@ -278,35 +1753,1177 @@ fn execute_command(
             }
             Ok(false)
         }
-        Command::Go => {
-            show.go()?;
+        Command::Calibrate(fixture_channel) => {
+            let (response_tx, response_rx) = std::sync::mpsc::channel();
+            command_tx
+                .send(UniverseCommand::GetCalibration {
+                    fixture_channel: *fixture_channel,
+                    response: response_tx,
+                })
+                .with_context(|| "Failed to send GetCalibration command")?;
+
+            let table = response_rx
+                .recv_timeout(std::time::Duration::from_millis(100))
+                .with_context(|| "Timeout receiving calibration")??;
 
+            println!("Fixture {} intensity:", fixture_channel);
+            println!("  Percent  Requested  Output");
+            for point in table {
+                println!(
+                    "  {:>6}%  {:>9}  {:>6}",
+                    point.percent, point.requested, point.output
+                );
+            }
             Ok(false)
         }
-        Command::Back => {
-            show.back()?;
+        Command::Report(fixture_channel) => {
+            let fixtures = query_fixtures(command_tx)?;
+            let fixture = fixtures
+                .iter()
+                .find(|fixture| fixture.channel == *fixture_channel)
+                .ok_or_else(|| anyhow!("No fixture found at channel {}", fixture_channel))?;
+            let channels = query_capabilities(command_tx, *fixture_channel)?;
+            let dmx_state = query_dmx_state(command_tx)?;
+
+            println!(
+                "Fixture {} ({}) - {}{}",
+                fixture.channel,
+                fixture.label,
+                fixture.profile,
+                if fixture.muted { " [muted]" } else { "" }
+            );
+            println!("  Function        Addr Value");
+            println!("  --------------- ---- -----");
+            for channel in &channels {
+                let address = fixture.buffer_index(channel.offset);
+                let value = dmx_state[address];
+                let level = format_level(value, Some(&channel.channel_type), state.percent_mode);
+                let detail = describe_capability(channel, value)
+                    .map(|detail| format!(" ({})", detail))
+                    .unwrap_or_default();
+                println!(
+                    "  {:15} {:4} {}{}",
+                    channel.channel_type, address, level, detail
+                );
+                if let Some(controller) = switch_controller(&channels, channel) {
+                    println!(
+                        "    function depends on {} at address {}",
+                        controller.channel_type,
+                        fixture.buffer_index(controller.offset)
+                    );
+                }
+            }
 
             Ok(false)
         }
-        Command::RecordCue { name, time_in_ms } => {
-            show.record_cue(name, *time_in_ms as u64)?;
+        Command::Capabilities(fixture_channel) => {
+            let fixtures = query_fixtures(command_tx)?;
+            let fixture = fixtures
+                .iter()
+                .find(|fixture| fixture.channel == *fixture_channel)
+                .ok_or_else(|| anyhow!("No fixture found at channel {}", fixture_channel))?;
+            let channels = query_capabilities(command_tx, *fixture_channel)?;
+
+            println!(
+                "Fixture {} ({}) - {}",
+                fixture.channel, fixture.label, fixture.profile
+            );
+            for channel in &channels {
+                println!(
+                    "  {} (address {})",
+                    channel.channel_type,
+                    fixture.buffer_index(channel.offset)
+                );
+                if channel.capabilities.is_empty() {
+                    println!("    0-255    {}", channel.channel_type);
+                }
+                for capability in &channel.capabilities {
+                    let (start, end) = capability.range();
+                    let label = capability.label();
+                    let detail = if label == capability.capability_type {
+                        label
+                    } else {
+                        format!("{}: {}", capability.capability_type, label)
+                    };
+                    println!("    {:<8} {}", format!("{}-{}", start, end), detail);
+                }
+                if let Some(controller) = switch_controller(&channels, channel) {
+                    println!(
+                        "    function depends on {} at address {}",
+                        controller.channel_type,
+                        fixture.buffer_index(controller.offset)
+                    );
+                }
+            }
 
             Ok(false)
         }
-        Command::DeleteCue(name) => {
-            show.delete_cue(&name)?;
+        Command::Query(address) => {
+            let (response_tx, response_rx) = std::sync::mpsc::channel();
+
+            command_tx
+                .send(UniverseCommand::GetChannelValue {
+                    channel: *address,
+                    response: response_tx,
+                })
+                .with_context(|| "Failed to send GetChannelValue command")?;
+
+            let value = response_rx
+                .recv_timeout(std::time::Duration::from_millis(100))
+                .with_context(|| format!("Query timeout for address {}", address))?;
+            let fixtures = query_fixtures(command_tx)?;
+            let channel_type = fixture_at(&fixtures, *address).map(|(_, ctype)| ctype);
+
+            println!(
+                "ch {} = {}  {}",
+                address,
+                format_level(value, channel_type, state.percent_mode),
+                describe_address(&fixtures, *address)
+            );
+            Ok(false)
+        }
+        Command::Patch => {
+            let fixtures = query_fixtures(command_tx)?;
+            if fixtures.is_empty() {
+                println!("No fixtures patched");
+                return Ok(false);
+            }
+
+            println!("  Chan Addr Label                Profile");
+            println!("  ---- ---- -------------------- -------");
+            for fixture in fixtures {
+                println!(
+                    "  {:4} {:4} {:20} {}{}",
+                    fixture.channel,
+                    fixture.dmx_start,
+                    fixture.label,
+                    fixture.profile,
+                    if fixture.muted { " [muted]" } else { "" }
+                );
+            }
+            Ok(false)
+        }
+        Command::CheckLibrary => {
+            let check = registry.check_all()?;
+            for (path, error) in &check.failures {
+                println!("  {}: {}", path.display(), error);
+            }
+            println!("{} fixtures OK, {} failed", check.ok, check.failures.len());
+
+            Ok(false)
+        }
+        Command::Search { term, name_only } => {
+            let results = registry.search_fixtures(term, *name_only)?;
+            if results.is_empty() {
+                println!("No fixtures match \"{}\"", term);
+            }
+            for result in results {
+                let why = match result.field {
+                    MatchField::Name => String::new(),
+                    MatchField::ShortName => format!("  (short name: {})", result.matched),
+                    MatchField::Category => format!("  (category: {})", result.matched),
+                };
+                println!("  {}/{}{}", result.manufacturer, result.fixture, why);
+            }
+
+            Ok(false)
+        }
+        Command::Rdm(id) => {
+            match registry.manufacturer_by_rdm_id(*id) {
+                Some(manufacturer) => {
+                    println!("RDM manufacturer {:04X} is {}", id, manufacturer.name)
+                }
+                None => println!("RDM manufacturer {:04X} is not in the library", id),
+            }
+            Ok(false)
+        }
+        Command::Compare { a, b } => {
+            let diff = registry.compare_fixtures((&a.0, &a.1), (&b.0, &b.1))?;
+            let a = format!("{}/{}", a.0, a.1);
+            let b = format!("{}/{}", b.0, b.1);
+            if diff.is_empty() {
+                println!("{} and {} have the same modes and channels", a, b);
+                return Ok(false);
+            }
+
+            let list = |names: &[String]| names.join(", ");
+            if !diff.modes_only_in_a.is_empty() {
+                println!("Modes only in {}: {}", a, list(&diff.modes_only_in_a));
+            }
+            if !diff.modes_only_in_b.is_empty() {
+                println!("Modes only in {}: {}", b, list(&diff.modes_only_in_b));
+            }
+            for mode in &diff.changed_modes {
+                println!("Mode '{}' has different channels:", mode.mode);
+                println!("  {}: {}", a, list(&mode.a_channels));
+                println!("  {}: {}", b, list(&mode.b_channels));
+            }
+            if !diff.channels_only_in_a.is_empty() {
+                println!("Channels only in {}: {}", a, list(&diff.channels_only_in_a));
+            }
+            if !diff.channels_only_in_b.is_empty() {
+                println!("Channels only in {}: {}", b, list(&diff.channels_only_in_b));
+            }
+            if !diff.changed_channels.is_empty() {
+                println!(
+                    "Channels with different capabilities: {}",
+                    list(&diff.changed_channels)
+                );
+            }
+
+            Ok(false)
+        }
+        Command::Manufacturers(prefix) => {
+            let manufacturers = registry
+                .get_manufacturers()
+                .ok_or_else(|| anyhow!("No manufacturer database loaded"))?;
+
+            let prefix = prefix.as_deref().map(str::to_lowercase);
+            let mut matches: Vec<_> = manufacturers
+                .iter()
+                .filter(|(key, manufacturer)| {
+                    prefix.as_deref().is_none_or(|prefix| {
+                        key.starts_with(prefix)
+                            || manufacturer.name.to_lowercase().starts_with(prefix)
+                    })
+                })
+                .collect();
+            matches.sort_by_key(|(key, _)| key.as_str());
+
+            if matches.is_empty() {
+                println!("No manufacturers match");
+                return Ok(false);
+            }
+
+            // A prefix is an explicit filter, so show everything it matched
+            let shown = if prefix.is_some() {
+                matches.len()
+            } else {
+                MANUFACTURER_LIST_LIMIT
+            };
+            for (key, manufacturer) in matches.iter().take(shown) {
+                let count = registry.get_fixtures_for_manufacturer(key)?.len();
+                println!("  {:30} {:25} {} fixtures", manufacturer.name, key, count);
+            }
+            if matches.len() > shown {
+                println!(
+                    "  ...and {} more (use 'manufacturers <prefix>' to filter)",
+                    matches.len() - shown
+                );
+            }
+
+            Ok(false)
+        }
+        Command::FreeAddress(footprint) => {
+            let (response_tx, response_rx) = std::sync::mpsc::channel();
+            command_tx
+                .send(UniverseCommand::GetFreeAddresses {
+                    footprint: *footprint,
+                    response: response_tx,
+                })
+                .with_context(|| "Failed to send GetFreeAddresses command")?;
+
+            let (ranges, suggestion) = response_rx
+                .recv_timeout(std::time::Duration::from_millis(100))
+                .with_context(|| "Timeout receiving free addresses")?;
+
+            println!("Free addresses:");
+            for (first, last) in &ranges {
+                println!("  {:3}-{:3} ({} channels)", first, last, last - first + 1);
+            }
+            match suggestion {
+                Some(address) => println!(
+                    "Next free address for a {}-channel fixture: {}",
+                    footprint, address
+                ),
+                None => println!("No gap fits a {}-channel fixture", footprint),
+            }
+
+            Ok(false)
+        }
+        Command::Raw { channel, values } => {
+            command_tx
+                .send(UniverseCommand::SetFixtureRaw {
+                    fixture_channel: *channel,
+                    values: values.clone(),
+                })
+                .with_context(|| "Failed to send raw command")?;
+            println!("Set channel {} raw to {:?}", channel, values);
+
+            Ok(false)
+        }
+        Command::All(values) => {
+            let fixtures = query_fixtures(command_tx)?;
+            for (ctype, value) in values {
+                command_tx
+                    .send(UniverseCommand::SetAllFixtureValue {
+                        ctype: ctype.clone(),
+                        value: *value,
+                    })
+                    .with_context(|| "Failed to send all-fixtures command")?;
+            }
+
+            // Fixtures with at least one of the functions; muted ones ignore the change
+            let names: Vec<String> = values
+                .iter()
+                .map(|(ctype, _)| format!("{:?}", ctype))
+                .collect();
+            let affected = fixtures
+                .iter()
+                .filter(|fixture| !fixture.muted)
+                .filter(|fixture| {
+                    fixture
+                        .channels
+                        .iter()
+                        .any(|(channel_type, _)| names.contains(channel_type))
+                })
+                .count();
+            println!("Set {} on {} fixtures", names.join("/"), affected);
+
+            Ok(false)
+        }
+        Command::Neutral(target) => {
+            let (response_tx, response_rx) = std::sync::mpsc::channel();
+            command_tx
+                .send(UniverseCommand::Neutralize {
+                    target: *target,
+                    response: response_tx,
+                })
+                .with_context(|| "Failed to send neutral command")?;
+
+            let count = response_rx
+                .recv_timeout(std::time::Duration::from_millis(100))
+                .with_context(|| "Timeout receiving neutral result")??;
+            let what = match target {
+                Neutral::Color { .. } => "color",
+                Neutral::Position => "position",
+                Neutral::Home => "home position",
+            };
+            println!("Reset {} on {} fixtures", what, count);
+
+            Ok(false)
+        }
+        Command::SetHome {
+            channel,
+            with_color,
+        } => {
+            let (response_tx, response_rx) = std::sync::mpsc::channel();
+            command_tx
+                .send(UniverseCommand::SetHome {
+                    fixture_channel: *channel,
+                    with_color: *with_color,
+                    response: response_tx,
+                })
+                .with_context(|| "Failed to send sethome command")?;
+
+            let stored = response_rx
+                .recv_timeout(std::time::Duration::from_millis(100))
+                .with_context(|| "Timeout receiving sethome result")??;
+            let whose = match channel {
+                Some(channel) => format!("fixture {}", channel),
+                None => "every fixture".to_string(),
+            };
+            println!(
+                "Stored {} channels as home for {}; 'neutral home' returns there",
+                stored, whose
+            );
+
+            Ok(false)
+        }
+        Command::Category { category, values } => {
+            let members: Vec<FixtureInfo> = query_fixtures(command_tx)?
+                .into_iter()
+                .filter(|fixture| {
+                    fixture
+                        .categories
+                        .iter()
+                        .any(|c| c.eq_ignore_ascii_case(category))
+                })
+                .collect();
+
+            match values {
+                Some(values) => {
+                    command_tx
+                        .send(UniverseCommand::SetCategoryValues {
+                            category: category.clone(),
+                            values: values.clone(),
+                        })
+                        .with_context(|| "Failed to send category command")?;
+                    println!("Set {} fixtures in \"{}\"", members.len(), category);
+                }
+                None if members.is_empty() => println!("No fixtures in \"{}\"", category),
+                None => {
+                    for fixture in members {
+                        println!(
+                            "  {:>3}  {}  ({})",
+                            fixture.channel, fixture.label, fixture.profile
+                        );
+                    }
+                }
+            }
+
+            Ok(false)
+        }
+        Command::ColorLimit {
+            channel,
+            ctype,
+            limit,
+        } => {
+            command_tx
+                .send(UniverseCommand::SetColorLimit {
+                    fixture_channel: *channel,
+                    ctype: ctype.clone(),
+                    limit: *limit,
+                })
+                .with_context(|| "Failed to send color limit command")?;
+            match limit {
+                Some(limit) => println!("Limited channel {} {:?} to {}", channel, ctype, limit),
+                None => println!("Removed channel {} {:?} limit", channel, ctype),
+            }
+
+            Ok(false)
+        }
+        Command::Curve { channel, curve } => {
+            command_tx
+                .send(UniverseCommand::SetDimmerCurve {
+                    fixture_channel: *channel,
+                    curve: *curve,
+                })
+                .with_context(|| "Failed to send curve command")?;
+            println!("Channel {} now dims with a {:?} curve", channel, curve);
+
+            Ok(false)
+        }
+        Command::Fine {
+            channel,
+            ctype,
+            value,
+        } => {
+            command_tx
+                .send(UniverseCommand::SetFixture16Bit {
+                    fixture_channel: *channel,
+                    ctype: ctype.clone(),
+                    value: *value,
+                })
+                .with_context(|| "Failed to send fixture command")?;
+            println!("Set channel {} {:?} to {} of 65535", channel, ctype, value);
+
+            Ok(false)
+        }
+        Command::Solo(Some(channel)) => {
+            command_tx
+                .send(UniverseCommand::AddSolo(*channel))
+                .with_context(|| "Failed to send solo command")?;
+            println!("Soloed channel {}", channel);
+
+            Ok(false)
+        }
+        Command::Solo(None) => {
+            command_tx
+                .send(UniverseCommand::ClearSolo)
+                .with_context(|| "Failed to send solo command")?;
+            println!("Solo cleared");
+
+            Ok(false)
+        }
+        Command::Copy { source, targets } => {
+            if targets.contains(source) {
+                return Err(anyhow!("Fixture {} can't be copied onto itself", source));
+            }
+            let fixtures = query_fixtures(command_tx)?;
+            let find = |channel: &usize| {
+                fixtures
+                    .iter()
+                    .find(|fixture| fixture.channel == *channel)
+                    .ok_or_else(|| anyhow!("No fixture found at channel {}", channel))
+            };
+            let from = find(source)?;
+            let targets = targets.iter().map(find).collect::<Result<Vec<_>>>()?;
+            let dmx_state = query_dmx_state(command_tx)?;
+
+            let mut changes = Vec::new();
+            for target in targets {
+                let writes = from.copy_to(target, &dmx_state);
+                println!(
+                    "Copied {} channels from fixture {} to {}",
+                    writes.len(),
+                    from.channel,
+                    target.channel
+                );
+                changes.extend(writes);
+            }
+            command_tx
+                .send(UniverseCommand::SetMultiple { changes })
+                .with_context(|| "Failed to send copy command")?;
+
+            Ok(false)
+        }
+        Command::Mirror { channel, source } => {
+            command_tx
+                .send(UniverseCommand::SetMirror {
+                    fixture_channel: *channel,
+                    source: *source,
+                })
+                .with_context(|| "Failed to send mirror command")?;
+            match source {
+                Some(source) => println!("Channel {} now mirrors channel {}", channel, source),
+                None => println!("Channel {} no longer mirrors", channel),
+            }
+
+            Ok(false)
+        }
+        Command::Mute { channel, muted } => {
+            command_tx
+                .send(UniverseCommand::SetMuted {
+                    fixture_channel: *channel,
+                    muted: *muted,
+                })
+                .with_context(|| "Failed to send mute command")?;
+            if *muted {
+                println!("Muted channel {}", channel);
+            } else {
+                println!("Unmuted channel {}", channel);
+            }
+
+            Ok(false)
+        }
+        Command::Go { playback, fade } => {
+            show.go_in(playback, *fade)?;
+
+            Ok(false)
+        }
+        Command::Back(playback) => {
+            show.back(playback)?;
+
+            Ok(false)
+        }
+        Command::Fader { playback, level } => {
+            let cue = show.set_level(playback, *level)?;
+            println!(
+                "Cue {} on {} at {}",
+                cue.name(),
+                playback,
+                format_level(*level, Some("Intensity"), state.percent_mode)
+            );
+
+            Ok(false)
+        }
+        Command::DefineGroup { name, channels } => {
+            let verb = match show.define_group(name, channels.clone())? {
+                Some(_) => "Redefined",
+                None => "Defined",
+            };
+            println!(
+                "{} group {} as channels {}",
+                verb,
+                name,
+                join_addresses(channels)
+            );
+
+            Ok(false)
+        }
+        Command::DeleteGroup(name) => {
+            show.delete_group(name)?;
+            println!("Deleted group {}", name);
+
+            Ok(false)
+        }
+        Command::ListGroups => {
+            if show.groups().is_empty() {
+                println!("No groups defined");
+            }
+            for (name, channels) in show.groups() {
+                println!("{}: channels {}", name, join_addresses(channels));
+            }
+
+            Ok(false)
+        }
+        Command::Playback(Some(name)) => {
+            show.select_playback(name);
+            println!("Editing cues on playback {}", name);
+
+            Ok(false)
+        }
+        Command::Playback(None) => {
+            for (name, playback) in show.playbacks() {
+                let marker = if name == show.selected_playback() { "*" } else { " " };
+                let current = playback.current_cue().map_or("-", |cue| cue.name());
+                println!("{} {}: current cue {}", marker, name, current);
+            }
+
+            Ok(false)
+        }
+        Command::GoToCue {
+            name,
+            intensity_percent,
+        } => {
+            show.go_to_cue(name, *intensity_percent)?;
+
+            Ok(false)
+        }
+        Command::RecordCue {
+            name,
+            time_in,
+            source,
+        } => {
+            show.record_cue(name, *time_in, source)?;
+            if let RecordSource::Playback(playback) = source {
+                println!("Recorded cue {} from playback {}", name, playback);
+            }
+
+            Ok(false)
+        }
+        Command::DeleteCue(name) => {
+            show.delete_cue(&name)?;
+
+            Ok(false)
+        }
+        Command::RenameCue { old, new } => {
+            show.rename_cue(old, new)?;
+            println!("Renamed cue {} to {}", old, new);
+
+            Ok(false)
+        }
+        Command::UpdateCue if show.editing_cue().is_some() => {
+            let (name, written) = show.save_editing_cue()?;
+            println!("Saved {} edited channels into cue {}", written, name);
+
+            Ok(false)
+        }
+        Command::LoadCue(name) => {
+            show.load_cue(name)?;
+            println!("Editing cue {}; use update to save", name);
+
+            Ok(false)
+        }
+        Command::UpdateCue => {
+            let name = show
+                .current_cue()
+                .ok_or_else(|| anyhow!("No current cue to update"))?
+                .name()
+                .to_string();
+            let updated = show.update_cue(&name)?;
+            println!("Updated {} channels in cue {}", updated, name);
+
+            Ok(false)
+        }
+        Command::PreviewCue(name) => {
+            let channels = show.preview_cue(name)?;
+            if channels.is_empty() {
+                println!("Cue {} is empty", name);
+                return Ok(false);
+            }
+
+            // Only reads the patch for labels; nothing is sent to the output
+            let fixtures = query_fixtures(command_tx)?;
+            println!("Cue {} ({} channels):", name, channels.len());
+            println!("  Addr Value      Fixture");
+            println!("  ---- ---------- -------");
+            for (address, value) in channels {
+                let channel_type = fixture_at(&fixtures, address).map(|(_, ctype)| ctype);
+                println!(
+                    "  {:4} {:10} {}",
+                    address,
+                    format_level(value, channel_type, state.percent_mode),
+                    describe_address(&fixtures, address)
+                );
+            }
+
+            let orphans = show.orphan_channels(name)?;
+            if !orphans.is_empty() {
+                println!(
+                    "Warning: addresses {} are outside every patched fixture",
+                    join_addresses(&orphans)
+                );
+            }
+
+            Ok(false)
+        }
+        Command::CueSheet(path) => {
+            let fixtures = query_fixtures(command_tx)?;
+            std::fs::write(path, show.export_cue_sheet(&fixtures))
+                .with_context(|| format!("Failed to write cue sheet to {}", path))?;
+            println!("Wrote cue sheet to {}", path);
+
+            Ok(false)
+        }
+        Command::SaveShow(path) => {
+            let patch = query_universes(command_tx)?
+                .iter()
+                .map(|status| Ok((status.id, query_universe_patch(command_tx, status.id)?)))
+                .collect::<Result<BTreeMap<_, _>>>()?;
+            let saved = Show::capture(&patch, show)?;
+            save_show(path, &saved)?;
+            println!(
+                "Saved {} fixtures and {} cues to {}",
+                saved.fixture_count(),
+                saved.cue_count(),
+                path
+            );
+
+            Ok(false)
+        }
+        Command::LoadShow(path) => {
+            let loaded = load_show(path)?;
+            let universes = query_universes(command_tx)?;
+            let first = universes
+                .first()
+                .map(|status| status.id)
+                .ok_or_else(|| anyhow!("No universes are being driven"))?;
+            // The whole patch is rebuilt before anything is replaced, so a
+            // fixture missing from the library leaves the current show alone
+            let mut patch = loaded.patch(registry, first)?;
+            if let Some(id) = patch
+                .keys()
+                .find(|id| !universes.iter().any(|status| status.id == **id))
+            {
+                return Err(anyhow!(
+                    "The show patches universe {}, which isn't being driven",
+                    id
+                ));
+            }
+            let fixture_count = patch.values().map(Vec::len).sum::<usize>();
+            let cue_count = loaded.cue_count();
+
+            // Universes the show doesn't patch are cleared
+            for status in &universes {
+                command_tx
+                    .send(UniverseCommand::OnUniverse {
+                        universe: status.id,
+                        command: Box::new(UniverseCommand::SetPatch(
+                            patch.remove(&status.id).unwrap_or_default(),
+                        )),
+                    })
+                    .with_context(|| "Failed to send SetPatch command")?;
+            }
+            show.replace_cues(loaded.playbacks);
+            show.replace_groups(loaded.groups);
+            println!(
+                "Loaded {} fixtures and {} cues from {}",
+                fixture_count, cue_count, path
+            );
+
+            Ok(false)
+        }
+        Command::Validate => {
+            let invalid = show.validate_cues()?;
+            if invalid.is_empty() {
+                println!("Every cue only uses patched addresses");
+            }
+            for (name, orphans) in invalid {
+                println!(
+                    "Cue {}: addresses {} are outside every patched fixture",
+                    name,
+                    join_addresses(&orphans)
+                );
+            }
+
+            Ok(false)
+        }
+        Command::Trace(target) => {
+            let addresses: Vec<(usize, String)> = match target {
+                TraceTarget::Address(address) => vec![(*address, format!("Address {}", address))],
+                TraceTarget::Fixture(channel) => {
+                    let fixtures = query_fixtures(command_tx)?;
+                    let fixture = fixtures
+                        .iter()
+                        .find(|fixture| fixture.channel == *channel)
+                        .ok_or_else(|| anyhow!("No fixture found at channel {}", channel))?;
+
+                    fixture
+                        .channels
+                        .iter()
+                        .map(|(channel_type, offset)| {
+                            let address = fixture.buffer_index(*offset);
+                            (address, format!("{} (address {})", channel_type, address))
+                        })
+                        .collect()
+                }
+            };
+
+            for (address, heading) in addresses {
+                let cues = show.cues_using_channel(address);
+                if cues.is_empty() {
+                    println!("{}: not used by any cue", heading);
+                    continue;
+                }
+
+                println!("{}:", heading);
+                for cue in cues {
+                    println!("  cue {:10} {}", cue.name(), cue.channels()[address]);
+                }
+            }
+
+            Ok(false)
+        }
+        Command::Stats => {
+            let (response_tx, response_rx) = std::sync::mpsc::channel();
+
+            command_tx
+                .send(UniverseCommand::GetStats(response_tx))
+                .with_context(|| "Failed to send GetStats command")?;
+
+            let stats = response_rx
+                .recv_timeout(std::time::Duration::from_millis(100))
+                .with_context(|| "Timeout receiving stats")?;
+
+            println!("DMX output:");
+            println!(
+                "  Frame rate:        {:.1} Hz (target {:.1} Hz)",
+                stats.fps,
+                1.0 / stats.frame_interval.as_secs_f32()
+            );
+            println!("  Frames sent:       {}", stats.frames_sent);
+            println!("  Late frames:       {}", stats.late_frames);
+            println!("  Commands/second:   {}", stats.commands_per_second);
+            println!(
+                "  Command budget:    {} µs per tick",
+                stats.command_budget.as_micros()
+            );
+            println!("  Command overruns:  {}", stats.overruns);
+            println!("  Queue high water:  {}", stats.queue_high_water);
+            println!("  Coalesced writes:  {}", stats.coalesced_writes);
+            println!("  Deduped writes:    {}", stats.deduplicated_writes);
+            println!("  Break failures:    {}", stats.break_failures);
+            if stats.consecutive_break_failures > 0 {
+                println!(
+                    "  Link:              DOWN ({} breaks failed in a row)",
+                    stats.consecutive_break_failures
+                );
+            }
+            Ok(false)
+        }
+        Command::FrameRate(hz) => {
+            let (response_tx, response_rx) = std::sync::mpsc::channel();
+            command_tx
+                .send(UniverseCommand::SetFrameRate {
+                    hz: *hz,
+                    response: response_tx,
+                })
+                .with_context(|| "Failed to send SetFrameRate command")?;
+
+            let applied = response_rx
+                .recv_timeout(std::time::Duration::from_millis(100))
+                .with_context(|| "Timeout setting frame rate")?;
+            if applied != *hz {
+                println!(
+                    "{} Hz is outside {}-{} Hz, using {} Hz",
+                    hz, MIN_FRAME_RATE, MAX_FRAME_RATE, applied
+                );
+            }
+            println!(
+                "DMX refresh rate {:.1} Hz ({:.1} ms per frame)",
+                applied,
+                1000.0 / applied
+            );
+            Ok(false)
+        }
+        Command::Health(longer_than) => {
+            let (response_tx, response_rx) = std::sync::mpsc::channel();
+            command_tx
+                .send(UniverseCommand::GetPinnedChannels {
+                    longer_than: *longer_than,
+                    response: response_tx,
+                })
+                .with_context(|| "Failed to send GetPinnedChannels command")?;
+
+            let pinned = response_rx
+                .recv_timeout(std::time::Duration::from_millis(100))
+                .with_context(|| "Timeout receiving pinned channels")?;
+
+            let minutes = longer_than.as_secs() / 60;
+            if pinned.is_empty() {
+                println!("No addresses at full for {} minutes or more", minutes);
+                return Ok(false);
+            }
+
+            let universes: HashSet<u8> = pinned.iter().map(|channel| channel.universe).collect();
+            let patches = universes
+                .into_iter()
+                .map(|universe| Ok((universe, query_universe_fixtures(command_tx, universe)?)))
+                .collect::<Result<HashMap<_, _>>>()?;
+
+            println!("At full for {} minutes or more:", minutes);
+            println!("  Univ  Addr  Time      Fixture");
+            for channel in &pinned {
+                println!(
+                    "  {:4}  {:4}  {}  {}",
+                    channel.universe,
+                    channel.address,
+                    format_clock(channel.duration),
+                    describe_address(&patches[&channel.universe], channel.address)
+                );
+            }
+            Ok(false)
+        }
+        Command::Status => {
+            let universes = query_universes(command_tx)?;
+            for status in &universes {
+                println!(
+                    "Universe {}: output {}, {} fixtures patched",
+                    status.id,
+                    if status.output_enabled { "on" } else { "OFF" },
+                    status.fixture_count
+                );
+            }
+            println!(
+                "Clock {}{}",
+                format_clock(state.clock.elapsed()),
+                if state.clock.is_running() {
+                    ""
+                } else {
+                    " (stopped)"
+                }
+            );
+            // Only name the universe when there is more than one to tell apart
+            for status in &universes {
+                let on = match universes.len() {
+                    1 => String::new(),
+                    _ => format!(" on universe {}", status.id),
+                };
+                for (channel, source) in &status.mirrors {
+                    println!("Channel {} mirrors channel {}{}", channel, source, on);
+                }
+                for (address, value) in &status.parked {
+                    println!("Address {} parked at {}{}", address, value, on);
+                }
+                if status.blackout_latched {
+                    println!("Blackout LATCHED{}", on);
+                }
+            }
+            if let Some(cue) = show.editing_cue() {
+                println!("Editing cue {}", cue.name());
+            }
+            Ok(false)
+        }
+        Command::Output { universe, enabled } => {
+            command_tx
+                .send(UniverseCommand::SetOutputEnabled {
+                    universe: *universe,
+                    enabled: *enabled,
+                })
+                .with_context(|| "Failed to send output command")?;
+            println!(
+                "Universe {} output {}",
+                universe,
+                if *enabled { "on" } else { "off" }
+            );
+
+            Ok(false)
+        }
+        Command::Priority { universe, level } => {
+            let (response_tx, response_rx) = std::sync::mpsc::channel();
+            command_tx
+                .send(UniverseCommand::SetPriority {
+                    universe: *universe,
+                    priority: *level,
+                    response: response_tx,
+                })
+                .with_context(|| "Failed to send priority command")?;
+
+            response_rx
+                .recv_timeout(std::time::Duration::from_millis(100))
+                .with_context(|| "Timeout receiving priority result")??;
+            match universe {
+                Some(universe) => println!("Universe {} sACN priority {}", universe, level),
+                None => println!("sACN priority {}", level),
+            }
+
+            Ok(false)
+        }
+        Command::RecordSession(path) => {
+            if let Some(current) = &state.session_path {
+                return Err(anyhow!("Already recording to {}, use 'record stop'", current));
+            }
+
+            command_tx
+                .send(UniverseCommand::StartSessionRecording)
+                .with_context(|| "Failed to start session recording")?;
+            state.session_path = Some(path.clone());
+            println!("Recording session to {}", path);
+
+            Ok(false)
+        }
+        Command::StopSessionRecording => {
+            let path = state
+                .session_path
+                .take()
+                .ok_or_else(|| anyhow!("No session is being recorded"))?;
+            let (response_tx, response_rx) = std::sync::mpsc::channel();
+
+            command_tx
+                .send(UniverseCommand::StopSessionRecording(response_tx))
+                .with_context(|| "Failed to stop session recording")?;
+
+            let events = response_rx
+                .recv_timeout(std::time::Duration::from_millis(100))
+                .with_context(|| "Timeout receiving recorded session")?;
+            save_session(&path, &events)?;
+            println!("Saved {} commands to {}", events.len(), path);
+
+            Ok(false)
+        }
+        Command::PlaySession(path) => {
+            let events = load_session(path)?;
+            println!("Playing {} commands from {}", events.len(), path);
+            let playback = SessionPlayer::play(events, registry, command_tx.clone())?;
+            std::thread::spawn(move || {
+                if let Ok(Err(e)) = playback.join() {
+                    eprintln!("Session playback stopped: {}", e);
+                }
+            });
+
+            Ok(false)
+        }
+        Command::Clock(action) => {
+            match action {
+                ClockAction::Start => state.clock.start(),
+                ClockAction::Stop => state.clock.stop(),
+                ClockAction::Reset => state.clock.reset(),
+            }
+            println!(
+                "Clock {} {}",
+                format_clock(state.clock.elapsed()),
+                if state.clock.is_running() {
+                    "running"
+                } else {
+                    "stopped"
+                }
+            );
+
+            Ok(false)
+        }
+        Command::Mark => {
+            state.mark = Some(query_dmx_state(command_tx)?);
+            println!("Marked current DMX state");
+
+            Ok(false)
+        }
+        Command::Diff => {
+            let mark = state
+                .mark
+                .ok_or_else(|| anyhow!("No mark set, use 'mark' first"))?;
+            let current = query_dmx_state(command_tx)?;
+            let fixtures = query_fixtures(command_tx)?;
+
+            let changes = diff_buffers(&mark, &current);
+            if changes.is_empty() {
+                println!("No changes since mark");
+                return Ok(false);
+            }
+
+            println!("{} addresses changed since mark:", changes.len());
+            println!("  Addr Old        New        Fixture");
+            println!("  ---- ---------- ---------- -------");
+            for (address, old, new) in changes {
+                let channel_type = fixture_at(&fixtures, address).map(|(_, ctype)| ctype);
+                println!(
+                    "  {:4} {:10} {:10} {}",
+                    address,
+                    format_level(old, channel_type, state.percent_mode),
+                    format_level(new, channel_type, state.percent_mode),
+                    describe_address(&fixtures, address)
+                );
+            }
 
             Ok(false)
         }
         Command::Help => {
             println!("Available commands:");
             println!(
-                "  c <num> @ <intensity>         - Set fixture intensity (0-255 or 'f' for full)"
+                "  c <num> @ <intensity>         - Set fixture intensity (0-255, 50% or 'f' for full)"
             );
             println!("  c <num> rgb <r> <g> <b>       - Set fixture RGB color (0-255 each)");
+            println!("  c <num> @ <int> rgb <r> <g> <b> - Set intensity and color in one frame");
+            println!("  c <num> cct <kelvin>          - Set a white point (1000-12000K) by color temperature channel, or an approximate RGB mix");
+            println!("  c <num> hsv <h> <s> <v>       - Set color by hue in degrees (wraps), saturation and value (0-1), on hue/saturation channels if present");
+            println!("  c <num> strobe <0-255>        - Set the strobe rate (an error without a strobe channel)");
+            println!("  c <num> pos <pan> <tilt>      - Aim a moving light (0-255, or 0-65535 on axes with a fine channel)");
+            println!("  c <num> gobo <slot>           - Put the gobo wheel on a slot, from 1 in wheel order (get <fixture> lists them)");
+            println!("  c <num> \"<OFL name>\" @ <value> - Set a channel by its OFL name (e.g. \"Shutter / Strobe\")");
+            println!("  c <num> <function>#<n> @ <value> - Set the nth channel of a function a fixture repeats, e.g. dimmer#2");
+            println!("  c 1 thru 8 + 10 ...           - Any c command on a group, skipping channels with nothing patched");
+            println!("  group <name> = 1 thru 8 + 10  - Define or redefine a named group of channels (group alone lists them)");
+            println!("  g <name> ...                  - Any c command on a named group, e.g. g front @ full");
+            println!("  ungroup <name>                - Delete a group");
             println!("  a <addr> @ <value>            - Set DMX address directly (1-512)");
-            println!("  channels <fixture>            - List channels for fixture");
-            println!("  blackout                      - Turn off all fixtures");
+            println!("  a <univ>.<addr> @ <value>     - Set an address on a given universe (also 2/15; a bare address is on the first)");
+            println!("  <univ>/c <num> ...            - Any c command on a given universe, e.g. 2/c 5 @ 100");
+            println!("  get <fixture>                 - List channels for fixture");
+            println!("  report <fixture>              - Live value of each function, with capability names and angles");
+            println!("  capabilities <fixture>        - Every DMX range of every channel and what it does (alias: caps)");
+            println!("  calibrate <fixture>           - What 0/10/25/50/75/100% come out as after cue masters, limits, mute and solo");
+            println!("  query <address>               - Show a DMX value (intensity also in %)");
+            println!("  blackout                      - Turn off all fixtures and drop every playback's live cue");
+            println!("  blackout latch                - Hold the output dark, whatever playbacks or commands do, until released");
+            println!("  blackout release              - Release a latched blackout, bringing back the levels underneath");
+            println!("  manual clear                  - Drop every hand-set level (hand intensity merges highest-takes-precedence with cues)");
+            println!("  panic                         - Blackout and latch in one go");
+            println!("  patch                         - List patched fixtures");
+            println!("  manufacturers [prefix]        - List manufacturers with fixture counts");
+            println!("  search <term>                 - Find fixtures by file name, OFL short name or category");
+            println!(
+                "  search <term> names           - File names only, much faster on a large library"
+            );
+            println!("  checklib                      - Parse the whole fixture library and list files that fail, with the error location");
+            println!("  compare <mfr/fix> <mfr/fix>   - Compare two fixture definitions' modes, channel lists and capabilities");
+            println!("  rdm <uid>                     - Manufacturer of an RDM UID such as 0526:1A2B3C4D");
+            println!("  freeaddr <footprint>          - List unused addresses and the first gap that fits");
+            println!("  park <addr> @ <value>         - Hold an address at a value that cues, blackout and hand sets leave alone");
+            println!("  unpark <addr>                 - Release a parked address, which keeps its value until something else sets it");
+            println!("  mute <channel>                - Ignore commands and hold fixture dark");
+            println!("  unmute <channel>              - Return a muted fixture to normal");
+            println!("  solo <channel>                - Add a fixture to the solo set, darkening all others");
+            println!("  solo clear                    - Turn solo off");
+            println!("  copy <num> to <num> [+ <num>] - Write a fixture's current values onto others once (shared functions only)");
+            println!("  mirror <num> = <source>       - Copy another fixture's output live (shared functions only)");
+            println!("  unmirror <num>                - Stop mirroring");
+            println!("  raw <num> <b0> <b1> ...       - Write bytes straight to a fixture's slots from its start address");
+            println!("  colorlimit <num> <color> <max> - Cap a color channel (e.g. red 200, or off)");
+            println!("  curve <num> <curve>           - Dimmer curve applied at output: linear, square, invsquare or s");
+            println!("  fine <num> <type> <0-65535>   - Set a function across coarse and fine channels (high byte only without fine), e.g. fine 5 pan 32768");
+            println!("  all @ <level>                 - Set intensity on every fixture that has it");
+            println!("  all rgb <r> <g> <b>           - Set color on every RGB fixture");
+            println!("  all <type> <value>            - Set a channel type on every fixture (e.g. all pan 128)");
+            println!("  neutral color [white|off]     - Set every color channel to open white (default) or off");
+            println!("  neutral position              - Center pan and tilt on every mover");
+            println!("  neutral home                  - Return every fixture to its recorded home, or center without one");
+            println!("  sethome <num>|all [color]     - Record current pan/tilt (and with 'color', color) as the fixture's home");
+            println!("  category <name>               - List patched fixtures in an OFL category (e.g. \"Moving Head\")");
+            println!("  category <name> @ <level>     - Same value forms as all, for just that category");
+            println!("  rc <cue> <ms> [from <playback>] - Record live output into a cue, or only what one playback is putting out");
+            println!("  rc <cue> follow [from <playback>] - Record a cue that takes the previous cue's fade time, so retiming that one retimes both");
+            println!("  rename <old> <new>            - Rename or renumber a cue");
+            println!("  update                        - Write only channels changed since the last go/back into the current cue");
+            println!("  loadcue <cue>                 - Put a cue on stage to edit, for update to save your changes into");
+            println!("  preview <cue>                 - List a cue's stored levels by fixture, without outputting them");
+            println!("  validate                      - List cues with non-zero levels outside every patched fixture");
+            println!("  cuesheet <file>               - Write the selected playback's cues as a text sheet (number, label, fade, changes by fixture)");
+            println!("  save <file>                   - Save the patch and every playback's cues as JSON");
+            println!("  load <file>                   - Replace the patch and cues with a saved show, playing nothing until the next go");
+            println!("  goto <cue> [at <percent>]     - Jump to a cue ('at 50' plays its intensities at half)");
+            println!("  go [playback]                 - Play the next cue on a playback (default main)");
+            println!("  go [playback] [in] <seconds>  - Go once with this fade time instead of the cue's (the cue keeps its time)");
+            println!("  back [playback]               - Play the previous cue on a playback");
+            println!("  fader [playback] <level>      - Scale the live cue's intensity (0-255 or 'f') without changing the cue");
+            println!("  playback <name>               - Select the playback that record/update/goto edit, creating it");
+            println!("  playback                      - List playbacks and their current cues");
+            println!("  trace <addr>                  - List cues that set an address, with values");
+            println!("  trace c <channel>             - Same, for every channel of a fixture");
+            println!("  stats                         - Show DMX frame rate and command load");
+            println!("  rate dmx <hz>                 - Change the DMX refresh rate live (1-44 Hz) to find what a fixture likes");
+            println!("  health [minutes]              - Addresses at 255 for at least this long (default 10), a sign of a stuck fader");
+            println!("  status                        - Show each universe's output state");
+            println!("  output <universe> on|off      - Stop sending frames but keep programming (unlike blackout)");
+            println!("  priority [universe] <level>   - Set the sACN priority (0-200, default 100) another source merges against");
+            println!("  record session <path>         - Start recording commands with their timing");
+            println!("  record stop                   - Stop and save the recorded session");
+            println!("  play session <path>           - Replay a session at its original timing");
+            println!("  mark                          - Remember the current DMX output");
+            println!("  diff                          - List addresses changed since 'mark'");
+            println!("  clock start                   - Start (or resume) the show clock");
+            println!("  clock stop                    - Pause the show clock");
+            println!("  clock reset                   - Stop the show clock and zero it");
             println!("  quit/exit                     - Exit program");
             println!("  help                          - Show this help");
             println!();
@@ -314,6 +2931,8 @@ fn execute_command(
             println!("  c 1 @ 255         - Set channel 1 to full intensity");
             println!("  c 1 @ f           - Set channel 1 to full intensity");
             println!("  c 1 rgb 255 0 0   - Set channel 1 to red");
+            println!("  c 1 @ 80 rgb 255 0 0 - Set channel 1 to red at 80");
+            println!("  c \"Front Wash SL\" @ 80 - Set the fixture labelled Front Wash SL");
             println!("  a 10 @ 128        - Set DMX address 10 to 128");
             println!("  get 1         - Show channels for fixture 1");
             Ok(false)
@@ -325,3 +2944,500 @@ fn execute_command(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Command {
+        let tokens = tokenize(input).unwrap();
+        let args: Vec<&str> = tokens.iter().map(String::as_str).collect();
+        parse_command(&args)
+    }
+
+    #[test]
+    fn test_tokenize_keeps_quoted_text_together() {
+        assert_eq!(tokenize("  rc 1   3 ").unwrap(), vec!["rc", "1", "3"]);
+        assert_eq!(
+            tokenize(r#"rc "Act One" 3"#).unwrap(),
+            vec!["rc", "Act One", "3"]
+        );
+        assert_eq!(
+            tokenize(r#"goto " Act  One ""#).unwrap(),
+            vec!["goto", "Act  One"]
+        );
+        assert_eq!(tokenize(r#"preview """#).unwrap(), vec!["preview", ""]);
+        assert!(tokenize(r#"rc "Act One 3"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_quoted_and_unquoted_cue_names() {
+        assert!(matches!(
+            parse(r#"rc "Act One" 3"#),
+            Command::RecordCue { name, time_in, source: RecordSource::Live }
+                if name == "Act One" && time_in == CueTime::from_millis(3)
+        ));
+        assert!(matches!(
+            parse("rc 5 0"),
+            Command::RecordCue { name, time_in, source: RecordSource::Live }
+                if name == "5" && time_in == CueTime::from_millis(0)
+        ));
+        assert!(matches!(
+            parse("rc 6 2 from house"),
+            Command::RecordCue { name, source: RecordSource::Playback(playback), .. }
+                if name == "6" && playback == "house"
+        ));
+        assert!(matches!(
+            parse("rc 6 follow from house"),
+            Command::RecordCue {
+                time_in: CueTime::Inherit,
+                source: RecordSource::Playback(_),
+                ..
+            }
+        ));
+        assert!(matches!(parse("rc 6 2 house"), Command::Error(_)));
+        assert!(matches!(parse("rc 6"), Command::Error(_)));
+        assert!(matches!(
+            parse(r#"rc " Act One " 3"#),
+            Command::RecordCue { name, .. } if name == "Act One"
+        ));
+        assert!(matches!(parse("rc"), Command::Error(_)));
+        assert!(matches!(parse(r#"rc "" 3"#), Command::Error(_)));
+        assert!(matches!(parse(r#"rc "   " 3"#), Command::Error(_)));
+        assert!(matches!(parse("rc at 3"), Command::Error(_)));
+        assert!(matches!(
+            parse(r#"goto "Act Two" at 50"#),
+            Command::GoToCue { name, intensity_percent: 50 } if name == "Act Two"
+        ));
+        assert!(matches!(
+            parse(r#"rename "Act One" 1"#),
+            Command::RenameCue { old, new } if old == "Act One" && new == "1"
+        ));
+    }
+
+    #[test]
+    fn test_parse_go_fade_override() {
+        assert!(matches!(
+            parse("go"),
+            Command::Go { playback, fade: None } if playback == MAIN_PLAYBACK
+        ));
+        assert!(matches!(
+            parse("go house in 2.5"),
+            Command::Go { playback, fade: Some(fade) }
+                if playback == "house" && fade == Duration::from_millis(2500)
+        ));
+        assert!(matches!(
+            parse("go 0"),
+            Command::Go { fade: Some(Duration::ZERO), .. }
+        ));
+        assert!(matches!(parse("go -1"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_quoted_fixture_label() {
+        assert!(matches!(
+            parse(r#"c "Stage Left" @ 50"#),
+            Command::Channel {
+                fixture: FixtureRef::Label(label),
+                ..
+            } if label == "Stage Left"
+        ));
+        assert!(matches!(
+            parse("c 12 @ 50"),
+            Command::Channel {
+                fixture: FixtureRef::Channel(12),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_channel_groups() {
+        let channels = |input| match parse(input) {
+            Command::Channel {
+                fixture: FixtureRef::Channels(channels),
+                ..
+            } => channels,
+            other => panic!("{} parsed as {:?}", input, other),
+        };
+
+        assert_eq!(channels("c 1 thru 4 @ full"), vec![1, 2, 3, 4]);
+        assert_eq!(channels("c 1 + 3 + 5 @ 50"), vec![1, 3, 5]);
+        assert_eq!(channels("c 8 thru 6 + 2 rgb 255 0 0"), vec![6, 7, 8, 2]);
+        assert_eq!(channels("c 1 thru 3 + 2 @ 10"), vec![1, 2, 3]);
+        assert!(matches!(
+            parse("c 1 thru 3 \"Zoom\" @ 10"),
+            Command::NamedChannel {
+                fixture: FixtureRef::Channels(_),
+                ..
+            }
+        ));
+        assert!(matches!(parse("c 1 thru @ 50"), Command::Error(_)));
+        assert!(matches!(parse("c 1 + front @ 50"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_groups() {
+        assert!(matches!(
+            parse("group front = 1 thru 3 + 8"),
+            Command::DefineGroup { name, channels } if name == "front" && channels == [1, 2, 3, 8]
+        ));
+        assert!(matches!(
+            parse("group spot = 4"),
+            Command::DefineGroup { channels, .. } if channels == [4]
+        ));
+        assert!(matches!(parse("group front = Left"), Command::Error(_)));
+        assert!(matches!(parse("group front = 1 @ 50"), Command::Error(_)));
+        assert!(matches!(parse("group front"), Command::Error(_)));
+        assert!(matches!(parse("group"), Command::ListGroups));
+        assert!(matches!(parse("ungroup front"), Command::DeleteGroup(name) if name == "front"));
+
+        assert!(matches!(
+            parse("g front rgb 255 0 0"),
+            Command::Channel {
+                fixture: FixtureRef::Group(name),
+                action: ChannelAction {
+                    intensity: None,
+                    color: Some((255, 0, 0)),
+                    ..
+                },
+            } if name == "front"
+        ));
+        assert!(matches!(parse("g front"), Command::Error(_)));
+        assert!(matches!(parse("g"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_combined_intensity_and_color() {
+        for input in ["c 1 @ 80 rgb 255 0 0", "c 1 rgb 255 0 0 @ 80"] {
+            assert!(matches!(
+                parse(input),
+                Command::Channel {
+                    action: ChannelAction {
+                        intensity: Some(80),
+                        color: Some((255, 0, 0)),
+                        ..
+                    },
+                    ..
+                }
+            ));
+        }
+        assert!(matches!(
+            parse("c 1 @ f"),
+            Command::Channel {
+                action: ChannelAction {
+                    intensity: Some(255),
+                    color: None,
+                    ..
+                },
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse("c 1 rgb 0 0 255"),
+            Command::Channel {
+                action: ChannelAction {
+                    intensity: None,
+                    color: Some((0, 0, 255)),
+                    ..
+                },
+                ..
+            }
+        ));
+        assert!(matches!(parse("c 1"), Command::Error(_)));
+        assert!(matches!(parse("c 1 @ 80 @ 90"), Command::Error(_)));
+        assert!(matches!(parse("c 1 @ 80 rgb 255 0"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_color_temperature() {
+        for input in ["c 1 @ 80 cct 3200", "c 1 cct 3200K @ 80"] {
+            assert!(matches!(
+                parse(input),
+                Command::Channel {
+                    action: ChannelAction {
+                        intensity: Some(80),
+                        color: None,
+                        kelvin: Some(3200),
+                        ..
+                    },
+                    ..
+                }
+            ));
+        }
+        assert!(matches!(parse("c 1 cct warm"), Command::Error(_)));
+        assert!(matches!(parse("c 1 cct"), Command::Error(_)));
+        assert!(matches!(
+            parse("c 1 cct 3200 rgb 255 0 0"),
+            Command::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_hsv() {
+        assert!(matches!(
+            parse("c 1 hsv 240 1 0.5"),
+            Command::Channel {
+                action: ChannelAction {
+                    intensity: None,
+                    color: None,
+                    hsv: Some((h, s, v)),
+                    ..
+                },
+                ..
+            } if h == 240.0 && s == 1.0 && v == 0.5
+        ));
+        assert!(matches!(parse("c 1 hsv 240 1"), Command::Error(_)));
+        assert!(matches!(
+            parse("c 1 hsv 240 1 1 rgb 0 0 255"),
+            Command::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_strobe() {
+        assert!(matches!(
+            parse("c 1 @ full strobe 200"),
+            Command::Channel {
+                action: ChannelAction {
+                    intensity: Some(255),
+                    strobe: Some(200),
+                    ..
+                },
+                ..
+            }
+        ));
+        assert!(matches!(parse("c 1 strobe 300"), Command::Error(_)));
+        assert!(matches!(parse("c 1 strobe"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_position() {
+        assert!(matches!(
+            parse("c 1 pos 32768 200"),
+            Command::Channel {
+                action: ChannelAction {
+                    position: Some((32768, 200)),
+                    ..
+                },
+                ..
+            }
+        ));
+        assert!(matches!(parse("c 1 pos 128"), Command::Error(_)));
+        assert!(matches!(parse("c 1 pos 70000 0"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_gobo() {
+        assert!(matches!(
+            parse("c 1 gobo 3 @ full"),
+            Command::Channel {
+                action: ChannelAction {
+                    intensity: Some(255),
+                    gobo: Some(3),
+                    ..
+                },
+                ..
+            }
+        ));
+        assert!(matches!(parse("c 1 gobo open"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_mirror() {
+        assert!(matches!(
+            parse("mirror 7 = 3"),
+            Command::Mirror {
+                channel: 7,
+                source: Some(3)
+            }
+        ));
+        assert!(matches!(
+            parse("unmirror 7"),
+            Command::Mirror {
+                channel: 7,
+                source: None
+            }
+        ));
+        assert!(matches!(parse("mirror 7 3"), Command::Error(_)));
+        assert!(matches!(parse("mirror 7 ="), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_named_channel() {
+        assert!(matches!(
+            parse(r#"c 5 "Shutter / Strobe" @ 200"#),
+            Command::NamedChannel {
+                fixture: FixtureRef::Channel(5),
+                name,
+                value: 200,
+            } if name == "Shutter / Strobe"
+        ));
+        assert!(matches!(parse("c 5 @ 80"), Command::Channel { .. }));
+        assert!(matches!(parse(r#"c 5 "Color Wheel" @"#), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_indexed_channel() {
+        assert!(matches!(
+            parse("c 5 dimmer#2 @ 200"),
+            Command::IndexedChannel {
+                fixture: FixtureRef::Channel(5),
+                ctype: ChannelType::Dimmer,
+                instance: 1,
+                value: 200,
+            }
+        ));
+        assert!(matches!(parse("c 5 dimmer#0 @ 200"), Command::Error(_)));
+        assert!(matches!(parse("c 5 dimmer#x @ 200"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_fader() {
+        assert!(matches!(
+            parse("fader 128"),
+            Command::Fader { playback, level: 128 } if playback == MAIN_PLAYBACK
+        ));
+        assert!(matches!(
+            parse("fader house f"),
+            Command::Fader { playback, level: 255 } if playback == "house"
+        ));
+        assert!(matches!(parse("fader"), Command::Error(_)));
+        assert!(matches!(parse("fader 300"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_universe_addresses() {
+        let Command::OnUniverse { universe, command } = parse("a 2.15 @ 128") else {
+            panic!("expected a command on universe 2");
+        };
+        assert_eq!(universe, 2);
+        assert!(matches!(
+            *command,
+            Command::Address {
+                address: 15,
+                value: 128
+            }
+        ));
+
+        // A bare address stays on universe 0
+        assert!(matches!(
+            parse("a 15 @ 128"),
+            Command::Address {
+                address: 15,
+                value: 128
+            }
+        ));
+
+        let Command::OnUniverse { universe, command } = parse("2/c 5 @ 100") else {
+            panic!("expected a fixture command on universe 2");
+        };
+        assert_eq!(universe, 2);
+        assert!(matches!(*command, Command::Channel { .. }));
+
+        let Command::OnUniverse { universe, .. } = parse("a 3/10 @ 128") else {
+            panic!("expected a command on universe 3");
+        };
+        assert_eq!(universe, 3);
+
+        assert!(matches!(parse("a x.15 @ 128"), Command::Error(_)));
+        assert!(matches!(parse("2/c 5 @ 300"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_copy() {
+        assert!(matches!(
+            parse("copy 3 to 4 + 5 + 6"),
+            Command::Copy { source: 3, targets } if targets == [4, 5, 6]
+        ));
+        assert!(matches!(
+            parse("copy 3 to 4"),
+            Command::Copy { source: 3, targets } if targets == [4]
+        ));
+        assert!(matches!(parse("copy 3 to"), Command::Error(_)));
+        assert!(matches!(parse("copy 3 4"), Command::Error(_)));
+        assert!(matches!(parse("copy 3 to 4 + + 5"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_compare() {
+        assert!(matches!(
+            parse("compare acme/par-old acme/par-new"),
+            Command::Compare { a, b } if a.0 == "acme" && a.1 == "par-old" && b.1 == "par-new"
+        ));
+        assert!(matches!(parse("compare acme/par-old"), Command::Error(_)));
+        assert!(matches!(
+            parse("compare par-old par-new"),
+            Command::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_rdm_uid() {
+        assert!(matches!(parse("rdm 0526:1A2B3C4D"), Command::Rdm(0x0526)));
+        assert!(matches!(parse("rdm 0x08D4"), Command::Rdm(0x08D4)));
+        assert!(matches!(parse("rdm chauvet"), Command::Error(_)));
+        assert!(matches!(parse("rdm"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_priority() {
+        assert!(matches!(
+            parse("priority 150"),
+            Command::Priority {
+                universe: None,
+                level: 150
+            }
+        ));
+        assert!(matches!(
+            parse("priority 2 50"),
+            Command::Priority {
+                universe: Some(2),
+                level: 50
+            }
+        ));
+        assert!(matches!(parse("priority"), Command::Error(_)));
+        assert!(matches!(parse("priority 300"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_save_and_load_show() {
+        assert!(matches!(parse("save show.json"), Command::SaveShow(path) if path == "show.json"));
+        assert!(matches!(parse("load show.json"), Command::LoadShow(path) if path == "show.json"));
+        assert!(matches!(parse("load"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_park() {
+        assert!(matches!(
+            parse("park 12 @ full"),
+            Command::Park {
+                address: 12,
+                value: 255
+            }
+        ));
+        assert!(matches!(
+            parse("park 2.12 @ 0"),
+            Command::OnUniverse { universe: 2, .. }
+        ));
+        assert!(matches!(parse("unpark 12"), Command::Unpark(12)));
+        assert!(matches!(parse("park 12"), Command::Error(_)));
+    }
+
+    #[test]
+    fn test_parse_intensity_percent() {
+        assert_eq!(parse_intensity("50%").unwrap(), 128);
+        assert_eq!(parse_intensity("100%").unwrap(), 255);
+        assert_eq!(parse_intensity("0%").unwrap(), 0);
+        assert_eq!(parse_intensity("150%").unwrap(), 255);
+        assert_eq!(parse_intensity("50").unwrap(), 50);
+        assert_eq!(parse_intensity("full").unwrap(), 255);
+        assert!(parse_intensity("-5%").is_err());
+        assert!(parse_intensity("%").is_err());
+
+        // Every percent survives the trip back through the display rounding
+        for percent in 0..=100 {
+            assert_eq!(to_percent(from_percent(percent)), percent);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::{cell::RefCell, os::raw::c_char, slice};
+
+/// Descriptor the fake hands out from `dmx_open`; any other is rejected
+pub const FAKE_FD: i32 = 3;
+
+/// Largest write the C layer accepts: start code plus 512 slots
+const MAX_FRAME: i32 = 513;
+
+#[derive(Default)]
+struct FakePort {
+    breaks: u32,
+    failing_breaks: u32,
+    writes: Vec<Vec<u8>>,
+}
+
+thread_local! {
+    // Per thread, so tests running in parallel each see only their own port
+    static PORT: RefCell<FakePort> = RefCell::default();
+}
+
+/// Stands in for the C `dmx_open`; the port name is ignored
+pub unsafe fn dmx_open(_port: *const c_char) -> i32 {
+    FAKE_FD
+}
+
+pub unsafe fn dmx_send_break(fd: i32) -> i32 {
+    PORT.with_borrow_mut(|port| {
+        if fd != FAKE_FD {
+            return -1;
+        }
+        if port.failing_breaks > 0 {
+            port.failing_breaks -= 1;
+            return -1;
+        }
+        port.breaks += 1;
+        0
+    })
+}
+
+/// Copies `length` bytes out of `data`, the same bytes the real layer would
+/// hand to `write`, so a wrong pointer or length shows up in the recorded frame
+pub unsafe fn dmx_write(fd: i32, data: *const u8, length: i32) -> i32 {
+    if fd != FAKE_FD || data.is_null() || !(0..=MAX_FRAME).contains(&length) {
+        return -1;
+    }
+
+    let frame = slice::from_raw_parts(data, length as usize).to_vec();
+    PORT.with_borrow_mut(|port| port.writes.push(frame));
+    length
+}
+
+pub unsafe fn dmx_read_frame(_fd: i32, _buffer: *mut u8, _max_len: i32) -> i32 {
+    0
+}
+
+pub unsafe fn dmx_close(_fd: i32) {}
+
+/// Make the next `count` breaks on this thread fail, like an unplugged interface
+pub fn fail_breaks(count: u32) {
+    PORT.with_borrow_mut(|port| port.failing_breaks = count);
+}
+
+/// Breaks sent on this thread so far
+pub fn breaks_sent() -> u32 {
+    PORT.with_borrow(|port| port.breaks)
+}
+
+/// Every frame written on this thread since the last call
+pub fn take_writes() -> Vec<Vec<u8>> {
+    PORT.with_borrow_mut(|port| std::mem::take(&mut port.writes))
+}
@@ -1,5 +1,5 @@
 use serde_json;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -10,6 +10,7 @@ pub struct FixtureLoader {
     fixture_data_path: PathBuf,
     manufacturers: Option<OflManufacturers>,
     loaded_fixtures: HashMap<String, OflFixture>, // Key format: "manufacturer/fixture"
+    only_manufacturers: Option<HashSet<String>>,  // When set, every other directory is ignored
 }
 
 impl FixtureLoader {
@@ -18,9 +19,29 @@ impl FixtureLoader {
             fixture_data_path: fixture_data_path.as_ref().to_path_buf(),
             manufacturers: None,
             loaded_fixtures: HashMap::new(),
+            only_manufacturers: None,
         }
     }
 
+    /// Loader that only sees the given manufacturer directories, so discovery
+    /// doesn't walk the whole fixture tree
+    pub fn with_manufacturers<P: AsRef<Path>>(
+        fixture_data_path: P,
+        manufacturers: &[&str],
+    ) -> Self {
+        Self {
+            only_manufacturers: Some(manufacturers.iter().map(|m| m.to_string()).collect()),
+            ..Self::new(fixture_data_path)
+        }
+    }
+
+    /// Whether a manufacturer directory is visible to this loader
+    fn includes(&self, manufacturer: &str) -> bool {
+        self.only_manufacturers
+            .as_ref()
+            .is_none_or(|only| only.contains(manufacturer))
+    }
+
     /// Load the manufacturers database
     pub fn load_manufacturers(&mut self) -> Result<()> {
         let manufacturers_path = self.fixture_data_path.join("manufacturers.json");
@@ -44,26 +65,45 @@ impl FixtureLoader {
     ) -> Result<&OflFixture> {
         let key = format!("{}/{}", manufacturer, fixture_name);
 
+        if !self.includes(manufacturer) {
+            return Err(anyhow!("Manufacturer '{}' is not loaded", manufacturer));
+        }
+
         // Check if fixture is already loaded
         if self.loaded_fixtures.contains_key(&key) {
             return Ok(&self.loaded_fixtures[&key]);
         }
 
-        // Try to load the fixture file
-        let fixture_path = self
-            .fixture_data_path
-            .join(manufacturer)
-            .join(format!("{}.json", fixture_name));
+        let fixture = self.read_fixture(manufacturer, fixture_name)?;
+
+        self.loaded_fixtures.insert(key.clone(), fixture);
+        Ok(&self.loaded_fixtures[&key])
+    }
+
+    /// Parse a fixture file without caching it, for scanning many fixtures once
+    pub fn read_fixture(&self, manufacturer: &str, fixture_name: &str) -> Result<OflFixture> {
+        if !self.includes(manufacturer) {
+            return Err(anyhow!("Manufacturer '{}' is not loaded", manufacturer));
+        }
+
+        let fixture_path = self.fixture_path(manufacturer, fixture_name);
 
         if !fixture_path.exists() {
-            return Err(anyhow!("Fixture file not found: {}", fixture_path.display()).into());
+            return Err(anyhow!(
+                "Fixture file not found: {}",
+                fixture_path.display()
+            ));
         }
 
         let content = fs::read_to_string(&fixture_path)?;
-        let fixture: OflFixture = serde_json::from_str(&content)?;
+        Ok(serde_json::from_str(&content)?)
+    }
 
-        self.loaded_fixtures.insert(key.clone(), fixture);
-        Ok(&self.loaded_fixtures[&key])
+    /// Where a fixture's JSON file lives
+    pub fn fixture_path(&self, manufacturer: &str, fixture_name: &str) -> PathBuf {
+        self.fixture_data_path
+            .join(manufacturer)
+            .join(format!("{}.json", fixture_name))
     }
 
     /// Discover available fixtures for a manufacturer
@@ -73,7 +113,7 @@ impl FixtureLoader {
     ) -> Result<Vec<String>> {
         let manufacturer_dir = self.fixture_data_path.join(manufacturer);
 
-        if !manufacturer_dir.exists() || !manufacturer_dir.is_dir() {
+        if !self.includes(manufacturer) || !manufacturer_dir.exists() || !manufacturer_dir.is_dir() {
             return Ok(Vec::new());
         }
 
@@ -99,6 +139,17 @@ impl FixtureLoader {
     ) -> Result<HashMap<String, Vec<String>>> {
         let mut all_fixtures = HashMap::new();
 
+        // Only the listed directories need reading, not the whole tree
+        if let Some(only) = &self.only_manufacturers {
+            for manufacturer in only {
+                let fixtures = self.list_fixtures_for_manufacturer(manufacturer)?;
+                if !fixtures.is_empty() {
+                    all_fixtures.insert(manufacturer.clone(), fixtures);
+                }
+            }
+            return Ok(all_fixtures);
+        }
+
         for entry in fs::read_dir(&self.fixture_data_path)? {
             let entry = entry?;
             let path = entry.path();
@@ -161,4 +212,21 @@ mod tests {
             assert!(!all_fixtures.is_empty());
         }
     }
+
+    #[test]
+    fn test_manufacturer_filter_hides_other_directories() {
+        if Path::new("fixture-data/etc").exists() {
+            let mut loader = FixtureLoader::with_manufacturers("fixture-data", &["etc"]);
+
+            let all_fixtures = loader.discover_all_fixtures().unwrap();
+            assert_eq!(all_fixtures.keys().collect::<Vec<_>>(), vec!["etc"]);
+
+            assert!(loader
+                .list_fixtures_for_manufacturer("chauvet-dj")
+                .unwrap()
+                .is_empty());
+            assert!(loader.load_fixture("chauvet-dj", "slimpar-pro-rgba").is_err());
+            assert!(loader.load_fixture("etc", "colorsource-par").is_ok());
+        }
+    }
 }
@@ -1,4 +1,6 @@
 pub mod loader;
 pub mod ofl;
 pub mod patch;
-pub mod registry;
\ No newline at end of file
+pub mod registry;
+#[cfg(test)]
+pub mod test_library;
@@ -89,9 +89,148 @@ pub struct OflCapability {
     pub color: Option<String>,
     pub colors: Option<Vec<String>>,
     pub comment: Option<String>,
+    #[serde(rename = "angleStart")]
+    pub angle_start: Option<String>,
+    #[serde(rename = "angleEnd")]
+    pub angle_end: Option<String>,
+    #[serde(rename = "colorTemperature")]
+    pub color_temperature: Option<String>,
+    #[serde(rename = "colorTemperatureStart")]
+    pub color_temperature_start: Option<String>,
+    #[serde(rename = "colorTemperatureEnd")]
+    pub color_temperature_end: Option<String>,
+    /// Switching channel alias -> the channel it behaves as while this range is selected
+    #[serde(rename = "switchChannels")]
+    pub switch_channels: Option<HashMap<String, String>>,
     // Add more fields as needed for different capability types
 }
 
+impl OflCapability {
+    /// DMX range covered, inclusive. A capability without one spans the whole channel
+    pub fn range(&self) -> (u8, u8) {
+        match self.dmx_range.as_deref() {
+            Some([start, end]) => (*start, *end),
+            _ => (0, 255),
+        }
+    }
+
+    pub fn contains(&self, value: u8) -> bool {
+        let (start, end) = self.range();
+        (start..=end).contains(&value)
+    }
+
+    /// Operator-facing name: the comment, else the color(s), else the capability type
+    pub fn label(&self) -> String {
+        if let Some(comment) = &self.comment {
+            comment.clone()
+        } else if let Some(color) = &self.color {
+            color.clone()
+        } else if let Some(colors) = &self.colors {
+            colors.join("/")
+        } else {
+            self.capability_type.clone()
+        }
+    }
+
+    /// Angle in degrees at `value`, for capabilities with an angle range such as Pan or Tilt
+    pub fn angle_at(&self, value: u8) -> Option<f32> {
+        let start = parse_degrees(self.angle_start.as_deref()?)?;
+        let end = parse_degrees(self.angle_end.as_deref()?)?;
+        let (range_start, range_end) = self.range();
+        if range_end <= range_start {
+            return Some(start);
+        }
+
+        let position =
+            (value.saturating_sub(range_start)) as f32 / (range_end - range_start) as f32;
+        Some(start + (end - start) * position.min(1.0))
+    }
+
+    /// Color temperatures in Kelvin at the start and end of the range, for
+    /// ColorTemperature capabilities that give them as e.g. "3200K"
+    pub fn kelvin_range(&self) -> Option<(f32, f32)> {
+        match (
+            self.color_temperature_start.as_deref(),
+            self.color_temperature_end.as_deref(),
+        ) {
+            (Some(start), Some(end)) => Some((parse_kelvin(start)?, parse_kelvin(end)?)),
+            _ => {
+                let kelvin = parse_kelvin(self.color_temperature.as_deref()?)?;
+                Some((kelvin, kelvin))
+            }
+        }
+    }
+
+    /// DMX value giving `kelvin`, as a fraction of a step so 16-bit channels
+    /// can use the precision. None if the range doesn't cover it.
+    pub fn value_for_kelvin(&self, kelvin: f32) -> Option<f32> {
+        let (start, end) = self.kelvin_range()?;
+        if kelvin < start.min(end) || kelvin > start.max(end) {
+            return None;
+        }
+
+        let (range_start, range_end) = self.range();
+        let position = if start == end {
+            0.0
+        } else {
+            (kelvin - start) / (end - start)
+        };
+        Some(range_start as f32 + (range_end - range_start) as f32 * position)
+    }
+}
+
+impl OflChannel {
+    /// Every capability, whether the channel defines one or several
+    pub fn all_capabilities(&self) -> impl Iterator<Item = &OflCapability> {
+        self.capability
+            .iter()
+            .chain(self.capabilities.iter().flatten())
+    }
+}
+
+impl OflFixture {
+    /// Resolve a switching channel alias used in a mode, returning the name
+    /// of the channel whose value selects its function and the channel it
+    /// behaves as by default (from the controlling channel's first range)
+    pub fn switching_channel(&self, alias: &str) -> Option<(&str, &str)> {
+        self.available_channels
+            .iter()
+            .find_map(|(controller, channel)| {
+                channel
+                    .all_capabilities()
+                    .find_map(|capability| capability.switch_channels.as_ref()?.get(alias))
+                    .map(|default| (controller.as_str(), default.as_str()))
+            })
+    }
+
+    /// Resolve a fine channel alias used in a mode, returning the name of the
+    /// coarse channel it belongs to and its place among that channel's fine
+    /// aliases (0 for the low byte of a 16-bit value)
+    pub fn fine_channel(&self, alias: &str) -> Option<(&str, usize)> {
+        self.available_channels
+            .iter()
+            .find_map(|(coarse, channel)| {
+                channel
+                    .fine_channel_aliases
+                    .as_ref()?
+                    .iter()
+                    .position(|fine| fine == alias)
+                    .map(|position| (coarse.as_str(), position))
+            })
+    }
+}
+
+/// Parse an OFL angle such as "-270deg"
+fn parse_degrees(angle: &str) -> Option<f32> {
+    angle.strip_suffix("deg")?.parse().ok()
+}
+
+/// Parse an OFL color temperature such as "3200K". Relative ones like
+/// "warm" don't say where they are, so they give None.
+fn parse_kelvin(temperature: &str) -> Option<f32> {
+    temperature.strip_suffix('K')?.parse().ok()
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct OflMode {
     pub name: String,
@@ -118,3 +257,42 @@ pub struct OflManufacturer {
     #[serde(rename = "rdmId")]
     pub rdm_id: Option<u32>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capability(json: &str) -> OflCapability {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_capability_angle_and_label() {
+        let pan = capability(r#"{"type": "Pan", "angleStart": "-270deg", "angleEnd": "270deg"}"#);
+        assert_eq!(pan.angle_at(0), Some(-270.0));
+        assert_eq!(pan.angle_at(255), Some(270.0));
+        assert!(pan.contains(128));
+
+        let gobo =
+            capability(r#"{"dmxRange": [32, 47], "type": "WheelSlot", "comment": "Gobo 2"}"#);
+        assert!(gobo.contains(37));
+        assert!(!gobo.contains(48));
+        assert_eq!(gobo.label(), "Gobo 2");
+        assert_eq!(gobo.angle_at(37), None);
+    }
+
+    #[test]
+    fn test_color_temperature_range() {
+        let cct = capability(
+            r#"{"type": "ColorTemperature", "colorTemperatureStart": "6500K", "colorTemperatureEnd": "2700K"}"#,
+        );
+        assert_eq!(cct.kelvin_range(), Some((6500.0, 2700.0)));
+        assert_eq!(cct.value_for_kelvin(6500.0), Some(0.0));
+        assert_eq!(cct.value_for_kelvin(2700.0), Some(255.0));
+        assert_eq!(cct.value_for_kelvin(4600.0), Some(127.5));
+        assert_eq!(cct.value_for_kelvin(8000.0), None);
+
+        let warm = capability(r#"{"type": "ColorTemperature", "colorTemperature": "warm"}"#);
+        assert_eq!(warm.kelvin_range(), None);
+    }
+}
@@ -1,30 +1,130 @@
-use crate::fixture::ofl::{OflFixture, OflMode};
+use crate::fixture::ofl::{OflCapability, OflFixture, OflMode};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::sync::Arc;
 use std::sync::LazyLock;
 
 /// These are the patch entries in the universe
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 #[allow(unused)]
 pub struct PatchedFixture {
     pub id: String,
+    /// OFL mode the profile was built from, so a saved show can rebuild it
+    pub mode: String,
     pub channel: usize,
     pub profile: Arc<FixtureProfile>,
     pub dmx_start: u16,
     pub label: String,
+    /// Muted fixtures ignore commands and are held dark at output
+    pub muted: bool,
+    /// Highest value each color channel may be driven to
+    pub color_limits: HashMap<ChannelType, u8>,
+    /// Values by offset that `neutral home` returns to, recorded with `sethome`
+    pub home: HashMap<u8, u8>,
+    /// Response applied to intensity at output
+    pub curve: DimmerCurve,
+}
+
+impl PatchedFixture {
+    /// DMX buffer index for a channel offset within this fixture
+    pub fn buffer_index(&self, offset: u8) -> usize {
+        self.dmx_start as usize + offset as usize + 1
+    }
+
+    /// Offsets of every Intensity or Dimmer channel
+    pub fn intensity_offsets(&self) -> Vec<u8> {
+        self.profile
+            .channels
+            .iter()
+            .filter(|(ctype, _)| matches!(ctype, ChannelType::Intensity | ChannelType::Dimmer))
+            .map(|(_, offset)| *offset)
+            .collect()
+    }
+
+    /// Offsets to zero to put the fixture out: its intensity channels, or its
+    /// whole footprint when it has none
+    pub fn dark_offsets(&self) -> Vec<u8> {
+        let offsets = self.intensity_offsets();
+        if offsets.is_empty() {
+            (0..self.profile.footprint).collect()
+        } else {
+            offsets
+        }
+    }
+}
+
+/// How a fixture's intensity level maps to what it is sent. Curves are
+/// applied to the final output level, after any fade, so a fade still moves
+/// evenly through the levels and only the fixture's response changes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DimmerCurve {
+    #[default]
+    Linear,
+    /// Level squared: slow at the bottom, for LEDs that jump on at low levels
+    SquareLaw,
+    /// Square root: fast at the bottom
+    InvSquareLaw,
+    /// Smoothstep: slow at both ends, fast through the middle
+    SCurve,
+}
+
+impl DimmerCurve {
+    /// Curve by name as typed in the CLI
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "linear" => Some(DimmerCurve::Linear),
+            "square" | "squarelaw" => Some(DimmerCurve::SquareLaw),
+            "invsquare" | "invsquarelaw" => Some(DimmerCurve::InvSquareLaw),
+            "s" | "scurve" => Some(DimmerCurve::SCurve),
+            _ => None,
+        }
+    }
+
+    /// Map a level through the curve. 0 and 255 always map to themselves.
+    pub fn apply(&self, level: u8) -> u8 {
+        (self.shape(level as f32 / 255.0) * 255.0).round() as u8
+    }
+
+    /// `apply` for a 16-bit level, as a dimmer with a fine channel carries
+    pub fn apply_16bit(&self, level: u16) -> u16 {
+        (self.shape(level as f32 / 65535.0) * 65535.0).round() as u16
+    }
+
+    fn shape(&self, t: f32) -> f32 {
+        match self {
+            DimmerCurve::Linear => t,
+            DimmerCurve::SquareLaw => t * t,
+            DimmerCurve::InvSquareLaw => t.sqrt(),
+            DimmerCurve::SCurve => t * t * (3.0 - 2.0 * t),
+        }
+    }
 }
 
 /// describes one fixture type (ex, source four conventional)
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct FixtureProfile {
     pub name: String,
     pub footprint: u8,
-    /// Type, offset
-    pub channels: HashMap<ChannelType, u8>,
+    /// Type, offset in channel order. A type can appear more than once (e.g. dual dimmers)
+    pub channels: Vec<(ChannelType, u8)>,
+    /// OFL channel name at each offset, as the mode lists them
+    pub channel_names: Vec<String>,
+    /// OFL capabilities (ranges, gobo and color slot names) keyed by offset
+    pub capabilities: HashMap<u8, Vec<OflCapability>>,
+    /// OFL categories such as "Moving Head" or "Color Changer"
+    pub categories: Vec<String>,
+    /// Offsets whose function depends on another channel's value (OFL switching
+    /// channels), mapped to the offset of the channel that selects it. These are
+    /// typed by their default function.
+    pub switches: HashMap<u8, u8>,
+    /// Offsets of 16-bit channels mapped to the offset of their fine channel,
+    /// from the OFL fine channel aliases the mode lists
+    pub fine_channels: HashMap<u8, u8>,
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 #[allow(unused)]
 pub enum ChannelType {
     // Color channels
@@ -77,6 +177,25 @@ pub enum ChannelType {
 }
 
 impl ChannelType {
+    /// Whether this is an emitter color channel
+    pub fn is_color(&self) -> bool {
+        matches!(
+            self,
+            ChannelType::Red
+                | ChannelType::Green
+                | ChannelType::Blue
+                | ChannelType::Amber
+                | ChannelType::Lime
+                | ChannelType::Cyan
+                | ChannelType::Magenta
+                | ChannelType::Yellow
+                | ChannelType::White
+                | ChannelType::WarmWhite
+                | ChannelType::CoolWhite
+                | ChannelType::Uv
+        )
+    }
+
     /// Convert from OFL capability type string to ChannelType
     pub fn from_ofl_capability_type(capability_type: &str) -> Self {
         match capability_type {
@@ -143,17 +262,112 @@ pub static ETC_SOURCE_FOUR_CONVENTIONAL: LazyLock<Arc<FixtureProfile>> = LazyLoc
     Arc::new(FixtureProfile {
         name: "ETC Source Four Conventional".to_string(),
         footprint: 1,
-        channels: [(ChannelType::Intensity, 0u8)].into_iter().collect(),
+        channels: vec![(ChannelType::Intensity, 0u8)],
+        channel_names: vec!["Intensity".to_string()],
+        capabilities: HashMap::new(),
+        categories: vec!["Dimmer".to_string()],
+        switches: HashMap::new(),
+        fine_channels: HashMap::new(),
     })
 });
 
 impl FixtureProfile {
-    /// Create a FixtureProfile from an OFL fixture and mode
-    pub fn from_ofl_fixture(ofl_fixture: &OflFixture, mode: &OflMode) -> Self {
-        let mut channels = HashMap::new();
+    /// Offset of the first channel of a type
+    pub fn offset_of(&self, channel_type: &ChannelType) -> Option<u8> {
+        self.offset_of_instance(channel_type, 0)
+    }
+
+    /// Offset of the Nth (0-based) channel of a type, for fixtures that repeat a function
+    pub fn offset_of_instance(&self, channel_type: &ChannelType, instance: usize) -> Option<u8> {
+        self.channels
+            .iter()
+            .filter(|(ctype, _)| ctype == channel_type)
+            .nth(instance)
+            .map(|(_, offset)| *offset)
+    }
+
+    /// Offset of the fine channel carrying the low byte of the first channel
+    /// of a type, if the mode has one
+    pub fn fine_offset_of(&self, channel_type: &ChannelType) -> Option<u8> {
+        let coarse = self.offset_of(channel_type)?;
+        self.fine_channels.get(&coarse).copied()
+    }
+
+    /// Offset of a channel by its OFL name (case-insensitive), falling back to
+    /// the channel type the name maps to, e.g. "Dimmer" for a channel called "Master Dimmer"
+    pub fn offset_of_name(&self, channel_name: &str) -> Option<u8> {
+        let channel_name = channel_name.trim();
+        let by_name = self
+            .channel_names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(channel_name))
+            .map(|offset| offset as u8);
+
+        by_name.or_else(|| match ChannelType::from_ofl_channel_name(channel_name) {
+            ChannelType::Custom(_) => None,
+            channel_type => self.offset_of(&channel_type),
+        })
+    }
+
+    /// Offset of the gobo wheel: a channel typed Gobo, else the first whose
+    /// OFL name mentions a gobo and which lists wheel slots, as "Gobo Wheel"
+    /// does. Color wheels list wheel slots too, hence the name check.
+    pub fn gobo_offset(&self) -> Option<u8> {
+        self.offset_of(&ChannelType::Gobo).or_else(|| {
+            (0..self.channel_names.len() as u8).find(|&offset| {
+                self.channel_names[offset as usize]
+                    .to_lowercase()
+                    .contains("gobo")
+                    && self.wheel_slots(offset).next().is_some()
+            })
+        })
+    }
+
+    /// The WheelSlot capabilities of a channel, in wheel order
+    pub fn wheel_slots(&self, offset: u8) -> impl Iterator<Item = &OflCapability> {
+        self.capabilities
+            .get(&offset)
+            .into_iter()
+            .flatten()
+            .filter(|capability| capability.capability_type == "WheelSlot")
+    }
+
+    /// Create a FixtureProfile from an OFL fixture and mode. A mode with no
+    /// channels (or too many to address) is rejected, since a fixture patched
+    /// from it would occupy no addresses and ignore every command.
+    pub fn from_ofl_fixture(ofl_fixture: &OflFixture, mode: &OflMode) -> Result<Self> {
+        let footprint = u8::try_from(mode.channels.len())
+            .ok()
+            .filter(|footprint| *footprint > 0)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Mode '{}' of '{}' has {} channels; a profile needs 1 to 255",
+                    mode.name,
+                    ofl_fixture.name,
+                    mode.channels.len()
+                )
+            })?;
+
+        let mut channels = Vec::new();
+        let mut capabilities = HashMap::new();
+        let mut switches = HashMap::new();
+        let mut fine_channels = HashMap::new();
+
+        for (channel_offset, mode_channel) in mode.channels.iter().enumerate() {
+            // Look up the channel definition in the OFL fixture. A switching channel
+            // alias isn't defined itself, so fall back to the channel it defaults to.
+            let mut channel_name = mode_channel.as_str();
+            if !ofl_fixture.available_channels.contains_key(channel_name) {
+                if let Some((controller, default)) = ofl_fixture.switching_channel(channel_name) {
+                    if let Some(controller_offset) =
+                        mode.channels.iter().position(|name| name == controller)
+                    {
+                        switches.insert(channel_offset as u8, controller_offset as u8);
+                    }
+                    channel_name = default;
+                }
+            }
 
-        for (channel_offset, channel_name) in mode.channels.iter().enumerate() {
-            // Look up the channel definition in the OFL fixture
             if let Some(channel_def) = ofl_fixture.available_channels.get(channel_name) {
                 // First try to infer from the channel name, as this is usually more specific
                 let channel_type_from_name = ChannelType::from_ofl_channel_name(channel_name);
@@ -190,14 +404,47 @@ impl FixtureProfile {
                     _ => channel_type_from_name,
                 };
 
-                channels.insert(channel_type, channel_offset as u8);
+                channels.push((channel_type, channel_offset as u8));
+
+                let channel_capabilities: Vec<OflCapability> =
+                    channel_def.all_capabilities().cloned().collect();
+                capabilities.insert(channel_offset as u8, channel_capabilities);
             }
         }
 
-        FixtureProfile {
+        // Fine channel aliases aren't defined themselves. Only the first alias
+        // (the low byte of a 16-bit value) is recorded; finer bytes stay untyped.
+        for (fine_offset, mode_channel) in mode.channels.iter().enumerate() {
+            let Some((coarse, 0)) = ofl_fixture.fine_channel(mode_channel) else {
+                continue;
+            };
+            let Some(coarse_offset) = mode.channels.iter().position(|name| name == coarse) else {
+                continue;
+            };
+            let coarse_type = channels
+                .iter()
+                .find(|(_, offset)| *offset as usize == coarse_offset)
+                .map(|(ctype, _)| ctype);
+            let fine_type = match coarse_type {
+                Some(ChannelType::Pan) => ChannelType::PanFine,
+                Some(ChannelType::Tilt) => ChannelType::TiltFine,
+                _ => ChannelType::Custom(mode_channel.clone()),
+            };
+
+            channels.push((fine_type, fine_offset as u8));
+            fine_channels.insert(coarse_offset as u8, fine_offset as u8);
+        }
+        channels.sort_by_key(|(_, offset)| *offset);
+
+        Ok(FixtureProfile {
             name: format!("{} ({})", ofl_fixture.name, mode.name),
-            footprint: mode.channels.len() as u8,
+            footprint,
             channels,
-        }
+            channel_names: mode.channels.clone(),
+            capabilities,
+            categories: ofl_fixture.categories.clone(),
+            switches,
+            fine_channels,
+        })
     }
 }
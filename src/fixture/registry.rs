@@ -1,29 +1,166 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::env;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::fixture::{
     loader::FixtureLoader,
-    ofl::{OflFixture, OflManufacturer},
-    patch::{FixtureProfile, PatchedFixture},
+    ofl::{OflChannel, OflFixture, OflManufacturer},
+    patch::{DimmerCurve, FixtureProfile, PatchedFixture},
 };
 
 use anyhow::{anyhow, Result};
 
+/// Environment variable naming the fixture library directory
+pub const FIXTURE_DATA_ENV: &str = "LIGHTS_FIXTURE_DATA";
+
+/// Directory name looked for next to the executable and in the working directory
+const FIXTURE_DATA_DIR: &str = "fixture-data";
+
+/// Find the fixture library, trying in order an explicit path, the
+/// `LIGHTS_FIXTURE_DATA` environment variable, `fixture-data` next to the
+/// executable, then `fixture-data` in the working directory. Returns the
+/// directory and a description of where it came from.
+pub fn locate_fixture_data(explicit: Option<&Path>) -> Option<(PathBuf, String)> {
+    let mut candidates: Vec<(PathBuf, String)> = Vec::new();
+
+    if let Some(path) = explicit {
+        candidates.push((path.to_path_buf(), "--fixtures".to_string()));
+    }
+    if let Some(path) = env::var_os(FIXTURE_DATA_ENV) {
+        candidates.push((PathBuf::from(path), FIXTURE_DATA_ENV.to_string()));
+    }
+    if let Some(exe_dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        candidates.push((
+            exe_dir.join(FIXTURE_DATA_DIR),
+            "executable directory".to_string(),
+        ));
+    }
+    candidates.push((
+        PathBuf::from(FIXTURE_DATA_DIR),
+        "working directory".to_string(),
+    ));
+
+    candidates.into_iter().find(|(path, source)| {
+        let found = path.join("manufacturers.json").is_file();
+        if !found && source == "--fixtures" {
+            eprintln!("No fixture library at {}", path.display());
+        }
+        found
+    })
+}
+
+/// Which part of a fixture a search term matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchField {
+    Name,
+    ShortName,
+    Category,
+}
+
+/// One fixture found by `search_fixtures`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FixtureMatch {
+    pub manufacturer: String,
+    pub fixture: String,
+    pub field: MatchField,
+    /// The text that matched, e.g. the short name or category
+    pub matched: String,
+}
+
+/// Outcome of parsing every fixture in the library
+#[derive(Debug, Default)]
+pub struct LibraryCheck {
+    pub ok: usize,
+    /// Fixture files that failed to parse, with the error and where in the file it is
+    pub failures: Vec<(PathBuf, String)>,
+}
+
+/// A mode both fixtures define, but with different channel lists
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModeDiff {
+    pub mode: String,
+    pub a_channels: Vec<String>,
+    pub b_channels: Vec<String>,
+}
+
+/// How two fixture definitions differ, from `compare_fixtures`. Only the parts
+/// that change how a fixture patches are compared: modes and their channel
+/// lists, and the available channels with their capabilities.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FixtureDiff {
+    pub modes_only_in_a: Vec<String>,
+    pub modes_only_in_b: Vec<String>,
+    pub changed_modes: Vec<ModeDiff>,
+    pub channels_only_in_a: Vec<String>,
+    pub channels_only_in_b: Vec<String>,
+    /// Channels both define whose capability types or ranges differ
+    pub changed_channels: Vec<String>,
+}
+
+impl FixtureDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == FixtureDiff::default()
+    }
+}
+
 /// Registry for managing fixture definitions and creating patched fixtures
 pub struct FixtureRegistry {
     loader: FixtureLoader,
     profile_cache: HashMap<String, Arc<FixtureProfile>>, // Key: "manufacturer/fixture/mode"
+    rdm_index: HashMap<u32, String>,                     // Key: RDM manufacturer ID
 }
 
 impl FixtureRegistry {
     pub fn new<P: AsRef<Path>>(fixture_data_path: P) -> Result<Self> {
-        let mut loader = FixtureLoader::new(fixture_data_path);
+        Self::from_loader(FixtureLoader::new(fixture_data_path))
+    }
+
+    /// Registry restricted to a whitelist of manufacturer directories (e.g.
+    /// `&["etc", "chauvet-dj"]`). Discovery and search never look outside it.
+    pub fn new_filtered<P: AsRef<Path>>(
+        fixture_data_path: P,
+        manufacturers: &[&str],
+    ) -> Result<Self> {
+        Self::from_loader(FixtureLoader::with_manufacturers(
+            fixture_data_path,
+            manufacturers,
+        ))
+    }
+
+    /// Registry with no fixture library, for running without one. Nothing can
+    /// be patched from it, but the rest of the board still works.
+    pub fn empty() -> Self {
+        FixtureRegistry {
+            loader: FixtureLoader::with_manufacturers(PathBuf::new(), &[]),
+            profile_cache: HashMap::new(),
+            rdm_index: HashMap::new(),
+        }
+    }
+
+    fn from_loader(mut loader: FixtureLoader) -> Result<Self> {
         loader.load_manufacturers()?;
 
+        // Index manufacturers by RDM ID once, so discovery lookups don't scan
+        let rdm_index = loader
+            .get_manufacturers()
+            .map(|manufacturers| {
+                manufacturers
+                    .iter()
+                    .filter_map(|(key, manufacturer)| {
+                        manufacturer.rdm_id.map(|id| (id, key.clone()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
         Ok(FixtureRegistry {
             loader,
             profile_cache: HashMap::new(),
+            rdm_index,
         })
     }
 
@@ -32,6 +169,12 @@ impl FixtureRegistry {
         self.loader.get_manufacturers()
     }
 
+    /// Look up a manufacturer by the ESTA manufacturer ID from an RDM UID
+    pub fn manufacturer_by_rdm_id(&self, id: u32) -> Option<&OflManufacturer> {
+        let key = self.rdm_index.get(&id)?;
+        self.get_manufacturers()?.get(key)
+    }
+
     /// Get available fixtures for a manufacturer
     pub fn get_fixtures_for_manufacturer(
         &self,
@@ -80,7 +223,7 @@ impl FixtureRegistry {
             })?;
 
         // Create the profile
-        let profile = Arc::new(FixtureProfile::from_ofl_fixture(fixture, mode));
+        let profile = Arc::new(FixtureProfile::from_ofl_fixture(fixture, mode)?);
 
         // Cache it
         self.profile_cache.insert(cache_key, profile.clone());
@@ -102,10 +245,15 @@ impl FixtureRegistry {
 
         Ok(PatchedFixture {
             id: format!("{}/{}", manufacturer, fixture_name),
+            mode: mode_name.to_string(),
             channel,
             profile,
             dmx_start,
             label,
+            muted: false,
+            color_limits: HashMap::new(),
+            home: HashMap::new(),
+            curve: DimmerCurve::default(),
         })
     }
 
@@ -116,19 +264,47 @@ impl FixtureRegistry {
         self.loader.discover_all_fixtures()
     }
 
-    /// Search for fixtures by name (case-insensitive partial match)
-    pub fn search_fixtures(
-        &self,
-        search_term: &str,
-    ) -> Result<Vec<(String, String)>> {
+    /// Search for fixtures (case-insensitive partial match) by file name, and
+    /// unless `name_only` is set, by OFL short name and category too. The
+    /// broader search parses every fixture file, so `name_only` is much faster.
+    /// Files that fail to parse are skipped.
+    pub fn search_fixtures(&self, search_term: &str, name_only: bool) -> Result<Vec<FixtureMatch>> {
         let all_fixtures = self.discover_all_fixtures()?;
         let search_lower = search_term.to_lowercase();
         let mut results = Vec::new();
 
         for (manufacturer, fixtures) in all_fixtures {
             for fixture in fixtures {
+                let found = |field, matched: &str| FixtureMatch {
+                    manufacturer: manufacturer.clone(),
+                    fixture: fixture.clone(),
+                    field,
+                    matched: matched.to_string(),
+                };
+
                 if fixture.to_lowercase().contains(&search_lower) {
-                    results.push((manufacturer.clone(), fixture));
+                    results.push(found(MatchField::Name, &fixture));
+                    continue;
+                }
+                if name_only {
+                    continue;
+                }
+
+                let Ok(ofl) = self.loader.read_fixture(&manufacturer, &fixture) else {
+                    continue;
+                };
+                if let Some(short_name) = ofl
+                    .short_name
+                    .as_deref()
+                    .filter(|short_name| short_name.to_lowercase().contains(&search_lower))
+                {
+                    results.push(found(MatchField::ShortName, short_name));
+                } else if let Some(category) = ofl
+                    .categories
+                    .iter()
+                    .find(|category| category.to_lowercase().contains(&search_lower))
+                {
+                    results.push(found(MatchField::Category, category));
                 }
             }
         }
@@ -137,6 +313,30 @@ impl FixtureRegistry {
         Ok(results)
     }
 
+    /// Parse every fixture file in the library, so broken files show up
+    /// before someone tries to patch them
+    pub fn check_all(&self) -> Result<LibraryCheck> {
+        let mut check = LibraryCheck::default();
+
+        let mut all_fixtures: Vec<(String, Vec<String>)> =
+            self.discover_all_fixtures()?.into_iter().collect();
+        all_fixtures.sort();
+
+        for (manufacturer, fixtures) in all_fixtures {
+            for fixture in fixtures {
+                match self.loader.read_fixture(&manufacturer, &fixture) {
+                    Ok(_) => check.ok += 1,
+                    Err(e) => check.failures.push((
+                        self.loader.fixture_path(&manufacturer, &fixture),
+                        e.to_string(),
+                    )),
+                }
+            }
+        }
+
+        Ok(check)
+    }
+
     /// Get fixture information (returns the loaded OFL fixture data)
     pub fn get_fixture_info(
         &mut self,
@@ -146,6 +346,66 @@ impl FixtureRegistry {
         self.loader.load_fixture(manufacturer, fixture_name)
     }
 
+    /// Compare two fixture definitions, given as (manufacturer, fixture), to
+    /// decide which of two near-duplicate library entries to keep. Read-only,
+    /// like `check_all`, so it can run from the shared registry.
+    pub fn compare_fixtures(&self, a: (&str, &str), b: (&str, &str)) -> Result<FixtureDiff> {
+        let a = self.loader.read_fixture(a.0, a.1)?;
+        let b = self.loader.read_fixture(b.0, b.1)?;
+
+        let mut diff = FixtureDiff::default();
+
+        for mode in &a.modes {
+            match b.modes.iter().find(|other| other.name == mode.name) {
+                None => diff.modes_only_in_a.push(mode.name.clone()),
+                Some(other) if other.channels != mode.channels => {
+                    diff.changed_modes.push(ModeDiff {
+                        mode: mode.name.clone(),
+                        a_channels: mode.channels.clone(),
+                        b_channels: other.channels.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        diff.modes_only_in_b = b
+            .modes
+            .iter()
+            .filter(|mode| !a.modes.iter().any(|other| other.name == mode.name))
+            .map(|mode| mode.name.clone())
+            .collect();
+
+        // Capability labels and comments are often reworded between copies,
+        // so only the type and range of each capability count
+        let shape = |channel: &OflChannel| -> Vec<(String, (u8, u8))> {
+            channel
+                .all_capabilities()
+                .map(|capability| (capability.capability_type.clone(), capability.range()))
+                .collect()
+        };
+        for (name, channel) in &a.available_channels {
+            match b.available_channels.get(name) {
+                None => diff.channels_only_in_a.push(name.clone()),
+                Some(other) if shape(channel) != shape(other) => {
+                    diff.changed_channels.push(name.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        diff.channels_only_in_b = b
+            .available_channels
+            .keys()
+            .filter(|name| !a.available_channels.contains_key(*name))
+            .cloned()
+            .collect();
+
+        diff.channels_only_in_a.sort();
+        diff.channels_only_in_b.sort();
+        diff.changed_channels.sort();
+
+        Ok(diff)
+    }
+
     /// List all cached profiles
     pub fn get_cached_profiles(&self) -> &HashMap<String, Arc<FixtureProfile>> {
         &self.profile_cache
@@ -155,6 +415,7 @@ impl FixtureRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixture::{patch::ChannelType, test_library::write_library};
     use std::path::Path;
 
     #[test]
@@ -172,9 +433,224 @@ mod tests {
             assert!(!all_fixtures.is_empty());
 
             // Test search
-            let search_results = registry.search_fixtures("par").unwrap();
+            let search_results = registry.search_fixtures("par", true).unwrap();
             // Should find some fixtures with "par" in the name
             println!("Found {} fixtures matching 'par'", search_results.len());
+
+            // Test RDM manufacturer lookup against the loaded database
+            for manufacturer in manufacturers.unwrap().values() {
+                if let Some(id) = manufacturer.rdm_id {
+                    let found = registry.manufacturer_by_rdm_id(id).unwrap();
+                    assert_eq!(found.rdm_id, Some(id));
+                }
+            }
         }
     }
+
+    #[test]
+    fn test_manufacturer_by_rdm_id() {
+        let dir = write_library("rdm", &[]);
+        let registry = FixtureRegistry::new(&dir).unwrap();
+
+        let acme = registry.manufacturer_by_rdm_id(0x7FF0).unwrap();
+        assert_eq!(acme.name, "Acme");
+        assert!(registry.manufacturer_by_rdm_id(0x0526).is_none());
+        assert!(FixtureRegistry::empty()
+            .manufacturer_by_rdm_id(0x7FF0)
+            .is_none());
+    }
+
+    #[test]
+    fn test_empty_registry_finds_nothing() {
+        let mut registry = FixtureRegistry::empty();
+
+        assert!(registry.get_manufacturers().is_none());
+        assert!(registry.discover_all_fixtures().unwrap().is_empty());
+        assert!(registry.search_fixtures("par", false).unwrap().is_empty());
+        assert!(registry
+            .get_fixture_profile("etc", "colorsource-par", "5 Channel (Default)")
+            .is_err());
+    }
+
+    #[test]
+    fn test_locate_fixture_data_prefers_explicit_path() {
+        if Path::new("fixture-data/manufacturers.json").exists() {
+            let (path, source) = locate_fixture_data(Some(Path::new("fixture-data"))).unwrap();
+            assert_eq!(path, Path::new("fixture-data"));
+            assert_eq!(source, "--fixtures");
+        }
+    }
+
+    #[test]
+    fn test_filtered_registry_searches_only_whitelist() {
+        if Path::new("fixture-data/etc").exists() {
+            let registry = FixtureRegistry::new_filtered("fixture-data", &["etc"]).unwrap();
+
+            assert!(!registry
+                .search_fixtures("colorsource", true)
+                .unwrap()
+                .is_empty());
+            assert!(registry
+                .search_fixtures("slimpar", false)
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn test_check_all_reports_broken_files() {
+        let dir = write_library("checklib", &[("broken", r#"{"name": 5}"#)]);
+
+        let registry = FixtureRegistry::new(&dir).unwrap();
+        let check = registry.check_all().unwrap();
+
+        assert_eq!(check.ok, 0);
+        assert_eq!(check.failures.len(), 1);
+        assert!(check.failures[0].0.ends_with("acme/broken.json"));
+        assert!(check.failures[0].1.contains("line 1"));
+    }
+
+    #[test]
+    fn test_search_matches_short_name() {
+        if Path::new("fixture-data/chauvet-dj/slimpar-pro-w.json").exists() {
+            let registry = FixtureRegistry::new_filtered("fixture-data", &["chauvet-dj"]).unwrap();
+
+            let results = registry
+                .search_fixtures("ChauvetSlimPARProW", false)
+                .unwrap();
+            assert!(results
+                .iter()
+                .any(|result| result.fixture == "slimpar-pro-w"
+                    && result.field == MatchField::ShortName));
+
+            // Name-only search doesn't open the files
+            let results = registry
+                .search_fixtures("ChauvetSlimPARProW", true)
+                .unwrap();
+            assert!(results.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_switching_channel_records_its_controller() {
+        if Path::new("fixture-data/uking/par-light-b262.json").exists() {
+            let mut registry = FixtureRegistry::new_filtered("fixture-data", &["uking"]).unwrap();
+            let profile = registry
+                .get_fixture_profile("uking", "par-light-b262", "7-channel")
+                .unwrap();
+
+            // "Hue Selection / Speed" is switched by the Mode channel before it
+            assert_eq!(profile.switches.get(&6), Some(&5));
+            assert_eq!(profile.switches.len(), 1);
+            assert!(profile.channels.iter().any(|(_, offset)| *offset == 6));
+        }
+    }
+
+    #[test]
+    fn test_mode_without_channels_is_rejected() {
+        let fixture = r#"{
+            "name": "Hollow",
+            "categories": ["Other"],
+            "meta": {"authors": [], "createDate": "2024-01-01", "lastModifyDate": "2024-01-01"},
+            "availableChannels": {},
+            "modes": [{"name": "Empty", "shortName": "0ch", "channels": []}],
+            "fixtureKey": "hollow",
+            "manufacturerKey": "acme"
+        }"#;
+        let dir = write_library("empty-mode", &[("hollow", fixture)]);
+
+        let mut registry = FixtureRegistry::new(&dir).unwrap();
+        let result =
+            registry.create_patched_fixture("acme", "hollow", "Empty", 1, 1, "Hollow".to_string());
+
+        let error = result.err().unwrap().to_string();
+        assert!(error.contains("has 0 channels"), "{}", error);
+    }
+
+    #[test]
+    fn test_compare_fixtures_reports_mode_and_channel_differences() {
+        let fixture = |key: &str, channels: &str, modes: &str| {
+            format!(
+                r#"{{
+                    "name": "Par",
+                    "categories": ["Color Changer"],
+                    "meta": {{"authors": [], "createDate": "2024-01-01", "lastModifyDate": "2024-01-01"}},
+                    "availableChannels": {{{}}},
+                    "modes": [{}],
+                    "fixtureKey": "{}",
+                    "manufacturerKey": "acme"
+                }}"#,
+                channels, modes, key
+            )
+        };
+        let old = fixture(
+            "par-old",
+            r#""Dimmer": {"capability": {"type": "Intensity"}},
+               "Red": {"capability": {"type": "ColorIntensity", "color": "Red"}}"#,
+            r#"{"name": "2ch", "shortName": "2ch", "channels": ["Dimmer", "Red"]},
+               {"name": "1ch", "shortName": "1ch", "channels": ["Dimmer"]}"#,
+        );
+        let new = fixture(
+            "par-new",
+            r#""Dimmer": {"capability": {"type": "Intensity", "comment": "reworded"}},
+               "Red": {"capabilities": [
+                   {"dmxRange": [0, 127], "type": "ColorIntensity", "color": "Red"},
+                   {"dmxRange": [128, 255], "type": "StrobeSpeed"}]},
+               "Strobe": {"capability": {"type": "StrobeSpeed"}}"#,
+            r#"{"name": "2ch", "shortName": "2ch", "channels": ["Red", "Dimmer"]},
+               {"name": "3ch", "shortName": "3ch", "channels": ["Dimmer", "Red", "Strobe"]}"#,
+        );
+        let dir = write_library("compare", &[("par-old", &old), ("par-new", &new)]);
+
+        let registry = FixtureRegistry::new(&dir).unwrap();
+        let diff = registry
+            .compare_fixtures(("acme", "par-old"), ("acme", "par-new"))
+            .unwrap();
+        let same = registry.compare_fixtures(("acme", "par-old"), ("acme", "par-old"));
+
+        assert_eq!(diff.modes_only_in_a, vec!["1ch"]);
+        assert_eq!(diff.modes_only_in_b, vec!["3ch"]);
+        assert_eq!(diff.changed_modes.len(), 1);
+        assert_eq!(diff.changed_modes[0].mode, "2ch");
+        assert!(diff.channels_only_in_a.is_empty());
+        assert_eq!(diff.channels_only_in_b, vec!["Strobe"]);
+        // A reworded comment is not a difference, a split range is
+        assert_eq!(diff.changed_channels, vec!["Red"]);
+        assert!(same.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fine_channel_aliases_pair_with_their_coarse_channel() {
+        let fixture = r#"{
+            "name": "Mover",
+            "categories": ["Moving Head"],
+            "meta": {"authors": [], "createDate": "2024-01-01", "lastModifyDate": "2024-01-01"},
+            "availableChannels": {
+                "Pan": {"fineChannelAliases": ["Pan fine"], "capability": {"type": "Pan"}},
+                "Tilt": {"fineChannelAliases": ["Tilt fine", "Tilt fine^2"], "capability": {"type": "Tilt"}},
+                "Dimmer": {"capability": {"type": "Intensity"}}
+            },
+            "modes": [
+                {"name": "16bit", "shortName": "16bit", "channels": ["Pan", "Pan fine", "Tilt", "Tilt fine", "Tilt fine^2", "Dimmer"]},
+                {"name": "8bit", "shortName": "8bit", "channels": ["Pan", "Tilt", "Dimmer"]}
+            ],
+            "fixtureKey": "mover",
+            "manufacturerKey": "acme"
+        }"#;
+        let dir = write_library("fine", &[("mover", fixture)]);
+
+        let mut registry = FixtureRegistry::new(&dir).unwrap();
+        let wide = registry
+            .get_fixture_profile("acme", "mover", "16bit")
+            .unwrap();
+        let narrow = registry.get_fixture_profile("acme", "mover", "8bit");
+
+        assert_eq!(wide.fine_channels, HashMap::from([(0, 1), (2, 3)]));
+        assert_eq!(wide.offset_of(&ChannelType::PanFine), Some(1));
+        assert_eq!(wide.offset_of(&ChannelType::TiltFine), Some(3));
+        assert_eq!(wide.fine_offset_of(&ChannelType::Tilt), Some(3));
+        assert_eq!(wide.fine_offset_of(&ChannelType::Dimmer), None);
+        assert_eq!(wide.offset_of(&ChannelType::Dimmer), Some(5));
+        assert!(narrow.unwrap().fine_channels.is_empty());
+    }
 }
@@ -0,0 +1,45 @@
+use std::{
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+/// A fixture library written to the temp directory for a test. It is removed
+/// when dropped, so a failing assertion doesn't leave it behind.
+pub struct TempLibrary(PathBuf);
+
+impl Deref for TempLibrary {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempLibrary {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempLibrary {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+/// Write a library named `dir` with one manufacturer, `acme` (RDM ID 0x7FF0),
+/// holding each `(fixture key, JSON)` pair in `fixtures` as a file
+pub fn write_library(dir: &str, fixtures: &[(&str, &str)]) -> TempLibrary {
+    let path = std::env::temp_dir().join(format!("lights-{}-{}", dir, std::process::id()));
+    fs::create_dir_all(path.join("acme")).unwrap();
+    fs::write(
+        path.join("manufacturers.json"),
+        r#"{"acme": {"name": "Acme", "rdmId": 32752}}"#,
+    )
+    .unwrap();
+    for (key, json) in fixtures {
+        fs::write(path.join("acme").join(format!("{}.json", key)), json).unwrap();
+    }
+    TempLibrary(path)
+}
@@ -1,36 +1,110 @@
 mod cli;
+#[cfg(test)]
+mod fake_dmx;
 mod fixture;
 mod universe;
+#[cfg(feature = "ws-server")]
+mod ws_server;
 
-use std::{ffi::CString, thread};
+use std::{
+    str::FromStr,
+    sync::{mpsc::Sender, Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
 
 use crate::{
     cli::run_cli,
-    fixture::registry::FixtureRegistry,
-    universe::{cue::CueEngine, dmx_thread, Universe},
+    fixture::registry::{locate_fixture_data, FixtureRegistry},
+    universe::{
+        cue::CueEngine,
+        dmx_thread,
+        sink::{
+            ArtNetSink, DmxSink, SacnSink, SacnSource, SerialSink, DEFAULT_SACN_PRIORITY,
+            DEFAULT_SACN_SOURCE_NAME,
+        },
+        DmxConfig, Universe, UniverseOutput,
+    },
 };
 
-// Include the bindgen-generated bindings
+// Include the bindgen-generated bindings. Tests use an in-memory fake with the
+// same functions instead, so the FFI calls are checked without an interface.
+#[cfg(not(test))]
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+#[cfg(test)]
+use fake_dmx::{dmx_close, dmx_open, dmx_read_frame, dmx_send_break, dmx_write};
 
 fn main() {
     // Create command channel
     let (command_tx, command_rx) = std::sync::mpsc::channel();
     let (shutdown_tx, shutdown_rx) = std::sync::mpsc::channel();
 
-    let mut registry = match FixtureRegistry::new("fixture-data") {
-        Ok(registry) => {
-            println!("✓ Loaded fixture database from fixture-data/");
-            registry
+    // `--fixtures <path>` overrides where the fixture library is looked for
+    let args: Vec<String> = std::env::args().collect();
+    let explicit_fixtures = args
+        .iter()
+        .position(|arg| arg == "--fixtures")
+        .and_then(|idx| args.get(idx + 1))
+        .map(std::path::PathBuf::from);
+
+    // `--manufacturers=etc,chauvet-dj` only loads those brands, for faster startup
+    let only_manufacturers = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--manufacturers="));
+
+    let mut registry = match locate_fixture_data(explicit_fixtures.as_deref()) {
+        Some((path, source)) => {
+            let registry = match only_manufacturers {
+                Some(list) => {
+                    let list: Vec<&str> = list.split(',').map(str::trim).collect();
+                    FixtureRegistry::new_filtered(&path, &list)
+                }
+                None => FixtureRegistry::new(&path),
+            };
+
+            match registry {
+                Ok(registry) => {
+                    println!(
+                        "✓ Loaded fixture database from {} ({})",
+                        path.display(),
+                        source
+                    );
+                    registry
+                }
+                Err(e) => {
+                    println!("⚠ Could not load fixture database: {}", e);
+                    FixtureRegistry::empty()
+                }
+            }
+        }
+        None => {
+            println!("⚠ No fixture database found, continuing without fixture profiles");
+            FixtureRegistry::empty()
         }
-        Err(e) => {
-            println!("⚠ Could not load fixture database: {}", e);
+    };
+
+    // Setup DMX output; see `open_sinks` for the flags
+    let sinks = match open_sinks(&args) {
+        Ok(sinks) => sinks,
+        Err(error) => {
+            eprintln!("{}", error);
             return;
         }
     };
 
-    // Create universe (will be moved to DMX thread)
-    let mut universe = Universe::new(0);
+    // Create a universe per sink (moved to the DMX thread)
+    // `--no-default-white` patches color fixtures black instead of white
+    let default_white = !args.iter().any(|arg| arg == "--no-default-white");
+    let mut outputs: Vec<UniverseOutput> = sinks
+        .into_iter()
+        .map(|(id, sink)| {
+            let mut universe = Universe::new(id);
+            universe.default_white = default_white;
+            UniverseOutput { universe, sink }
+        })
+        .collect();
 
     match registry.create_patched_fixture(
         "etc",
@@ -40,90 +114,148 @@ fn main() {
         10, // DMX start address 10
         "Front wash".to_string(),
     ) {
-        Ok(fixture) => universe.add_fixture(fixture),
+        Ok(fixture) => outputs[0].universe.add_fixture(fixture),
         Err(error) => eprintln!("Error adding fixture: {}", error),
     }
 
-    // Setup DMX
-    let port = CString::new("COM3").expect("Failed to create port string");
-    let fd = unsafe { dmx_open(port.as_ptr()) };
-
-    #[cfg(not(feature = "no-dmx"))]
-    if fd < 0 {
-        eprintln!("Failed to open DMX port COM3");
-        return;
+    // `--command-budget-us=<n>` caps how long each tick spends on commands
+    let mut dmx_config = DmxConfig::default();
+    if let Some(budget) = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--command-budget-us="))
+    {
+        match budget.parse() {
+            Ok(micros) => dmx_config.command_budget = Duration::from_micros(micros),
+            Err(_) => eprintln!("Ignoring invalid --command-budget-us value '{}'", budget),
+        }
     }
 
-    // Start DMX thread (takes ownership of universe)
-    let dmx_handle = thread::spawn(move || {
-        dmx_thread(universe, command_rx, shutdown_rx, fd);
+    // Start DMX thread (takes ownership of the universes)
+    let dmx_handle = Arc::new(Mutex::new(Some(thread::spawn(move || {
+        dmx_thread(outputs, command_rx, shutdown_rx, dmx_config);
+    }))));
+
+    // Ctrl-C takes the same path as `quit`, so the DMX thread fades out and closes
+    // the port. The first press shuts down on its own thread so a second press can
+    // still get through and force an exit if that hangs.
+    let mut interrupted = false;
+    let interrupt_shutdown = (shutdown_tx.clone(), dmx_handle.clone());
+    let handler = ctrlc::set_handler(move || {
+        if interrupted {
+            eprintln!("Forcing exit");
+            std::process::exit(130);
+        }
+        interrupted = true;
+        println!("\nInterrupted, fading out (Ctrl-C again to force exit)");
+
+        let (shutdown_tx, dmx_handle) = interrupt_shutdown.clone();
+        thread::spawn(move || {
+            shut_down(&shutdown_tx, &dmx_handle);
+            std::process::exit(130);
+        });
     });
+    if let Err(e) = handler {
+        eprintln!("Failed to install Ctrl-C handler: {}", e);
+    }
+
+    // Create cue engine with command sender, shared with any other front-ends
+    // `--no-duplicate-warning` records identical consecutive cues silently
+    let mut engine = CueEngine::new(command_tx.clone());
+    engine.warn_duplicate_cues = !std::env::args().any(|arg| arg == "--no-duplicate-warning");
+    let show = Arc::new(Mutex::new(engine));
 
-    // Create cue engine with command sender
-    let mut show = CueEngine::new(command_tx.clone());
+    #[cfg(feature = "ws-server")]
+    if let Err(e) = ws_server::start_ws_server("0.0.0.0:9001", command_tx.clone(), show.clone()) {
+        eprintln!("Failed to start WebSocket server: {}", e);
+    }
 
-    // run cli
-    run_cli(command_tx.clone(), &mut show);
+    // run cli, `--percent` shows intensity levels as percent first
+    let percent_mode = std::env::args().any(|arg| arg == "--percent");
+    run_cli(command_tx.clone(), &show, &mut registry, percent_mode);
 
-    // Shutdown
-    println!("Shutting down...");
-    shutdown_tx.send(()).ok();
-    dmx_handle.join().ok();
+    shut_down(&shutdown_tx, &dmx_handle);
 }
 
-#[allow(dead_code)]
-fn demonstrate_fixture_registry(registry: &mut FixtureRegistry) {
-    println!("\nFixture Database Information:");
-    println!("============================");
-
-    // Show available manufacturers
-    if let Some(manufacturers) = registry.get_manufacturers() {
-        println!("Available manufacturers: {}", manufacturers.len());
-        let mut mfg_names: Vec<_> = manufacturers.keys().collect();
-        mfg_names.sort();
-        for (i, name) in mfg_names.iter().take(5).enumerate() {
-            if let Some(mfg) = manufacturers.get(*name) {
-                println!("  {}. {} ({})", i + 1, mfg.name, name);
-            }
-        }
-        if manufacturers.len() > 5 {
-            println!("  ... and {} more", manufacturers.len() - 5);
-        }
+/// Value of a `--name=<n>` flag, or `default` when it isn't given
+fn numeric_flag<T: FromStr>(args: &[String], flag: &str, default: T) -> Result<T> {
+    match args.iter().find_map(|arg| arg.strip_prefix(flag)) {
+        None => Ok(default),
+        Some(value) => value
+            .parse()
+            .map_err(|_| anyhow!("{}{} is not a valid number", flag, value)),
     }
+}
 
-    // Try to show some ETC fixtures
-    match registry.get_fixtures_for_manufacturer("etc") {
-        Ok(fixtures) => {
-            println!("\nETC fixtures available: {}", fixtures.len());
-            for (i, fixture) in fixtures.iter().take(3).enumerate() {
-                println!("  {}. {}", i + 1, fixture);
-            }
-            if fixtures.len() > 3 {
-                println!("  ... and {} more", fixtures.len() - 3);
-            }
-        }
-        Err(_) => {
-            println!("\nNo ETC fixtures found");
-        }
+/// Pick where frames go: `--artnet=<ip>[:port]` sends Art-Net starting at the
+/// port-address from `--artnet-universe=<n>` (default 0), `--sacn` multicasts
+/// sACN starting at universe `--sacn-universe=<n>` (default 1) at
+/// `--sacn-priority=<n>`, as `--sacn-name=<name>` with component ID
+/// `--sacn-cid=<uuid>`, and otherwise the serial interface on COM3 is used.
+/// `--universes=<n>` drives that many consecutive universes over the network;
+/// the serial interface only carries one. Returns each universe's id and sink.
+fn open_sinks(args: &[String]) -> Result<Vec<(u8, Box<dyn DmxSink>)>> {
+    let count: u8 = numeric_flag(args, "--universes=", 1)?;
+    if count == 0 {
+        return Err(anyhow!("--universes= needs at least one universe"));
     }
+    let mut sinks: Vec<(u8, Box<dyn DmxSink>)> = Vec::new();
 
-    // Search for PAR fixtures
-    match registry.search_fixtures("par") {
-        Ok(results) => {
-            println!("\nPAR fixtures found: {}", results.len());
-            for (i, (mfg, fixture)) in results.iter().take(3).enumerate() {
-                println!("  {}. {} / {}", i + 1, mfg, fixture);
-            }
-            if results.len() > 3 {
-                println!("  ... and {} more", results.len() - 3);
-            }
+    if let Some(target) = args.iter().find_map(|arg| arg.strip_prefix("--artnet=")) {
+        let first: u16 = numeric_flag(args, "--artnet-universe=", 0)?;
+        let address = ArtNetSink::parse_target(target)?;
+        // Art-Net universes are numbered from 0 on the board, whatever their port-address
+        for index in 0..count {
+            let number = first.saturating_add(index.into());
+            sinks.push((index, Box::new(ArtNetSink::new(address, number)?)));
         }
-        Err(_) => {
-            println!("\nNo PAR fixtures found");
+        println!("✓ Sending Art-Net to {}", target);
+        return Ok(sinks);
+    }
+
+    if args.iter().any(|arg| arg == "--sacn") {
+        let first: u16 = numeric_flag(args, "--sacn-universe=", 1)?;
+        let priority = numeric_flag(args, "--sacn-priority=", DEFAULT_SACN_PRIORITY)?;
+        let flag = |flag: &str| args.iter().find_map(|arg| arg.strip_prefix(flag));
+        let source = SacnSource::new(
+            flag("--sacn-name=").unwrap_or(DEFAULT_SACN_SOURCE_NAME),
+            flag("--sacn-cid="),
+        )?;
+        for number in (first..).take(count.into()) {
+            let sink = SacnSink::new(number, priority, source.clone())?;
+            println!(
+                "✓ Sending sACN universe {} to {}",
+                number,
+                SacnSink::multicast_group(number)
+            );
+            // The board's universe is the sACN one, so `2/c 5 @ 100` style
+            // addressing matches what is on the network
+            sinks.push((number as u8, Box::new(sink)));
         }
+        return Ok(sinks);
     }
 
-    println!();
+    if count > 1 {
+        return Err(anyhow!(
+            "The serial interface carries one universe; use --artnet or --sacn for more"
+        ));
+    }
+    let sink = SerialSink::open("COM3")?;
+    #[cfg(not(feature = "no-dmx"))]
+    if !sink.is_open() {
+        return Err(anyhow!("Failed to open DMX port COM3"));
+    }
+    sinks.push((0, Box::new(sink)));
+    Ok(sinks)
+}
+
+/// Stop the DMX thread and wait for it to fade out and close the port. Safe to
+/// call more than once; only the first call waits.
+fn shut_down(shutdown_tx: &Sender<()>, dmx_handle: &Mutex<Option<JoinHandle<()>>>) {
+    println!("Shutting down...");
+    shutdown_tx.send(()).ok();
+    if let Some(handle) = dmx_handle.lock().unwrap().take() {
+        handle.join().ok();
+    }
 }
 
 /// reads from the dmx frame and dumps it to std out
@@ -0,0 +1,97 @@
+use std::time::{Duration, Instant};
+
+/// Internal show clock for rehearsal timing. It runs on wall time, so it keeps
+/// counting no matter how busy the DMX thread is.
+#[derive(Debug, Default)]
+pub struct ShowClock {
+    /// When the clock was last started, while it is running
+    started: Option<Instant>,
+    /// Time banked by earlier start/stop runs
+    banked: Duration,
+}
+
+impl ShowClock {
+    /// Start counting, carrying on from wherever the clock was stopped
+    pub fn start(&mut self) {
+        self.start_at(Instant::now());
+    }
+
+    /// Stop counting, keeping the elapsed time
+    pub fn stop(&mut self) {
+        self.stop_at(Instant::now());
+    }
+
+    /// Back to zero, stopped
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed_at(Instant::now())
+    }
+
+    fn start_at(&mut self, now: Instant) {
+        self.started.get_or_insert(now);
+    }
+
+    fn stop_at(&mut self, now: Instant) {
+        if let Some(started) = self.started.take() {
+            self.banked += now.duration_since(started);
+        }
+    }
+
+    fn elapsed_at(&self, now: Instant) -> Duration {
+        self.banked
+            + self
+                .started
+                .map(|started| now.duration_since(started))
+                .unwrap_or_default()
+    }
+}
+
+/// Format a clock reading as `HH:MM:SS.t`
+pub fn format_clock(elapsed: Duration) -> String {
+    let tenths = elapsed.as_millis() / 100;
+    format!(
+        "{:02}:{:02}:{:02}.{}",
+        tenths / 36_000,
+        tenths / 600 % 60,
+        tenths / 10 % 60,
+        tenths % 10
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_banks_time_across_stops() {
+        let mut clock = ShowClock::default();
+        let start = Instant::now();
+
+        clock.start_at(start);
+        // Starting again while running doesn't restart the count
+        clock.start_at(start + Duration::from_secs(5));
+        clock.stop_at(start + Duration::from_secs(10));
+        assert!(!clock.is_running());
+        assert_eq!(
+            clock.elapsed_at(start + Duration::from_secs(60)),
+            Duration::from_secs(10)
+        );
+
+        clock.start_at(start + Duration::from_secs(60));
+        assert_eq!(
+            clock.elapsed_at(start + Duration::from_millis(62_500)),
+            Duration::from_millis(12_500)
+        );
+
+        clock.reset();
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+        assert_eq!(format_clock(Duration::from_millis(3_723_450)), "01:02:03.4");
+    }
+}
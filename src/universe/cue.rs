@@ -1,149 +1,1357 @@
-use crate::universe::UniverseCommand;
-use std::{sync::mpsc::Sender, time::Duration};
+use crate::universe::{to_percent, FixtureInfo, UniverseCommand};
+use std::{collections::BTreeMap, sync::mpsc::Sender, time::Duration};
 use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
 
-pub struct CueEngine {
-    command_tx: Sender<UniverseCommand>,
+/// Longest fade a cue can store; anything longer is almost certainly a typo
+pub const MAX_FADE_TIME: Duration = Duration::from_secs(10 * 60);
+
+/// Clamp a requested fade time to `MAX_FADE_TIME`, warning when it had to
+pub fn clamp_fade_time(time_in_ms: u64) -> Duration {
+    let requested = Duration::from_millis(time_in_ms);
+    if requested > MAX_FADE_TIME {
+        eprintln!(
+            "Warning: fade time {}ms is longer than {}s, clamping",
+            time_in_ms,
+            MAX_FADE_TIME.as_secs()
+        );
+        MAX_FADE_TIME
+    } else {
+        requested
+    }
+}
+
+/// How long a cue takes to fade in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CueTime {
+    Fixed(Duration),
+    /// Use the time of the cue before it in the stack, so a run of cues keeps
+    /// in step when the first one's time is changed
+    Inherit,
+}
+
+impl CueTime {
+    pub fn from_millis(time_in_ms: u64) -> Self {
+        CueTime::Fixed(Duration::from_millis(time_in_ms))
+    }
+}
+
+/// Words the command line reads as syntax next to a cue name
+const RESERVED_CUE_NAMES: &[&str] = &["at", "in", "from", "to", "@", "+"];
+
+/// Trim a cue name, rejecting one that could never be typed back: empty,
+/// all whitespace, or a reserved word
+pub fn validate_cue_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("Cue name can't be empty"));
+    }
+    if RESERVED_CUE_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(name))
+    {
+        return Err(anyhow!("\"{}\" is reserved and can't be a cue name", name));
+    }
+    Ok(name)
+}
+
+/// Fade time in milliseconds for PlayCue, saturating rather than truncating
+fn fade_time_ms(duration: Duration) -> u32 {
+    u32::try_from(duration.as_millis()).unwrap_or(u32::MAX)
+}
+
+/// Playback that `go`/`back` drive when none is named
+pub const MAIN_PLAYBACK: &str = "main";
+
+/// One independent cue list with its own position, like a fader on a console
+#[derive(Default)]
+pub struct Playback {
     current_cue: Option<usize>,
+    /// Cue loaded with `load_cue`, which `save_editing_cue` writes back to
+    editing: Option<usize>,
     cues: Vec<Cue>,
 }
 
+impl Playback {
+    fn position(&self, name: &str) -> Result<usize> {
+        self.cues
+            .iter()
+            .position(|cue| cue.name == name)
+            .ok_or_else(|| anyhow!("There is no cue \"{}\"", name))
+    }
+
+    /// Index of the cue numbered just above the current one, or the first
+    /// cue if none is active or the current one is gone
+    pub fn next_cue(&self) -> Option<usize> {
+        let current = self
+            .current_cue
+            .filter(|&idx| idx < self.cues.len())
+            .map(|idx| self.cue_order(idx));
+
+        (0..self.cues.len())
+            .filter(|&idx| current.is_none_or(|current| self.cue_order(idx) > current))
+            .min_by(|&a, &b| self.cue_order(a).partial_cmp(&self.cue_order(b)).unwrap())
+    }
+
+    /// Index of the cue numbered just below the current one, or the last cue
+    /// if the current one is gone
+    pub fn prev_cue(&self) -> Option<usize> {
+        let current = self.current_cue?;
+        if current >= self.cues.len() {
+            return self.stack_order().last().copied();
+        }
+        let current = self.cue_order(current);
+
+        (0..self.cues.len())
+            .filter(|&idx| self.cue_order(idx) < current)
+            .max_by(|&a, &b| self.cue_order(a).partial_cmp(&self.cue_order(b)).unwrap())
+    }
+
+    /// Cue indices in stack order
+    pub fn stack_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.cues.len()).collect();
+        order.sort_by(|&a, &b| self.cue_order(a).partial_cmp(&self.cue_order(b)).unwrap());
+        order
+    }
+
+    /// The cue most recently played
+    pub fn current_cue(&self) -> Option<&Cue> {
+        self.cues.get(self.current_cue?)
+    }
+
+    /// The cue loaded for editing, if any
+    pub fn editing_cue(&self) -> Option<&Cue> {
+        self.cues.get(self.editing?)
+    }
+
+    /// The cue just before `cue_index` in stack order, if its levels are byte-identical
+    fn identical_predecessor(&self, cue_index: usize) -> Option<&Cue> {
+        let order = self.stack_order();
+        let position = order.iter().position(|&idx| idx == cue_index)?;
+        let previous = &self.cues[order[position.checked_sub(1)?]];
+
+        (previous.channels == self.cues[cue_index].channels).then_some(previous)
+    }
+
+    /// Fade time a cue plays with, following inherited times back up the
+    /// stack. A cue with nothing above it to inherit from snaps in.
+    fn fade_time(&self, cue_index: usize) -> Duration {
+        let order = self.stack_order();
+        let position = order
+            .iter()
+            .position(|&idx| idx == cue_index)
+            .expect("cue is in its own playback");
+
+        order[..=position]
+            .iter()
+            .rev()
+            .find_map(|&idx| match self.cues[idx].time_in() {
+                CueTime::Fixed(time) => Some(time),
+                CueTime::Inherit => None,
+            })
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Sort key for stack order: numbered cues by value, then unnumbered cues in record order
+    fn cue_order(&self, cue_index: usize) -> (f64, usize) {
+        let number = self
+            .cues
+            .get(cue_index)
+            .and_then(Cue::number)
+            .unwrap_or(f64::MAX);
+        (number, cue_index)
+    }
+}
+
+/// Where `record_cue` takes its levels from
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum RecordSource {
+    /// The merged output of every playback plus manual changes
+    #[default]
+    Live,
+    /// Only what the named playback's live cue contributes
+    Playback(String),
+}
+
+/// Runs any number of named playbacks. Recording and editing act on the
+/// selected playback; `go` and `back` name the playback they step.
+pub struct CueEngine {
+    command_tx: Sender<UniverseCommand>,
+    playbacks: BTreeMap<String, Playback>,
+    selected: String,
+    /// Named sets of control channels, driven together with `g <name>`
+    groups: BTreeMap<String, Vec<usize>>,
+    /// Warn when a recorded cue looks exactly like the cue before it
+    pub warn_duplicate_cues: bool,
+}
+
 impl CueEngine {
     pub fn new(command_tx: Sender<UniverseCommand>) -> Self {
+        let mut playbacks = BTreeMap::new();
+        playbacks.insert(MAIN_PLAYBACK.to_string(), Playback::default());
+
         Self {
             command_tx,
-            current_cue: None,
-            cues: Vec::new(),
+            playbacks,
+            selected: MAIN_PLAYBACK.to_string(),
+            groups: BTreeMap::new(),
+            warn_duplicate_cues: true,
         }
     }
 
-    pub fn record_cue(&mut self, name: &str, time_in: u64) -> Result<()> {
-        let (response_tx, response_rx) = std::sync::mpsc::channel();
+    /// Choose the playback that record, update, rename, delete and goto act on,
+    /// creating it if it doesn't exist yet
+    pub fn select_playback(&mut self, name: &str) {
+        self.playbacks.entry(name.to_string()).or_default();
+        self.selected = name.to_string();
+    }
 
-        self.command_tx
-            .send(UniverseCommand::GetDMXState(response_tx))
-            .with_context(|| "Failed to get DMX state")?;
+    pub fn selected_playback(&self) -> &str {
+        &self.selected
+    }
 
-        let state = response_rx
-            .recv_timeout(Duration::from_millis(100))
-            .with_context(|| "Timeout reciving DMX state")?;
+    /// Every playback by name, in name order
+    pub fn playbacks(&self) -> impl Iterator<Item = (&str, &Playback)> {
+        self.playbacks
+            .iter()
+            .map(|(name, playback)| (name.as_str(), playback))
+    }
+
+    /// Copy of every playback's cues by name, for saving a show
+    pub fn cue_lists(&self) -> BTreeMap<String, Vec<Cue>> {
+        self.playbacks
+            .iter()
+            .map(|(name, playback)| (name.clone(), playback.cues.clone()))
+            .collect()
+    }
+
+    /// Replace every playback with the given cue lists, as loaded from a show.
+    /// Nothing is live afterwards and the main playback is selected.
+    pub fn replace_cues(&mut self, cue_lists: BTreeMap<String, Vec<Cue>>) {
+        self.playbacks = cue_lists
+            .into_iter()
+            .map(|(name, cues)| {
+                let playback = Playback {
+                    cues,
+                    ..Default::default()
+                };
+                (name, playback)
+            })
+            .collect();
+        self.playbacks.entry(MAIN_PLAYBACK.to_string()).or_default();
+        self.selected = MAIN_PLAYBACK.to_string();
+    }
+
+    pub fn groups(&self) -> &BTreeMap<String, Vec<usize>> {
+        &self.groups
+    }
+
+    /// Member channels of a group, in the order they were given
+    pub fn group(&self, name: &str) -> Result<&[usize]> {
+        self.groups
+            .get(name)
+            .map(Vec::as_slice)
+            .ok_or_else(|| anyhow!("No group named '{}'", name))
+    }
+
+    /// Define a group, replacing any group of the same name. Returns the
+    /// members it had before, if it was a redefinition.
+    pub fn define_group(&mut self, name: &str, channels: Vec<usize>) -> Result<Option<Vec<usize>>> {
+        if name.is_empty() {
+            return Err(anyhow!("Group name cannot be empty"));
+        }
+        if channels.is_empty() {
+            return Err(anyhow!("Group {} needs at least one channel", name));
+        }
+        Ok(self.groups.insert(name.to_string(), channels))
+    }
+
+    pub fn delete_group(&mut self, name: &str) -> Result<Vec<usize>> {
+        self.groups
+            .remove(name)
+            .ok_or_else(|| anyhow!("No group named '{}'", name))
+    }
+
+    /// Replace every group, as loaded from a show
+    pub fn replace_groups(&mut self, groups: BTreeMap<String, Vec<usize>>) {
+        self.groups = groups;
+    }
+
+    fn playback(&self) -> &Playback {
+        &self.playbacks[&self.selected]
+    }
+
+    fn playback_mut(&mut self) -> &mut Playback {
+        self.playbacks
+            .get_mut(&self.selected)
+            .expect("selected playback always exists")
+    }
+
+    fn named_playback(&self, name: &str) -> Result<&Playback> {
+        self.playbacks
+            .get(name)
+            .ok_or_else(|| anyhow!("There is no playback \"{}\"", name))
+    }
+
+    /// Record a cue on the selected playback from `source`, replacing the
+    /// levels and fade of an existing cue with the same name
+    pub fn record_cue(
+        &mut self,
+        name: &str,
+        time_in: CueTime,
+        source: &RecordSource,
+    ) -> Result<()> {
+        let name = validate_cue_name(name)?;
+        let state = self.source_state(source)?;
+        let time_in = match time_in {
+            CueTime::Fixed(time) => CueTime::Fixed(clamp_fade_time(time.as_millis() as u64)),
+            CueTime::Inherit => CueTime::Inherit,
+        };
 
-        if let Some(cue_idx) = self.cues.iter().position(|cue| cue.name == name) {
-            self.cues[cue_idx].time_in = Duration::from_millis(time_in);
-            self.cues[cue_idx].channels = state;
+        let playback = self.playback_mut();
+        let cue_idx = if let Ok(cue_idx) = playback.position(name) {
+            playback.cues[cue_idx].time_in = time_in;
+            playback.cues[cue_idx].channels = state;
+            cue_idx
         } else {
-            self.cues.push(Cue {
+            playback.cues.push(Cue {
                 name: name.to_string(),
-                time_in: Duration::from_millis(time_in),
+                time_in,
                 channels: state,
+                master: u8::MAX,
             });
+            playback.cues.len() - 1
+        };
+
+        // Identical cues are sometimes wanted for timing, so this only warns
+        if self.warn_duplicate_cues {
+            if let Some(previous) = self.playback().identical_predecessor(cue_idx) {
+                println!(
+                    "Warning: cue {} has the same levels as cue {} before it",
+                    name, previous.name
+                );
+            }
         }
 
         Ok(())
     }
 
-    pub fn delete_cue(&mut self, cue_id: &str) -> Result<()> {
-        let cue_index = match self.cues.iter().position(|cue| cue.name == cue_id) {
-            Some(idx) => idx,
-            None => {
-                return Err(anyhow!("There is no cue \"{}\"", cue_id));
+    fn source_state(&self, source: &RecordSource) -> Result<[u8; 513]> {
+        match source {
+            RecordSource::Live => {
+                let (response_tx, response_rx) = std::sync::mpsc::channel();
+                self.command_tx
+                    .send(UniverseCommand::GetDMXState(response_tx))
+                    .with_context(|| "Failed to get DMX state")?;
+
+                response_rx
+                    .recv_timeout(Duration::from_millis(100))
+                    .with_context(|| "Timeout reciving DMX state")
             }
-        };
+            RecordSource::Playback(playback) => {
+                self.named_playback(playback)?;
+
+                let (response_tx, response_rx) = std::sync::mpsc::channel();
+                self.command_tx
+                    .send(UniverseCommand::GetPlaybackOutput {
+                        playback: playback.clone(),
+                        response: response_tx,
+                    })
+                    .with_context(|| "Failed to get playback output")?;
+
+                response_rx
+                    .recv_timeout(Duration::from_millis(100))
+                    .with_context(|| "Timeout reciving playback output")?
+                    .ok_or_else(|| anyhow!("Playback \"{}\" has no live cue", playback))
+            }
+        }
+    }
+
+    /// Update a cue in place with only the channels touched since the last
+    /// cue recall, leaving everything else as recorded. Returns how many
+    /// channels were written.
+    pub fn update_cue(&mut self, name: &str) -> Result<usize> {
+        let cue_idx = self.playback().position(name)?;
+        self.write_touched(cue_idx)
+    }
+
+    /// Copy the channels touched since the last cue recall into a cue on the
+    /// selected playback, returning how many there were
+    fn write_touched(&mut self, cue_idx: usize) -> Result<usize> {
+        let (response_tx, response_rx) = std::sync::mpsc::channel();
+        self.command_tx
+            .send(UniverseCommand::GetTouched(response_tx))
+            .with_context(|| "Failed to get touched channels")?;
+
+        let touched = response_rx
+            .recv_timeout(Duration::from_millis(100))
+            .with_context(|| "Timeout reciving touched channels")?;
+
+        let cue = &mut self.playback_mut().cues[cue_idx];
+        for (address, value) in &touched {
+            cue.channels[*address] = *value;
+        }
+
+        Ok(touched.len())
+    }
+
+    /// The non-zero channels stored in a cue as (address, value), without playing it
+    pub fn preview_cue(&self, name: &str) -> Result<Vec<(usize, u8)>> {
+        let playback = self.playback();
+        let cue = &playback.cues[playback.position(name)?];
+
+        Ok((1..cue.channels.len())
+            .filter(|&address| cue.channels[address] > 0)
+            .map(|address| (address, cue.channels[address]))
+            .collect())
+    }
+
+    /// The cue most recently played on the selected playback
+    pub fn current_cue(&self) -> Option<&Cue> {
+        self.playback().current_cue()
+    }
+
+    /// Non-zero addresses in a cue that fall outside every patched fixture,
+    /// usually left over from a fixture that has since been unpatched
+    pub fn orphan_channels(&self, name: &str) -> Result<Vec<usize>> {
+        let playback = self.playback();
+        let cue = &playback.cues[playback.position(name)?];
+
+        Ok(orphans(cue, &self.query_patch()?))
+    }
+
+    /// Every cue on the selected playback that has orphan channels, in stack
+    /// order, with those channels
+    pub fn validate_cues(&self) -> Result<Vec<(&str, Vec<usize>)>> {
+        let fixtures = self.query_patch()?;
+        let playback = self.playback();
+
+        Ok(playback
+            .stack_order()
+            .into_iter()
+            .map(|idx| &playback.cues[idx])
+            .map(|cue| (cue.name.as_str(), orphans(cue, &fixtures)))
+            .filter(|(_, orphans)| !orphans.is_empty())
+            .collect())
+    }
+
+    /// A printable cue sheet for the selected playback: each cue in stack
+    /// order with its fade time and what it changes from the cue before it,
+    /// described by the fixture labels in `fixtures`
+    pub fn export_cue_sheet(&self, fixtures: &[FixtureInfo]) -> String {
+        let playback = self.playback();
+        let mut sheet = format!("Cue sheet, playback {}\n\n", self.selected);
+        sheet += &format!(
+            "{:<6} {:<20} {:<14} What happens\n",
+            "Cue", "Label", "Fade in"
+        );
+
+        let mut previous = [0u8; 513];
+        for idx in playback.stack_order() {
+            let cue = &playback.cues[idx];
+            let (number, label) = match cue.number() {
+                Some(_) => (cue.name.as_str(), ""),
+                None => ("", cue.name.as_str()),
+            };
+            let fade = format_seconds(playback.fade_time(idx));
+            let fade = match cue.time_in {
+                CueTime::Fixed(_) => fade,
+                CueTime::Inherit => format!("{} (follows)", fade),
+            };
+
+            sheet += &format!(
+                "{:<6} {:<20} {:<14} {}\n",
+                number,
+                label,
+                fade,
+                summarize_changes(&previous, &cue.channels, fixtures)
+            );
+            previous = cue.channels;
+        }
+
+        sheet
+    }
+
+    fn query_patch(&self) -> Result<Vec<FixtureInfo>> {
+        let (response_tx, response_rx) = std::sync::mpsc::channel();
+        self.command_tx
+            .send(UniverseCommand::GetFixtures(response_tx))
+            .with_context(|| "Failed to get patched fixtures")?;
+
+        response_rx
+            .recv_timeout(Duration::from_millis(100))
+            .with_context(|| "Timeout reciving patched fixtures")
+    }
+
+    /// Put a cue's stored levels on stage for editing, without moving the
+    /// playback's position. Manual changes then land on top of it in the live
+    /// output, and `save_editing_cue` writes them back.
+    pub fn load_cue(&mut self, name: &str) -> Result<()> {
+        let cue_idx = self.playback().position(name)?;
+        let cue = &self.playback().cues[cue_idx];
+
+        self.command_tx
+            .send(UniverseCommand::PlayCue {
+                playback: self.selected.clone(),
+                cue_idx,
+                cue_data: cue.channels,
+                fade_time_ms: 0,
+                intensity_percent: 100,
+                master: cue.master,
+            })
+            .with_context(|| "Failed to send cue command")?;
+
+        self.playback_mut().editing = Some(cue_idx);
+        Ok(())
+    }
+
+    /// The cue loaded for editing on the selected playback
+    pub fn editing_cue(&self) -> Option<&Cue> {
+        self.playback().editing_cue()
+    }
+
+    /// Write the channels changed by hand since `load_cue` back into the cue
+    /// being edited, returning its name and how many channels were written.
+    /// Other playbacks and anything else in the live output stay out of it.
+    pub fn save_editing_cue(&mut self) -> Result<(String, usize)> {
+        let cue_idx = self
+            .playback()
+            .editing
+            .ok_or_else(|| anyhow!("No cue loaded for editing"))?;
+
+        let written = self.write_touched(cue_idx)?;
+        Ok((self.playback().cues[cue_idx].name.clone(), written))
+    }
+
+    /// Rename a cue. Stack order follows cue numbers, so renaming to a new
+    /// number moves the cue while the current-cue pointer stays on it.
+    pub fn rename_cue(&mut self, old: &str, new: &str) -> Result<()> {
+        let new = validate_cue_name(new)?;
+        let playback = self.playback_mut();
+        if playback.position(new).is_ok() {
+            return Err(anyhow!("There is already a cue \"{}\"", new));
+        }
+
+        let cue_idx = playback.position(old)?;
+        playback.cues[cue_idx].name = new.to_string();
+
+        Ok(())
+    }
+
+    pub fn delete_cue(&mut self, cue_id: &str) -> Result<()> {
+        let cue_index = self.playback().position(cue_id)?;
 
         self.delete_cue_idx(cue_index)
     }
 
     pub fn delete_cue_idx(&mut self, cue_index: usize) -> Result<()> {
-        if cue_index > self.cues.len() {
+        let playback = self.playback_mut();
+        if cue_index >= playback.cues.len() {
             return Err(anyhow!("Cue {} out of bounds", cue_index));
         }
-        self.cues.remove(cue_index);
+        playback.cues.remove(cue_index);
+
+        // Keep the live and edit pointers on the same cues, or drop them if
+        // their cue is gone
+        let shift = |pointer: Option<usize>| match pointer {
+            Some(idx) if idx == cue_index => None,
+            Some(idx) if idx > cue_index => Some(idx - 1),
+            pointer => pointer,
+        };
+        playback.current_cue = shift(playback.current_cue);
+        playback.editing = shift(playback.editing);
 
         Ok(())
     }
 
-    pub fn go(&mut self) -> Result<()> {
-        let next_cue_index = self.current_cue.map_or(0, |c| c + 1);
+    /// Every cue on the selected playback that sets a DMX address to a
+    /// non-zero value, in stack order
+    pub fn cues_using_channel(&self, addr: usize) -> Vec<&Cue> {
+        let playback = self.playback();
+        playback
+            .stack_order()
+            .into_iter()
+            .map(|idx| &playback.cues[idx])
+            .filter(|cue| cue.channels.get(addr).is_some_and(|value| *value > 0))
+            .collect()
+    }
 
-        if let Some(cue) = self.cues.get(next_cue_index) {
-            self.command_tx
-                .send(UniverseCommand::PlayCue {
-                    cue_idx: next_cue_index,
-                    cue_data: cue.channels.clone(),
-                    fade_time_ms: cue.time_in.as_millis() as u32,
-                })
-                .with_context(|| "Failed to send cue command")?;
+    /// Send one cue of a playback to the DMX thread and make it current.
+    /// `fade` overrides the cue's stored time for this transition only.
+    fn play(
+        &mut self,
+        playback: &str,
+        cue_idx: usize,
+        intensity_percent: u8,
+        fade: Option<Duration>,
+    ) -> Result<&Cue> {
+        let stack = self.named_playback(playback)?;
+        let cue = &stack.cues[cue_idx];
+        let fade = fade.unwrap_or_else(|| stack.fade_time(cue_idx));
+        self.command_tx
+            .send(UniverseCommand::PlayCue {
+                playback: playback.to_string(),
+                cue_idx,
+                cue_data: cue.channels,
+                fade_time_ms: fade_time_ms(fade),
+                intensity_percent,
+                master: cue.master,
+            })
+            .with_context(|| "Failed to send cue command")?;
 
-            self.current_cue = Some(next_cue_index);
-            println!("GO: Moving to cue {}", next_cue_index + 1);
-            Ok(())
+        let playback = self
+            .playbacks
+            .get_mut(playback)
+            .expect("playback was found above");
+        playback.current_cue = Some(cue_idx);
+        // Playing a cue ends any editing load on this playback
+        playback.editing = None;
+        Ok(&playback.cues[cue_idx])
+    }
+
+    /// Move the fader of the cue live on a playback. The level is stored in
+    /// the cue, so it plays back at that level next time too.
+    pub fn set_level(&mut self, playback: &str, level: u8) -> Result<&Cue> {
+        let playback_state = self
+            .playbacks
+            .get_mut(playback)
+            .ok_or_else(|| anyhow!("There is no playback \"{}\"", playback))?;
+        let cue_idx = playback_state
+            .current_cue
+            .ok_or_else(|| anyhow!("No cue is live on playback {}", playback))?;
+        playback_state
+            .cues
+            .get_mut(cue_idx)
+            .ok_or_else(|| anyhow!("The live cue on playback {} was deleted", playback))?
+            .master = level;
+
+        self.command_tx
+            .send(UniverseCommand::SetCueLevel {
+                playback: playback.to_string(),
+                cue_idx,
+                level,
+            })
+            .with_context(|| "Failed to send cue level command")?;
+
+        Ok(&self.playbacks[playback].cues[cue_idx])
+    }
+
+    /// Play the next cue on `playback`, fading in `fade` instead of the
+    /// cue's recorded time when given.
+    /// The override is one-shot; the stored time is left alone.
+    pub fn go_in(&mut self, playback: &str, fade: Option<Duration>) -> Result<()> {
+        let next_cue_index = self
+            .named_playback(playback)?
+            .next_cue()
+            .ok_or_else(|| anyhow!("No cue after the current cue"))?;
+
+        let cue = self.play(playback, next_cue_index, 100, fade)?;
+        let timing = match fade {
+            Some(fade) => format!(" in {}s", fade.as_secs_f32()),
+            None => String::new(),
+        };
+        if playback == MAIN_PLAYBACK {
+            println!("GO: Moving to cue {}{}", cue.name, timing);
         } else {
-            Err(anyhow!("No cue {} available", next_cue_index + 1))
-        }
-    }
-
-    pub fn back(&mut self) -> Result<()> {
-        if let Some(current) = self.current_cue {
-            if current > 0 {
-                let prev_cue_index = current - 1;
-
-                if let Some(cue) = self.cues.get(prev_cue_index) {
-                    self.command_tx
-                        .send(UniverseCommand::PlayCue {
-                            cue_idx: prev_cue_index,
-                            cue_data: cue.channels.clone(),
-                            fade_time_ms: cue.time_in.as_millis() as u32,
-                        })
-                        .with_context(|| "Failed to send cue command")?;
-
-                    self.current_cue = Some(prev_cue_index);
-                    println!("BACK: Moving to cue {}", prev_cue_index + 1);
-                    Ok(())
-                } else {
-                    Err(anyhow!("Previous cue not found"))
-                }
-            } else {
-                Err(anyhow!("Already at first cue"))
-            }
+            println!("GO {}: Moving to cue {}{}", playback, cue.name, timing);
+        }
+        Ok(())
+    }
+
+    pub fn back(&mut self, playback: &str) -> Result<()> {
+        let stack = self.named_playback(playback)?;
+        if stack.current_cue.is_none() {
+            return Err(anyhow!("No current cue"));
+        }
+        let prev_cue_index = stack
+            .prev_cue()
+            .ok_or_else(|| anyhow!("Already at first cue"))?;
+
+        let cue = self.play(playback, prev_cue_index, 100, None)?;
+        if playback == MAIN_PLAYBACK {
+            println!("BACK: Moving to cue {}", cue.name);
         } else {
-            Err(anyhow!("No current cue"))
+            println!("BACK {}: Moving to cue {}", playback, cue.name);
         }
+        Ok(())
     }
 
-    pub fn go_to_cue(&mut self, cue_id: &str) -> Result<()> {
-        let cue_index = match self.cues.iter().position(|cue| cue.name == cue_id) {
-            Some(idx) => idx,
-            None => {
-                return Err(anyhow!("There is no cue \"{}\"", cue_id));
-            }
-        };
+    /// Jump to a cue by name on the selected playback, playing its intensities
+    /// at `intensity_percent` (100 for the look as recorded)
+    pub fn go_to_cue(&mut self, cue_id: &str, intensity_percent: u8) -> Result<()> {
+        let cue_index = self.playback().position(cue_id)?;
 
-        self.go_to_cue_idx(cue_index)
+        self.go_to_cue_idx(cue_index + 1, intensity_percent)
     }
 
-    pub fn go_to_cue_idx(&mut self, cue_number: usize) -> Result<()> {
+    pub fn go_to_cue_idx(&mut self, cue_number: usize, intensity_percent: u8) -> Result<()> {
         let cue_index = cue_number.saturating_sub(1); // Convert 1-based to 0-based
+        if cue_index >= self.playback().cues.len() {
+            return Err(anyhow!("Cue {} not found", cue_number));
+        }
 
-        if let Some(cue) = self.cues.get(cue_index) {
-            self.command_tx
-                .send(UniverseCommand::PlayCue {
-                    cue_idx: cue_index,
-                    cue_data: cue.channels.clone(),
-                    fade_time_ms: cue.time_in.as_millis() as u32,
-                })
-                .with_context(|| "Failed to send cue command")?;
-
-            self.current_cue = Some(cue_index);
-            println!("GOTO: Jumped to cue {}", cue_number);
-            Ok(())
+        let playback = self.selected.clone();
+        let cue = self.play(&playback, cue_index, intensity_percent, None)?;
+        if intensity_percent == 100 {
+            println!("GOTO: Jumped to cue {}", cue.name);
         } else {
-            Err(anyhow!("Cue {} not found", cue_number))
+            println!("GOTO: Jumped to cue {} at {}%", cue.name, intensity_percent);
+        }
+        Ok(())
+    }
+}
+
+/// Non-zero addresses in a cue that no fixture in `fixtures` covers
+fn orphans(cue: &Cue, fixtures: &[FixtureInfo]) -> Vec<usize> {
+    (1..cue.channels.len())
+        .filter(|&address| cue.channels[address] > 0)
+        .filter(|&address| !fixtures.iter().any(|fixture| fixture.covers(address)))
+        .collect()
+}
+
+/// Seconds with a decimal only when needed, e.g. "3s" or "2.5s"
+fn format_seconds(time: Duration) -> String {
+    let seconds = time.as_secs_f32();
+    if seconds.fract() == 0.0 {
+        format!("{}s", seconds)
+    } else {
+        format!("{:.1}s", seconds)
+    }
+}
+
+/// One line on what a cue does to each fixture, going from `old` to `new`:
+/// where its intensity goes, or which other channels it moves
+fn summarize_changes(old: &[u8; 513], new: &[u8; 513], fixtures: &[FixtureInfo]) -> String {
+    let changes = diff_buffers(old, new);
+    let mut parts = Vec::new();
+
+    for fixture in fixtures {
+        let moved: Vec<&(usize, u8, u8)> = changes
+            .iter()
+            .filter(|(address, _, _)| fixture.covers(*address))
+            .collect();
+        if moved.is_empty() {
+            continue;
         }
+
+        let intensity = moved.iter().find(|(address, _, _)| {
+            matches!(fixture.channel_at(*address), Some("Intensity" | "Dimmer"))
+        });
+        let what = match intensity {
+            Some((_, _, 0)) => "out".to_string(),
+            Some((_, _, u8::MAX)) => "to full".to_string(),
+            Some((_, old, new)) if new > old => format!("up to {}%", to_percent(*new)),
+            Some((_, _, new)) => format!("down to {}%", to_percent(*new)),
+            None => {
+                let mut types: Vec<&str> = moved
+                    .iter()
+                    .filter_map(|(address, _, _)| fixture.channel_at(*address))
+                    .collect();
+                types.dedup();
+                format!("changes {}", types.join(", "))
+            }
+        };
+        parts.push(format!("{} ({}) {}", fixture.label, fixture.channel, what));
+    }
+
+    let unpatched = changes
+        .iter()
+        .filter(|(address, _, _)| !fixtures.iter().any(|fixture| fixture.covers(*address)))
+        .count();
+    if unpatched > 0 {
+        parts.push(format!("{} unpatched addresses", unpatched));
+    }
+
+    if parts.is_empty() {
+        "no change".to_string()
+    } else {
+        parts.join("; ")
     }
 }
 
+/// Every address whose value differs between two buffers, as (address, old, new).
+/// The start code at index 0 is ignored.
+pub fn diff_buffers(old: &[u8; 513], new: &[u8; 513]) -> Vec<(usize, u8, u8)> {
+    (1..old.len())
+        .filter(|&address| old[address] != new[address])
+        .map(|address| (address, old[address], new[address]))
+        .collect()
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Cue {
     name: String,
-    time_in: Duration,
+    time_in: CueTime,
+    #[serde(with = "dmx_buffer")]
     channels: [u8; 513],
+    /// Fader level the cue's intensities are played at, 255 for as recorded
+    master: u8,
+}
+
+impl Cue {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn channels(&self) -> &[u8; 513] {
+        &self.channels
+    }
+
+    pub fn time_in(&self) -> CueTime {
+        self.time_in
+    }
+
+    /// The cue's number, if its name is numeric (e.g. "5" or point cue "5.5")
+    pub fn number(&self) -> Option<f64> {
+        self.name
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|number| number.is_finite())
+    }
+}
+
+/// Stores a cue's 513 slots as a plain list, since serde stops at 32-element arrays
+mod dmx_buffer {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(buffer: &[u8; 513], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(buffer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 513], D::Error> {
+        let values = Vec::<u8>::deserialize(deserializer)?;
+        let length = values.len();
+        values
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(length, &"513 DMX slots"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn cue(name: &str) -> Cue {
+        Cue {
+            name: name.to_string(),
+            time_in: CueTime::Fixed(Duration::ZERO),
+            channels: [0; 513],
+            master: u8::MAX,
+        }
+    }
+
+    fn current_name(engine: &CueEngine) -> &str {
+        &engine.current_cue().unwrap().name
+    }
+
+    #[test]
+    fn test_go_back_follow_cue_numbers() {
+        let (command_tx, _command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+        engine.playback_mut().cues = vec![cue("1"), cue("5"), cue("6")];
+
+        // Point cue recorded after the rest of the stack
+        engine.playback_mut().cues.push(cue("5.5"));
+
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        assert_eq!(current_name(&engine), "1");
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        assert_eq!(current_name(&engine), "5");
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        assert_eq!(current_name(&engine), "5.5");
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        assert_eq!(current_name(&engine), "6");
+        assert!(engine.go_in(MAIN_PLAYBACK, None).is_err());
+
+        engine.back(MAIN_PLAYBACK).unwrap();
+        assert_eq!(current_name(&engine), "5.5");
+        engine.back(MAIN_PLAYBACK).unwrap();
+        assert_eq!(current_name(&engine), "5");
+    }
+
+    #[test]
+    fn test_next_prev_at_stack_edges() {
+        let (command_tx, _command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+        engine.playback_mut().cues = vec![cue("2"), cue("1")];
+
+        // Before the first cue only `go` can move
+        assert_eq!(engine.playback().prev_cue(), None);
+        assert_eq!(engine.playback().next_cue(), Some(1));
+        assert!(engine.back(MAIN_PLAYBACK).is_err());
+
+        engine.playback_mut().current_cue = Some(1);
+        assert_eq!(engine.playback().prev_cue(), None);
+
+        engine.playback_mut().current_cue = Some(0);
+        assert_eq!(engine.playback().next_cue(), None);
+        assert_eq!(engine.playback().prev_cue(), Some(1));
+
+        // A current cue that no longer exists falls back to either end
+        engine.playback_mut().current_cue = Some(2);
+        assert_eq!(engine.playback().next_cue(), Some(1));
+        assert_eq!(engine.playback().prev_cue(), Some(0));
+    }
+
+    #[test]
+    fn test_go_and_back_step_through_a_cue_inserted_mid_stack() {
+        let mut engine = engine_with_state([0; 513]);
+        let snap = CueTime::Fixed(Duration::ZERO);
+        for name in ["1", "5", "6"] {
+            engine.record_cue(name, snap, &RecordSource::Live).unwrap();
+        }
+
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        assert_eq!(current_name(&engine), "5");
+
+        // Recorded while sitting on 5, it still plays between 5 and 6
+        engine.record_cue("5.5", snap, &RecordSource::Live).unwrap();
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        assert_eq!(current_name(&engine), "5.5");
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        assert_eq!(current_name(&engine), "6");
+        assert!(engine.go_in(MAIN_PLAYBACK, None).is_err());
+
+        engine.back(MAIN_PLAYBACK).unwrap();
+        assert_eq!(current_name(&engine), "5.5");
+        engine.back(MAIN_PLAYBACK).unwrap();
+        assert_eq!(current_name(&engine), "5");
+        engine.back(MAIN_PLAYBACK).unwrap();
+        assert_eq!(current_name(&engine), "1");
+        assert!(engine.back(MAIN_PLAYBACK).is_err());
+    }
+
+    /// Engine whose DMX thread stand-in hands every command to `answer`
+    fn engine_answering(answer: impl Fn(UniverseCommand) + Send + 'static) -> CueEngine {
+        let (command_tx, command_rx) = mpsc::channel();
+        std::thread::spawn(move || command_rx.into_iter().for_each(answer));
+        CueEngine::new(command_tx)
+    }
+
+    /// Engine whose DMX thread stand-in answers state queries with `state`
+    fn engine_with_state(state: [u8; 513]) -> CueEngine {
+        engine_answering(move |command| {
+            if let UniverseCommand::GetDMXState(response) = command {
+                response.send(state).ok();
+            }
+        })
+    }
+
+    /// Engine whose DMX thread stand-in reports `touched` as set by hand
+    fn engine_with_touched(touched: Vec<(usize, u8)>) -> CueEngine {
+        engine_answering(move |command| {
+            if let UniverseCommand::GetTouched(response) = command {
+                response.send(touched.clone()).ok();
+            }
+        })
+    }
+
+    #[test]
+    fn test_record_cue_clamps_fade_time() {
+        let mut engine = engine_with_state([0; 513]);
+
+        let live = RecordSource::Live;
+        engine
+            .record_cue("1", CueTime::from_millis(300_000_000), &live)
+            .unwrap();
+        assert_eq!(
+            engine.playback().cues[0].time_in,
+            CueTime::Fixed(MAX_FADE_TIME)
+        );
+
+        engine
+            .record_cue("2", CueTime::from_millis(3_000), &live)
+            .unwrap();
+        assert_eq!(
+            engine.playback().cues[1].time_in,
+            CueTime::Fixed(Duration::from_secs(3))
+        );
+    }
+
+    #[test]
+    fn test_record_cue_rejects_empty_names() {
+        let mut engine = engine_with_state([0; 513]);
+        let snap = CueTime::Fixed(Duration::ZERO);
+        let live = RecordSource::Live;
+
+        assert!(engine.record_cue("", snap, &live).is_err());
+        assert!(engine.record_cue("  \t", snap, &live).is_err());
+        assert!(engine.record_cue("from", snap, &live).is_err());
+        assert!(engine.playback().cues.is_empty());
+
+        engine.record_cue(" 7 ", snap, &live).unwrap();
+        assert_eq!(engine.playback().cues[0].name, "7");
+        assert!(engine.rename_cue("7", " ").is_err());
+    }
+
+    #[test]
+    fn test_identical_predecessor_follows_stack_order() {
+        let (command_tx, _command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+        let mut lit = cue("2");
+        lit.channels[1] = 255;
+        engine.playback_mut().cues = vec![cue("3"), cue("1"), lit];
+
+        let playback = engine.playback();
+        assert!(playback.identical_predecessor(0).is_none());
+        assert!(playback.identical_predecessor(1).is_none());
+
+        engine.playback_mut().cues[2].channels[1] = 0;
+        let playback = engine.playback();
+        assert_eq!(playback.identical_predecessor(2).unwrap().name, "1");
+        assert_eq!(playback.identical_predecessor(0).unwrap().name, "2");
+    }
+
+    #[test]
+    fn test_update_cue_only_writes_touched_channels() {
+        let mut engine = engine_with_touched(vec![(5, 200)]);
+        let mut recorded = cue("1");
+        recorded.channels[5] = 100;
+        recorded.channels[6] = 50;
+        engine.playback_mut().cues = vec![recorded];
+
+        assert_eq!(engine.update_cue("1").unwrap(), 1);
+        assert_eq!(engine.playback().cues[0].channels[5], 200);
+        assert_eq!(engine.playback().cues[0].channels[6], 50);
+        assert!(engine.update_cue("2").is_err());
+    }
+
+    #[test]
+    fn test_preview_cue_lists_non_zero_channels() {
+        let (command_tx, command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+        let mut recorded = cue("1");
+        recorded.channels[3] = 255;
+        recorded.channels[40] = 12;
+        engine.playback_mut().cues = vec![recorded];
+
+        assert_eq!(engine.preview_cue("1").unwrap(), vec![(3, 255), (40, 12)]);
+        assert!(engine.preview_cue("2").is_err());
+        assert!(command_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_go_to_cue_by_name_with_scale() {
+        let (command_tx, command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+        engine.playback_mut().cues = vec![cue("1"), cue("2"), cue("3")];
+
+        engine.go_to_cue("2", 50).unwrap();
+        assert_eq!(current_name(&engine), "2");
+        assert!(matches!(
+            command_rx.try_recv().unwrap(),
+            UniverseCommand::PlayCue {
+                cue_idx: 1,
+                intensity_percent: 50,
+                ..
+            }
+        ));
+        assert!(engine.go_to_cue("4", 100).is_err());
+    }
+
+    #[test]
+    fn test_go_in_overrides_fade_once() {
+        let (command_tx, command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+        let mut slow = cue("1");
+        slow.time_in = CueTime::Fixed(Duration::from_secs(5));
+        engine.playback_mut().cues = vec![slow];
+
+        engine
+            .go_in(MAIN_PLAYBACK, Some(Duration::from_millis(500)))
+            .unwrap();
+        assert!(matches!(
+            command_rx.try_recv().unwrap(),
+            UniverseCommand::PlayCue {
+                fade_time_ms: 500,
+                ..
+            }
+        ));
+        assert_eq!(
+            engine.playback().cues[0].time_in,
+            CueTime::Fixed(Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn test_set_level_is_stored_in_the_live_cue() {
+        let (command_tx, command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+        engine.playback_mut().cues = vec![cue("1"), cue("2")];
+        assert!(engine.set_level(MAIN_PLAYBACK, 100).is_err());
+
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        engine.set_level(MAIN_PLAYBACK, 100).unwrap();
+        command_rx.try_recv().unwrap();
+        assert!(matches!(
+            command_rx.try_recv().unwrap(),
+            UniverseCommand::SetCueLevel {
+                cue_idx: 0,
+                level: 100,
+                ..
+            }
+        ));
+
+        // The stored level goes out with the cue next time it plays
+        engine.go_to_cue("1", 100).unwrap();
+        assert!(matches!(
+            command_rx.try_recv().unwrap(),
+            UniverseCommand::PlayCue { master: 100, .. }
+        ));
+        assert_eq!(engine.playback().cues[1].master, u8::MAX);
+
+        // A fader move after the live cue is deleted is an error, not a panic
+        engine.playback_mut().cues.truncate(0);
+        assert!(engine.set_level(MAIN_PLAYBACK, 50).is_err());
+    }
+
+    #[test]
+    fn test_orphan_channels_outside_patch() {
+        let mut engine = engine_answering(|command| {
+            if let UniverseCommand::GetFixtures(response) = command {
+                let fixture = FixtureInfo {
+                    channel: 1,
+                    label: String::new(),
+                    profile: String::new(),
+                    dmx_start: 10,
+                    footprint: 3,
+                    channels: Vec::new(),
+                    categories: Vec::new(),
+                    muted: false,
+                };
+                response.send(vec![fixture]).ok();
+            }
+        });
+        let mut stale = cue("1");
+        stale.channels[11] = 255;
+        stale.channels[13] = 255;
+        stale.channels[14] = 80;
+        engine.playback_mut().cues = vec![stale, cue("2")];
+
+        assert_eq!(engine.orphan_channels("1").unwrap(), vec![14]);
+        let invalid = engine.validate_cues().unwrap();
+        assert_eq!(invalid, vec![("1", vec![14])]);
+    }
+
+    #[test]
+    fn test_load_cue_then_save_writes_only_the_edits() {
+        let mut engine = engine_with_touched(vec![(7, 99)]);
+        let mut recorded = cue("2");
+        recorded.channels[8] = 40;
+        engine.playback_mut().cues = vec![cue("1"), recorded];
+        engine.playback_mut().current_cue = Some(0);
+
+        assert!(engine.save_editing_cue().is_err());
+        engine.load_cue("2").unwrap();
+        assert_eq!(engine.editing_cue().unwrap().name, "2");
+        assert_eq!(current_name(&engine), "1");
+
+        assert_eq!(engine.save_editing_cue().unwrap(), ("2".to_string(), 1));
+        assert_eq!(engine.playback().cues[1].channels[7], 99);
+        assert_eq!(engine.playback().cues[1].channels[8], 40);
+
+        engine.delete_cue("1").unwrap();
+        assert_eq!(engine.editing_cue().unwrap().name, "2");
+    }
+
+    #[test]
+    fn test_delete_cue_keeps_the_live_cue_pointer_in_step() {
+        let (command_tx, _command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+        engine.playback_mut().cues = vec![cue("1"), cue("2"), cue("3")];
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+
+        // Deleting a cue above the live one leaves it live
+        engine.delete_cue("1").unwrap();
+        assert_eq!(current_name(&engine), "2");
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        assert_eq!(current_name(&engine), "3");
+
+        // Deleting the live cue leaves nothing live
+        engine.delete_cue("3").unwrap();
+        assert!(engine.current_cue().is_none());
+        assert!(engine.set_level(MAIN_PLAYBACK, 128).is_err());
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        assert_eq!(current_name(&engine), "2");
+
+        assert!(engine.delete_cue_idx(1).is_err());
+    }
+
+    #[test]
+    fn test_fade_time_ms_saturates() {
+        let huge = Duration::from_millis(u32::MAX as u64 + 5);
+        assert_eq!(fade_time_ms(huge), u32::MAX);
+        assert_eq!(fade_time_ms(MAX_FADE_TIME), 600_000);
+    }
+
+    #[test]
+    fn test_rename_cue() {
+        let (command_tx, _command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+        engine.playback_mut().cues = vec![cue("1"), cue("2"), cue("3")];
+        engine.playback_mut().current_cue = Some(0);
+
+        assert!(engine.rename_cue("1", "2").is_err());
+        assert!(engine.rename_cue("4", "5").is_err());
+
+        // Renumbering the current cue past the others keeps it current
+        engine.rename_cue("1", "3.5").unwrap();
+        assert_eq!(current_name(&engine), "3.5");
+        assert_eq!(engine.playback().next_cue(), None);
+        assert_eq!(engine.playback().prev_cue(), Some(2));
+    }
+
+    #[test]
+    fn test_playbacks_step_independently() {
+        let (command_tx, command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+        engine.playback_mut().cues = vec![cue("1"), cue("2")];
+        engine.select_playback("house");
+        engine.playback_mut().cues = vec![cue("10")];
+
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        engine.go_in("house", None).unwrap();
+        engine.go_in(MAIN_PLAYBACK, None).unwrap();
+        assert!(engine.go_in("house", None).is_err());
+        assert!(engine.go_in("stage", None).is_err());
+
+        assert_eq!(current_name(&engine), "10");
+        engine.select_playback(MAIN_PLAYBACK);
+        assert_eq!(current_name(&engine), "2");
+
+        let played: Vec<String> = command_rx
+            .try_iter()
+            .filter_map(|command| match command {
+                UniverseCommand::PlayCue { playback, .. } => Some(playback),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(played, vec!["main", "house", "main"]);
+    }
+
+    #[test]
+    fn test_inherited_time_follows_the_cue_before() {
+        let (command_tx, command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+        let mut four = cue("4");
+        four.time_in = CueTime::Fixed(Duration::from_secs(2));
+        let mut five = cue("5");
+        five.time_in = CueTime::Inherit;
+        engine.playback_mut().cues = vec![five, four];
+
+        let fade_of_next_cue = |engine: &mut CueEngine| {
+            engine.go_to_cue("5", 100).unwrap();
+            match command_rx.try_recv().unwrap() {
+                UniverseCommand::PlayCue { fade_time_ms, .. } => fade_time_ms,
+                _ => panic!("expected a cue to play"),
+            }
+        };
+        assert_eq!(fade_of_next_cue(&mut engine), 2_000);
+
+        // Retiming 4 carries 5 along with it
+        engine.playback_mut().cues[1].time_in = CueTime::Fixed(Duration::from_secs(6));
+        assert_eq!(fade_of_next_cue(&mut engine), 6_000);
+
+        // With nothing before it to inherit from, a cue snaps in
+        engine.playback_mut().cues.remove(1);
+        assert_eq!(fade_of_next_cue(&mut engine), 0);
+    }
+
+    #[test]
+    fn test_cues_using_channel_leaves_out_zero_levels() {
+        let (command_tx, _command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+        let mut wash = cue("1");
+        wash.channels[5] = 200;
+        // Recording a zero is the same as not using the address
+        let mut spot = cue("2");
+        spot.channels[6] = 100;
+        engine.playback_mut().cues = vec![spot, wash];
+
+        let names = |address| -> Vec<String> {
+            engine
+                .cues_using_channel(address)
+                .iter()
+                .map(|cue| cue.name().to_string())
+                .collect()
+        };
+        assert_eq!(names(5), vec!["1"]);
+        assert_eq!(names(6), vec!["2"]);
+        assert!(names(7).is_empty());
+    }
+
+    #[test]
+    fn test_cue_sheet_describes_changes_by_fixture_label() {
+        let (command_tx, _command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+        let fixture =
+            |channel: usize, label: &str, dmx_start: u16, channels: &[&str]| FixtureInfo {
+                channel,
+                label: label.to_string(),
+                profile: String::new(),
+                dmx_start,
+                footprint: channels.len() as u8,
+                channels: channels
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, channel_type)| (channel_type.to_string(), offset as u8))
+                    .collect(),
+                categories: Vec::new(),
+                muted: false,
+            };
+        let fixtures = vec![
+            fixture(1, "Front wash", 0, &["Intensity"]),
+            fixture(2, "Band", 10, &["Intensity", "Red", "Green", "Blue"]),
+        ];
+
+        let mut opening = cue("1");
+        opening.time_in = CueTime::Fixed(Duration::from_millis(2500));
+        opening.channels[1] = 255;
+        opening.channels[11] = 255;
+        let mut dim_band = cue("Band down");
+        dim_band.time_in = CueTime::Inherit;
+        dim_band.channels = opening.channels;
+        dim_band.channels[11] = 102;
+        dim_band.channels[13] = 200;
+        dim_band.channels[300] = 9;
+        let mut wash = cue("2");
+        wash.channels = opening.channels;
+        wash.channels[12] = 40;
+        engine.playback_mut().cues = vec![opening, wash, dim_band];
+
+        let sheet = engine.export_cue_sheet(&fixtures);
+        let lines: Vec<&str> = sheet.lines().collect();
+        assert_eq!(lines[0], "Cue sheet, playback main");
+        assert!(lines[3].starts_with("1 "));
+        assert!(lines[3].contains("2.5s"));
+        assert!(lines[3].ends_with("Front wash (1) to full; Band (2) to full"));
+        assert!(lines[4].ends_with("Band (2) changes Red"));
+        // Unnumbered cues sort last and show their name as the label
+        assert!(lines[5].contains("Band down"));
+        assert!(lines[5].contains("0s (follows)"));
+        assert!(lines[5].ends_with("Band (2) down to 40%; 1 unpatched addresses"));
+    }
+
+    #[test]
+    fn test_groups_can_be_redefined_and_deleted() {
+        let (command_tx, _command_rx) = mpsc::channel();
+        let mut engine = CueEngine::new(command_tx);
+
+        assert!(engine.group("front").is_err());
+        assert!(engine.define_group("front", Vec::new()).is_err());
+        assert_eq!(engine.define_group("front", vec![1, 2, 3]).unwrap(), None);
+        assert_eq!(engine.group("front").unwrap(), &[1, 2, 3]);
+
+        let previous = engine.define_group("front", vec![4, 5]).unwrap();
+        assert_eq!(previous, Some(vec![1, 2, 3]));
+        assert_eq!(engine.group("front").unwrap(), &[4, 5]);
+
+        assert_eq!(engine.delete_group("front").unwrap(), vec![4, 5]);
+        assert!(engine.delete_group("front").is_err());
+        assert!(engine.groups().is_empty());
+    }
 }
@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+
+/// A cue crossfade in progress. The universe's buffer already holds where the
+/// fade is going; this only remembers where each address started, so writes
+/// that arrive mid-fade still land in the buffer as usual.
+#[derive(Debug, Clone)]
+pub struct Fade {
+    from: [u8; 513],
+    started: Instant,
+    duration: Duration,
+}
+
+impl Fade {
+    pub fn new(from: [u8; 513], started: Instant, duration: Duration) -> Self {
+        Self {
+            from,
+            started,
+            duration,
+        }
+    }
+
+    /// How far through the fade `now` is, from 0.0 to 1.0
+    pub fn progress(&self, now: Instant) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        let elapsed = now.saturating_duration_since(self.started);
+        (elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+
+    pub fn is_finished(&self, now: Instant) -> bool {
+        self.progress(now) >= 1.0
+    }
+
+    /// Every address linearly between where it started and `target`. The
+    /// start code in slot 0 is never interpolated; it comes from `target`.
+    pub fn blend(&self, target: &[u8; 513], now: Instant) -> [u8; 513] {
+        let progress = self.progress(now);
+        let mut output = *target;
+        for (address, value) in output.iter_mut().enumerate().skip(1) {
+            let from = self.from[address] as f32;
+            *value = (from + (*value as f32 - from) * progress).round() as u8;
+        }
+        output
+    }
+
+    /// Move one address straight to `value`, so a hand write mid-fade takes
+    /// effect at once instead of being faded into
+    pub fn snap(&mut self, address: usize, value: u8) {
+        if let Some(from) = self.from.get_mut(address) {
+            *from = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blend_interpolates_everything_but_the_start_code() {
+        let start = Instant::now();
+        let mut from = [0u8; 513];
+        from[0] = 0xCC;
+        from[1] = 200;
+        let mut target = [0u8; 513];
+        target[2] = 255;
+        target[512] = 100;
+
+        let mut fade = Fade::new(from, start, Duration::from_secs(4));
+        let halfway = fade.blend(&target, start + Duration::from_secs(2));
+        assert_eq!(halfway[0], 0);
+        assert_eq!(halfway[1..3], [100, 128]);
+        assert_eq!(halfway[512], 50);
+
+        fade.snap(512, 100);
+        assert_eq!(
+            fade.blend(&target, start + Duration::from_secs(1))[512],
+            100
+        );
+
+        assert!(!fade.is_finished(start + Duration::from_secs(3)));
+        assert!(fade.is_finished(start + Duration::from_secs(5)));
+        assert_eq!(fade.blend(&target, start + Duration::from_secs(5)), target);
+    }
+}
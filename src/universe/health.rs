@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+
+/// How long an address has been held at full, from `UniverseCommand::GetPinnedChannels`
+#[derive(Debug, Clone)]
+pub struct PinnedChannel {
+    pub universe: u8,
+    pub address: usize,
+    pub duration: Duration,
+}
+
+/// Watches the output for addresses sitting at 255, a sign of a stuck fader
+/// or a runaway effect over a long show. Only changes are looked at, so each
+/// tick costs one comparison per address. Each universe has its own.
+pub struct HealthMonitor {
+    universe: u8,
+    last: [u8; 513],
+    pinned_since: [Option<Instant>; 513],
+}
+
+impl HealthMonitor {
+    pub fn new(universe: u8) -> Self {
+        Self {
+            universe,
+            last: [0; 513],
+            pinned_since: [None; 513],
+        }
+    }
+
+    /// Note the buffer as it stands at `now`
+    pub fn observe(&mut self, now: Instant, buffer: &[u8; 513]) {
+        for (address, &value) in buffer.iter().enumerate().skip(1) {
+            if value != self.last[address] {
+                self.last[address] = value;
+                self.pinned_since[address] = (value == u8::MAX).then_some(now);
+            }
+        }
+    }
+
+    /// Addresses that have been at full for at least `longer_than`, longest first
+    pub fn pinned(&self, now: Instant, longer_than: Duration) -> Vec<PinnedChannel> {
+        let mut pinned: Vec<PinnedChannel> = self
+            .pinned_since
+            .iter()
+            .enumerate()
+            .filter_map(|(address, since)| {
+                let duration = now.saturating_duration_since((*since)?);
+                (duration >= longer_than).then_some(PinnedChannel {
+                    universe: self.universe,
+                    address,
+                    duration,
+                })
+            })
+            .collect();
+        pinned.sort_by_key(|channel| std::cmp::Reverse(channel.duration));
+        pinned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinned_time_restarts_when_the_value_moves() {
+        let mut monitor = HealthMonitor::new(2);
+        let start = Instant::now();
+        let mut buffer = [0u8; 513];
+        buffer[1] = 255;
+        buffer[2] = 255;
+        buffer[3] = 200;
+        monitor.observe(start, &buffer);
+
+        // Rewriting the same value does not reset the clock
+        monitor.observe(start + Duration::from_secs(60), &buffer);
+        buffer[2] = 254;
+        monitor.observe(start + Duration::from_secs(90), &buffer);
+        buffer[2] = 255;
+        monitor.observe(start + Duration::from_secs(100), &buffer);
+
+        let now = start + Duration::from_secs(120);
+        let pinned = monitor.pinned(now, Duration::ZERO);
+        assert_eq!(pinned.len(), 2);
+        assert_eq!(pinned[0].universe, 2);
+        assert_eq!(pinned[0].address, 1);
+        assert_eq!(pinned[0].duration, Duration::from_secs(120));
+        assert_eq!(pinned[1].duration, Duration::from_secs(20));
+
+        let pinned = monitor.pinned(now, Duration::from_secs(60));
+        assert_eq!(pinned.len(), 1);
+    }
+}
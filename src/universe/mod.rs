@@ -1,9 +1,28 @@
+pub mod clock;
 pub mod cue;
+pub mod fade;
+pub mod health;
+pub mod session;
+pub mod show;
+pub mod sink;
+pub mod stats;
 
 use crate::{
-    dmx_close, dmx_send_break, dmx_write,
-    fixture::patch::{ChannelType, PatchedFixture},
+    fixture::{
+        ofl::OflCapability,
+        patch::{ChannelType, DimmerCurve, PatchedFixture},
+    },
+    universe::{
+        fade::Fade,
+        health::{HealthMonitor, PinnedChannel},
+        session::{SessionEvent, SessionRecorder},
+        sink::DmxSink,
+        stats::{DmxStats, StatsTracker},
+    },
 };
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::sync::mpsc::Receiver;
 use std::time::{Duration, Instant};
 use std::thread;
@@ -12,18 +31,174 @@ use anyhow::{anyhow, Result};
 
 const DMX_BUFFER_LENGTH: i32 = 513;
 
+/// Start code for ordinary dimmer data
+pub const NULL_START_CODE: u8 = 0;
+
+/// Queue depth past which redundant channel writes are coalesced
+const COALESCE_THRESHOLD: usize = 100;
+
+/// Slowest refresh `SetFrameRate` allows; much slower and fixtures may drop out
+pub const MIN_FRAME_RATE: f32 = 1.0;
+
+/// Fastest refresh a full 512-slot frame fits in on the wire
+pub const MAX_FRAME_RATE: f32 = 44.0;
+
+/// Frame interval for a refresh rate, clamped to the legal range, along with
+/// the rate actually used
+pub fn frame_interval_for(hz: f32) -> (Duration, f32) {
+    let hz = if hz.is_nan() {
+        MAX_FRAME_RATE
+    } else {
+        hz.clamp(MIN_FRAME_RATE, MAX_FRAME_RATE)
+    };
+    (Duration::from_secs_f32(1.0 / hz), hz)
+}
+
+/// Convert a DMX level to percent, rounding so 255 is 100%
+pub fn to_percent(value: u8) -> u8 {
+    ((value as u32 * 100 + 127) / 255) as u8
+}
+
+/// Convert a percent (0-100) to a DMX level, rounding so 100% is 255
+pub fn from_percent(percent: u8) -> u8 {
+    ((percent.min(100) as u32 * 255 + 50) / 100) as u8
+}
+
+/// Warmest color temperature `set_color_temperature` will aim for
+pub const MIN_KELVIN: u16 = 1000;
+
+/// Coolest color temperature `set_color_temperature` will aim for
+pub const MAX_KELVIN: u16 = 12000;
+
+/// Approximate the RGB color of a blackbody at `kelvin`, clamped to
+/// MIN_KELVIN..=MAX_KELVIN. This is Tanner Helland's curve fit to the CIE
+/// blackbody table, good to a few percent over that range. It assumes
+/// ideal emitters, so a real fixture's white will still drift a little.
+pub fn kelvin_to_rgb(kelvin: u16) -> (u8, u8, u8) {
+    let temp = kelvin.clamp(MIN_KELVIN, MAX_KELVIN) as f64 / 100.0;
+    let red = if temp <= 66.0 {
+        255.0
+    } else {
+        329.698727446 * (temp - 60.0).powf(-0.1332047592)
+    };
+    let green = if temp <= 66.0 {
+        99.4708025861 * temp.ln() - 161.1195681661
+    } else {
+        288.1221695283 * (temp - 60.0).powf(-0.0755148492)
+    };
+    let blue = if temp >= 66.0 {
+        255.0
+    } else if temp <= 19.0 {
+        0.0
+    } else {
+        138.5177312231 * (temp - 10.0).ln() - 305.0447927307
+    };
+
+    let level = |value: f64| value.round().clamp(0.0, 255.0) as u8;
+    (level(red), level(green), level(blue))
+}
+
+/// Wrap hue into 0-360 degrees and clamp saturation and value to 0-1
+fn normalize_hsv(hue: f32, saturation: f32, value: f32) -> (f32, f32, f32) {
+    (
+        hue.rem_euclid(360.0),
+        saturation.clamp(0.0, 1.0),
+        value.clamp(0.0, 1.0),
+    )
+}
+
+/// Convert hue in degrees, saturation and value to RGB levels. Hue wraps, so
+/// -90 is 270; saturation and value are clamped to 0-1.
+pub fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> (u8, u8, u8) {
+    let (hue, saturation, value) = normalize_hsv(hue, saturation, value);
+    let chroma = value * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    let level = |component: f32| ((component + value - chroma) * 255.0).round() as u8;
+    (level(r), level(g), level(b))
+}
+
+/// Whether a fixture can mix a color from red, green and blue
+fn has_rgb(fixture: &PatchedFixture) -> bool {
+    [ChannelType::Red, ChannelType::Green, ChannelType::Blue]
+        .iter()
+        .all(|color| fixture.profile.offset_of(color).is_some())
+}
+
+/// Timing for the DMX thread
+#[derive(Debug, Clone, Copy)]
+pub struct DmxConfig {
+    /// Time between frames; 25ms is 40Hz
+    pub frame_interval: Duration,
+    /// How long one tick may spend on commands before output gets a turn. At
+    /// least one command is handled per tick however long it takes.
+    pub command_budget: Duration,
+    /// How long output takes to fade to black when the thread shuts down
+    pub shutdown_fade: Duration,
+}
+
+impl Default for DmxConfig {
+    fn default() -> Self {
+        Self {
+            frame_interval: Duration::from_millis(25),
+            command_budget: Duration::from_millis(5),
+            shutdown_fade: Duration::from_secs(1),
+        }
+    }
+}
+
 pub struct Universe {
     pub id: u8,
-    pub fixtures: Vec<Option<PatchedFixture>>, // Index by channel, None = no fixture on that channel
+    /// When off, commands still update the buffer but no frames are transmitted
+    pub output_enabled: bool,
+    /// While latched every fixture goes out dark, whatever any buffer or
+    /// playback holds; releasing brings the levels underneath back
+    pub blackout_latched: bool,
+    /// Start newly patched color fixtures on white rather than black; see
+    /// `add_fixture`. On unless turned off with `--no-default-white`.
+    pub default_white: bool,
+    /// Sent in slot 0 of every frame. Buffers loaded with `set_dmx_buffer`
+    /// never override it, whatever their own first byte holds.
+    pub start_code: u8,
+    pub fixtures: BTreeMap<usize, PatchedFixture>, // Keyed by control channel, iterates in channel order
     dmx_buffer: [u8; DMX_BUFFER_LENGTH as usize], // 513 bytes: start code + 512 channels
+    cue_buffer: [u8; DMX_BUFFER_LENGTH as usize], // Playback layer, as the last cue left it
+    manual: BTreeMap<usize, u8>, // Manual layer: addresses set by hand, merged over cues
+    touched: BTreeSet<usize>, // Addresses written by hand since the last cue recall
+    solo: BTreeSet<usize>,    // Fixture channels left visible while solo is on
+    playbacks: BTreeMap<String, LiveCue>, // Last cue played on each playback
+    mirrors: BTreeMap<usize, usize>, // Mirroring fixture channel -> the fixture it copies
+    fade: Option<Fade>,       // Cue crossfade heading for dmx_buffer, if one is running
+    parked: BTreeMap<usize, u8>, // Addresses held at a value until unparked
 }
 
 impl Universe {
     pub fn new(id: u8) -> Self {
         Universe {
             id,
-            fixtures: vec![],
+            output_enabled: true,
+            blackout_latched: false,
+            default_white: true,
+            start_code: NULL_START_CODE,
+            fixtures: BTreeMap::new(),
             dmx_buffer: [0; DMX_BUFFER_LENGTH as usize],
+            cue_buffer: [0; DMX_BUFFER_LENGTH as usize],
+            manual: BTreeMap::new(),
+            touched: BTreeSet::new(),
+            solo: BTreeSet::new(),
+            playbacks: BTreeMap::new(),
+            mirrors: BTreeMap::new(),
+            fade: None,
+            parked: BTreeMap::new(),
         }
     }
 
@@ -35,36 +210,71 @@ impl Universe {
         universe
     }
 
-    /// Add a fixture at a specific channel index
-    /// Safely resizes the vector if needed, filling gaps with None
+    /// Add a fixture at its control channel, replacing any fixture already there.
+    ///
+    /// With `default_white` on, a fixture with red, green and blue channels
+    /// has them set to full (or its color limit), so bringing it to full shows
+    /// white instead of nothing. Intensity is left alone: a fixture with an
+    /// intensity channel stays dark until brought up, while one without comes
+    /// up lit white. The preset is not a hand edit, so it doesn't mark anything
+    /// touched for `update`.
     pub fn add_fixture(&mut self, fixture: PatchedFixture) {
-        let channel = fixture.channel;
+        let colors = [ChannelType::Red, ChannelType::Green, ChannelType::Blue];
+        let preset: Vec<(usize, u8)> = if self.default_white {
+            colors
+                .iter()
+                .map(|color| {
+                    let offset = fixture.profile.offset_of(color)?;
+                    let value = fixture.color_limits.get(color).copied();
+                    Some((fixture.buffer_index(offset), value.unwrap_or(u8::MAX)))
+                })
+                .collect::<Option<_>>()
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
 
-        if channel >= self.fixtures.len() {
-            self.fixtures.resize(channel + 1, None);
+        for (index, value) in preset {
+            if let Some(slot) = self.cue_buffer.get_mut(index) {
+                *slot = value;
+            }
+            if self.parked.contains_key(&index) {
+                continue;
+            }
+            if let Some(slot) = self.dmx_buffer.get_mut(index) {
+                *slot = value;
+            }
         }
-
-        self.fixtures[channel] = Some(fixture);
+        self.fixtures.insert(fixture.channel, fixture);
     }
 
     /// Remove a fixture from a specific channel
     /// returns the removed fixture
     pub fn remove_fixture(&mut self, channel: usize) -> Option<PatchedFixture> {
-        if channel < self.fixtures.len() {
-            self.fixtures[channel].take()
-        } else {
-            None
+        self.mirrors
+            .retain(|target, source| *target != channel && *source != channel);
+        self.fixtures.remove(&channel)
+    }
+
+    /// Swap the whole patch for another, as when a show is loaded. Mirrors and
+    /// solo refer to the old fixtures, so they are dropped too.
+    pub fn replace_patch(&mut self, fixtures: Vec<PatchedFixture>) {
+        self.fixtures.clear();
+        self.mirrors.clear();
+        self.solo.clear();
+        for fixture in fixtures {
+            self.add_fixture(fixture);
         }
     }
 
     /// Get a reference to a fixture at a specific channel
     pub fn get_fixture(&self, channel: usize) -> Option<&PatchedFixture> {
-        self.fixtures.get(channel)?.as_ref()
+        self.fixtures.get(&channel)
     }
 
     /// Get a mutable reference to a fixture at a specific channel
     pub fn get_fixture_mut(&mut self, channel: usize) -> Option<&mut PatchedFixture> {
-        self.fixtures.get_mut(channel)?.as_mut()
+        self.fixtures.get_mut(&channel)
     }
 
     /// Set DMX values for a specific fixture by channel
@@ -73,12 +283,33 @@ impl Universe {
         channel: usize,
         values: &[(ChannelType, u8)],
     ) -> Result<()> {
+        for (index, value) in self.fixture_updates(channel, values)? {
+            self.set_dmx_address(index, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// The addresses and levels `values` come to on a fixture, after its
+    /// color limits. Empty for a muted fixture.
+    fn fixture_updates(
+        &self,
+        channel: usize,
+        values: &[(ChannelType, u8)],
+    ) -> Result<Vec<(usize, u8)>> {
         let mut updates: Vec<(usize, u8)> = Vec::new();
         if let Some(fixture) = self.get_fixture(channel) {
+            if fixture.muted {
+                return Ok(updates);
+            }
+
             for (function, new_value) in values {
-                if let Some(offset) = fixture.profile.channels.get(function) {
-                    let buffer_index = fixture.dmx_start as usize + *offset as usize + 1;
-                    updates.push((buffer_index, *new_value));
+                if let Some(offset) = fixture.profile.offset_of(function) {
+                    let new_value = match fixture.color_limits.get(function) {
+                        Some(limit) => (*new_value).min(*limit),
+                        None => *new_value,
+                    };
+                    updates.push((fixture.buffer_index(offset), new_value));
                 } else {
                     // maybe include error here but for now i'll just skip that channel
                     eprintln!("Channel: {} has no value: {:?}", fixture.channel, function);
@@ -88,16 +319,383 @@ impl Universe {
             return Err(anyhow!("No fixture found on channel {}", channel));
         }
 
-        for (index, value) in updates {
-            self.set_dmx_address(index, value)?;
+        Ok(updates)
+    }
+
+    /// Set the Nth (0-based) channel of a type, for fixtures that repeat a function
+    /// such as dual-engine or matrix fixtures
+    pub fn set_fixture_values_indexed(
+        &mut self,
+        channel: usize,
+        ctype: ChannelType,
+        instance: usize,
+        value: u8,
+    ) -> Result<()> {
+        let fixture = self
+            .get_fixture(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
+        if fixture.muted {
+            return Ok(());
+        }
+
+        let offset = fixture
+            .profile
+            .offset_of_instance(&ctype, instance)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Channel {} has no {:?} channel #{}",
+                    channel,
+                    ctype,
+                    instance + 1
+                )
+            })?;
+
+        let buffer_index = fixture.buffer_index(offset);
+        self.set_dmx_address(buffer_index, value)
+    }
+
+    /// Set a 16-bit function such as pan or tilt. The high byte goes to the
+    /// coarse channel and the low byte to its fine channel; a mode without a
+    /// fine channel for the function gets the high byte only. A color limit
+    /// caps the whole value, so the fine byte can't lift it past the limit.
+    pub fn set_fixture_value_16bit(
+        &mut self,
+        channel: usize,
+        ctype: ChannelType,
+        value: u16,
+    ) -> Result<()> {
+        let fixture = self
+            .get_fixture(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
+        if fixture.muted {
+            return Ok(());
         }
 
+        if fixture.profile.offset_of(&ctype).is_none() {
+            return Err(anyhow!("Channel {} has no {:?} channel", channel, ctype));
+        }
+        let limit = fixture
+            .color_limits
+            .get(&ctype)
+            .map(|&limit| u16::from(limit) << 8);
+        let [coarse, fine] = value.min(limit.unwrap_or(u16::MAX)).to_be_bytes();
+        let fine_address = fixture
+            .profile
+            .fine_offset_of(&ctype)
+            .map(|offset| fixture.buffer_index(offset));
+
+        // The coarse byte goes the same way as any other level
+        self.set_fixture_values(channel, &[(ctype, coarse)])?;
+        if let Some(address) = fine_address {
+            self.set_dmx_address(address, fine)?;
+        }
+        Ok(())
+    }
+
+    /// Set a fixture's white to a color temperature, clamped to
+    /// MIN_KELVIN..=MAX_KELVIN. A fixture with its own color temperature
+    /// channel gets the Kelvin value mapped onto the range its profile gives,
+    /// or the nearest end of it, using the fine channel when there is one. An
+    /// RGB fixture gets the blackbody approximation from `kelvin_to_rgb`.
+    pub fn set_color_temperature(&mut self, channel: usize, kelvin: u16) -> Result<()> {
+        let fixture = self
+            .get_fixture(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
+        let kelvin = kelvin.clamp(MIN_KELVIN, MAX_KELVIN);
+
+        let Some(offset) = fixture.profile.offset_of(&ChannelType::ColorTemperature) else {
+            if !has_rgb(fixture) {
+                return Err(anyhow!(
+                    "Channel {} has no color temperature or RGB channels",
+                    channel
+                ));
+            }
+            let (r, g, b) = kelvin_to_rgb(kelvin);
+            return self.set_rgb(channel, r, g, b);
+        };
+
+        let kelvin = kelvin as f32;
+        let capabilities = fixture
+            .profile
+            .capabilities
+            .get(&offset)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let value = capabilities
+            .iter()
+            .find_map(|capability| capability.value_for_kelvin(kelvin))
+            .or_else(|| {
+                // Past every range, so settle for whichever end is closest
+                capabilities
+                    .iter()
+                    .filter_map(|capability| {
+                        let (start, end) = capability.kelvin_range()?;
+                        let (range_start, range_end) = capability.range();
+                        Some([(start, range_start), (end, range_end)])
+                    })
+                    .flatten()
+                    .min_by(|(a, _), (b, _)| (a - kelvin).abs().total_cmp(&(b - kelvin).abs()))
+                    .map(|(_, value)| value as f32)
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "Channel {}'s color temperature channel doesn't say which temperatures it covers",
+                    channel
+                )
+            })?;
+
+        // 255 * 257 is 65535, so full scale stays full scale
+        let value = (value * 257.0).round() as u16;
+        self.set_fixture_value_16bit(channel, ChannelType::ColorTemperature, value)
+    }
+
+    /// Aim a moving light. Each axis takes a 16-bit value split across its
+    /// coarse and fine channels when the mode has a fine channel for it, and
+    /// 0-255 otherwise. Nothing is written unless both values fit.
+    pub fn set_position(&mut self, channel: usize, pan: u16, tilt: u16) -> Result<()> {
+        let fixture = self
+            .get_fixture(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
+
+        let mut axes = Vec::new();
+        for (axis, value) in [(ChannelType::Pan, pan), (ChannelType::Tilt, tilt)] {
+            if fixture.profile.offset_of(&axis).is_none() {
+                return Err(anyhow!(
+                    "Fixture on channel {} has no {:?} channel",
+                    channel,
+                    axis
+                ));
+            }
+            let fine = fixture.profile.fine_offset_of(&axis).is_some();
+            if !fine && value > u8::MAX as u16 {
+                return Err(anyhow!(
+                    "Fixture on channel {} has no fine {:?} channel, so {:?} must be 0-255",
+                    channel,
+                    axis,
+                    axis
+                ));
+            }
+            axes.push((axis, value, fine));
+        }
+
+        for (axis, value, fine) in axes {
+            if fine {
+                self.set_fixture_value_16bit(channel, axis, value)?;
+            } else {
+                self.set_fixture_values(channel, &[(axis, value as u8)])?;
+            }
+        }
         Ok(())
     }
 
-    /// quickly set the intensity of a light
-    pub fn set_intensity(&mut self, channel: usize, intensity: u8) -> Result<()> {
-        return self.set_fixture_values(channel, &[(ChannelType::Intensity, intensity)]);
+    /// Put a fixture's gobo wheel on a slot, numbered from 1 in wheel order
+    /// as OFL numbers them. The value written is the middle of the slot's
+    /// DMX range, clear of the edges where the wheel sits between slots.
+    pub fn select_gobo(&mut self, channel: usize, slot: usize) -> Result<()> {
+        let fixture = self
+            .get_fixture(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
+        let offset = fixture
+            .profile
+            .gobo_offset()
+            .ok_or_else(|| anyhow!("Fixture on channel {} has no gobo wheel", channel))?;
+
+        let slots: Vec<&OflCapability> = fixture.profile.wheel_slots(offset).collect();
+        if slots.is_empty() {
+            return Err(anyhow!(
+                "The gobo wheel on channel {} doesn't list its slots",
+                channel
+            ));
+        }
+        let (start, end) = slot
+            .checked_sub(1)
+            .and_then(|index| slots.get(index))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Gobo slot {} is out of range; the wheel on channel {} has slots 1-{}",
+                    slot,
+                    channel,
+                    slots.len()
+                )
+            })?
+            .range();
+        if fixture.muted {
+            return Ok(());
+        }
+
+        let address = fixture.buffer_index(offset);
+        self.set_dmx_address(address, ((start as u16 + end as u16) / 2) as u8)
+    }
+
+    /// Set a fixture's strobe rate. Unlike `set_fixture_values` this is an
+    /// error for a fixture without a strobe channel, since a strobe that
+    /// silently does nothing is easy to miss.
+    pub fn set_strobe(&mut self, channel: usize, rate: u8) -> Result<()> {
+        let fixture = self
+            .get_fixture(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
+        if fixture.profile.offset_of(&ChannelType::Strobe).is_none() {
+            return Err(anyhow!(
+                "Fixture on channel {} has no strobe channel",
+                channel
+            ));
+        }
+        self.set_fixture_values(channel, &[(ChannelType::Strobe, rate)])
+    }
+
+    /// Set a fixture's color from hue in degrees, saturation and value. A
+    /// fixture with its own Hue and Saturation channels gets those directly,
+    /// with value going to its intensity or dimmer if it has one; anything
+    /// else is converted with `hsv_to_rgb`.
+    pub fn set_hsv(&mut self, channel: usize, hue: f32, saturation: f32, value: f32) -> Result<()> {
+        let fixture = self
+            .get_fixture(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
+        let profile = &fixture.profile;
+
+        if profile.offset_of(&ChannelType::Hue).is_some()
+            && profile.offset_of(&ChannelType::Saturation).is_some()
+        {
+            let (hue, saturation, value) = normalize_hsv(hue, saturation, value);
+            let mut values = vec![
+                (ChannelType::Hue, (hue / 360.0 * 255.0).round() as u8),
+                (ChannelType::Saturation, (saturation * 255.0).round() as u8),
+            ];
+            if let Some(level) = [ChannelType::Intensity, ChannelType::Dimmer]
+                .into_iter()
+                .find(|level| profile.offset_of(level).is_some())
+            {
+                values.push((level, (value * 255.0).round() as u8));
+            }
+            return self.set_fixture_values(channel, &values);
+        }
+
+        if !has_rgb(fixture) {
+            return Err(anyhow!(
+                "Channel {} has no hue and saturation or RGB channels",
+                channel
+            ));
+        }
+        let (r, g, b) = hsv_to_rgb(hue, saturation, value);
+        self.set_rgb(channel, r, g, b)
+    }
+
+    /// Set one channel function on every fixture that has it, skipping the rest.
+    /// Returns how many fixtures have the function.
+    pub fn set_all_fixture_value(&mut self, ctype: &ChannelType, value: u8) -> Result<usize> {
+        let channels: Vec<usize> = self
+            .fixtures
+            .values()
+            .filter(|fixture| fixture.profile.offset_of(ctype).is_some())
+            .map(|fixture| fixture.channel)
+            .collect();
+
+        for &channel in &channels {
+            self.set_fixture_values(channel, &[(ctype.clone(), value)])?;
+        }
+
+        Ok(channels.len())
+    }
+
+    /// Put one group of functions back to a neutral value on every fixture
+    /// that has them, leaving intensity alone. Returns how many fixtures
+    /// changed. A reset isn't a hand edit, so nothing is marked touched.
+    /// Muted fixtures are reset and counted too: their output is held dark,
+    /// but the values underneath are what comes back when they are unmuted.
+    pub fn neutralize(&mut self, target: Neutral) -> Result<usize> {
+        let updates: Vec<(usize, Vec<(ChannelType, u8)>)> = self
+            .fixtures
+            .values()
+            .map(|fixture| {
+                let values = fixture
+                    .profile
+                    .channels
+                    .iter()
+                    .filter_map(|(ctype, offset)| {
+                        let value = match target {
+                            // A recorded home wins over the factory center
+                            Neutral::Home => fixture
+                                .home
+                                .get(offset)
+                                .copied()
+                                .or_else(|| Neutral::Position.value_for(ctype))?,
+                            _ => target.value_for(ctype)?,
+                        };
+                        Some((ctype.clone(), value))
+                    })
+                    .collect::<Vec<_>>();
+                (fixture.channel, values)
+            })
+            .filter(|(_, values)| !values.is_empty())
+            .collect();
+
+        for (channel, values) in &updates {
+            for (address, value) in self.fixture_updates(*channel, values)? {
+                self.write_manual(address, value)?;
+            }
+        }
+
+        Ok(updates.len())
+    }
+
+    /// Record a fixture's current pan and tilt, and its color too with
+    /// `with_color`, as the home `neutral home` returns it to. Returns how
+    /// many channels were stored.
+    pub fn set_home_from_current(&mut self, channel: usize, with_color: bool) -> Result<usize> {
+        let buffer = self.dmx_buffer;
+        let fixture = self
+            .get_fixture_mut(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
+
+        let home: HashMap<u8, u8> = fixture
+            .profile
+            .channels
+            .iter()
+            .filter(|(ctype, _)| {
+                Neutral::Position.value_for(ctype).is_some() || (with_color && ctype.is_color())
+            })
+            .filter_map(|(_, offset)| Some((*offset, *buffer.get(fixture.buffer_index(*offset))?)))
+            .collect();
+        fixture.home = home;
+        Ok(fixture.home.len())
+    }
+
+    /// Control channels of patched fixtures whose OFL categories include
+    /// `category`, ignoring case
+    pub fn fixtures_by_category(&self, category: &str) -> Vec<usize> {
+        self.fixtures
+            .values()
+            .filter(|fixture| {
+                fixture
+                    .profile
+                    .categories
+                    .iter()
+                    .any(|c| c.eq_ignore_ascii_case(category))
+            })
+            .map(|fixture| fixture.channel)
+            .collect()
+    }
+
+    /// Set channel functions on every fixture in an OFL category, skipping
+    /// functions a fixture doesn't have. Returns how many fixtures matched.
+    pub fn set_category_values(
+        &mut self,
+        category: &str,
+        values: &[(ChannelType, u8)],
+    ) -> Result<usize> {
+        let channels = self.fixtures_by_category(category);
+
+        for &channel in &channels {
+            let profile = &self.fixtures[&channel].profile;
+            let present: Vec<(ChannelType, u8)> = values
+                .iter()
+                .filter(|(ctype, _)| profile.offset_of(ctype).is_some())
+                .cloned()
+                .collect();
+            self.set_fixture_values(channel, &present)?;
+        }
+
+        Ok(channels.len())
     }
 
     pub fn set_rgb(&mut self, channel: usize, r: u8, g: u8, b: u8) -> Result<()> {
@@ -111,224 +709,3038 @@ impl Universe {
         );
     }
 
-    pub fn set_dmx_buffer(&mut self, new_buffer: &[u8; 513]) {
-        //todo: check park values and make sure it isn't overwritten
-        self.dmx_buffer = *new_buffer;
-    }
+    /// Write raw bytes to consecutive slots from the fixture's start address,
+    /// bypassing the channel type mapping. Used for probing unknown fixtures.
+    pub fn set_fixture_raw(&mut self, channel: usize, values: &[u8]) -> Result<()> {
+        let fixture = self
+            .get_fixture(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
 
-    pub fn blackout(&mut self) -> Result<()> {
-        let channels: Vec<usize> = self.fixtures.iter().flatten().map(|f| f.channel).collect();
-        for channel in channels {
-            self.set_intensity(channel, 0u8)?;
+        if values.len() > fixture.profile.footprint as usize {
+            return Err(anyhow!(
+                "{} values given but fixture {} only has {} channels",
+                values.len(),
+                channel,
+                fixture.profile.footprint
+            ));
         }
 
-        Ok(())
-    }
-
-    /// Set a single DMX channel value, functions should use this to ensure that values aren't being set incorrectly
-    pub fn set_dmx_address(&mut self, dmx_address: usize, value: u8) -> Result<()> {
-        if dmx_address == 0 {
-            return Err(anyhow!("DMX address 0 is reserved for start code"));
+        if fixture.muted {
+            return Ok(());
         }
-        if dmx_address >= 513 {
-            return Err(anyhow!("DMX address must be between 1 and 512"));
+
+        let updates: Vec<(usize, u8)> = values
+            .iter()
+            .enumerate()
+            .map(|(offset, value)| (fixture.buffer_index(offset as u8), *value))
+            .collect();
+        for (address, value) in updates {
+            self.set_dmx_address(address, value)?;
         }
 
-        self.dmx_buffer[dmx_address] = value;
         Ok(())
     }
 
-    pub unsafe fn send_buffer(&self, fd: i32) -> Result<()> {
-        dmx_send_break(fd);
+    /// Set one channel of a fixture by its OFL channel name, for channels with
+    /// no `ChannelType` of their own such as "Shutter / Strobe"
+    pub fn set_channel_by_name(
+        &mut self,
+        channel: usize,
+        channel_name: &str,
+        value: u8,
+    ) -> Result<()> {
+        let fixture = self
+            .get_fixture(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
+        let offset = fixture
+            .profile
+            .offset_of_name(channel_name)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Fixture {} has no channel named '{}' (it has: {})",
+                    channel,
+                    channel_name,
+                    fixture.profile.channel_names.join(", ")
+                )
+            })?;
 
-        if dmx_write(fd, self.dmx_buffer.as_ptr(), DMX_BUFFER_LENGTH) < 0 {
-            return Err(anyhow!("Dmx failed to write"));
+        if fixture.muted {
+            return Ok(());
         }
 
-        Ok(())
+        let address = fixture.buffer_index(offset);
+        self.set_dmx_address(address, value)
     }
-}
 
-#[derive(Debug, Clone)]
-pub enum UniverseCommand {
-    // Single channel update
-    SetChannel {
-        channel: usize,
-        value: u8,
-    },
+    /// Load the playback layer, as recalling a cue does. Hand-set intensity
+    /// stays and is merged highest-takes-precedence; hand-set color, position
+    /// and everything else is latest-takes-precedence, so the cue takes it back.
+    pub fn set_dmx_buffer(&mut self, new_buffer: &[u8; 513]) {
+        self.cue_buffer = *new_buffer;
+        // A cue captured from a buffer with a stray first byte must not change
+        // what receivers take the frame to be
+        self.cue_buffer[0] = self.start_code;
+        let intensity_addresses = self.intensity_addresses();
+        self.manual
+            .retain(|address, _| intensity_addresses.contains(address));
+        self.merge_layers();
 
-    // Multiple channels at once (for cues)
-    SetMultiple {
-        changes: Vec<(usize, u8)>,
-    },
+        // Recalling a cue starts a fresh edit, so nothing is touched yet
+        self.touched.clear();
+        // and snaps, unless the caller starts a fade afterwards
+        self.fade = None;
+    }
 
-    // Complete cue with metadata
-    PlayCue {
-        cue_idx: usize,
-        cue_data: [u8; 513],
-        fade_time_ms: u32,
-    },
+    /// Fade the output from `from` to the current buffer over `duration`,
+    /// replacing any fade already running. A zero duration leaves it snapped.
+    pub fn start_fade(&mut self, from: [u8; 513], duration: Duration) {
+        self.fade = (!duration.is_zero()).then(|| Fade::new(from, Instant::now(), duration));
+    }
 
-    // Fixture-level commands
-    SetFixture {
-        fixture_channel: usize,
-        intensity: Option<u8>,
-        color: Option<(u8, u8, u8)>, // RGB
-    },
+    /// Drop a fade that has reached its target
+    pub fn settle_fade(&mut self, now: Instant) {
+        if self.fade.as_ref().is_some_and(|fade| fade.is_finished(now)) {
+            self.fade = None;
+        }
+    }
 
-    // Show control
-    Blackout,
+    /// The levels at `now`: the buffer itself, or part way to it during a fade
+    pub fn levels_at(&self, now: Instant) -> [u8; 513] {
+        match &self.fade {
+            Some(fade) => fade.blend(&self.dmx_buffer, now),
+            None => self.dmx_buffer,
+        }
+    }
 
-    // Query commands (with response channel)
-    GetChannelValue {
-        channel: usize,
-        response: std::sync::mpsc::Sender<u8>,
-    },
+    /// Buffer addresses of every patched intensity and dimmer channel
+    fn intensity_addresses(&self) -> Vec<usize> {
+        self.fixtures
+            .values()
+            .flat_map(|fixture| {
+                fixture
+                    .intensity_offsets()
+                    .into_iter()
+                    .map(|offset| fixture.buffer_index(offset))
+            })
+            .filter(|&address| address < DMX_BUFFER_LENGTH as usize)
+            .collect()
+    }
 
-    // Get fixture channel information
-    GetChannels {
-        fixture_channel: usize,
-        response: std::sync::mpsc::Sender<Option<Vec<(String, usize, usize)>>>, // (channel_type, dmx_address, offset)
-    },
+    /// Play a cue on a playback, with its intensity channels scaled to
+    /// `percent` of their recorded level and then by the cue's `master`.
+    /// Intensity is merged highest-takes-precedence with the last cue of every
+    /// other playback; everything else is latest-takes-precedence, so this
+    /// cue's color and position win.
+    pub fn play_cue(
+        &mut self,
+        playback: &str,
+        cue_idx: usize,
+        cue_data: &[u8; 513],
+        percent: u8,
+        master: u8,
+    ) {
+        let intensity_addresses = self.intensity_addresses();
+        let live = LiveCue {
+            cue_idx,
+            channels: *cue_data,
+            percent,
+            master,
+        };
 
-    GetDMXState(std::sync::mpsc::Sender<[u8; 513]>),
-}
+        self.playbacks.insert(playback.to_string(), live);
 
-pub fn dmx_thread(
-    mut universe: Universe, // Now OWNED by this thread
-    command_rx: Receiver<UniverseCommand>,
-    shutdown_rx: Receiver<()>,
-    fd: i32,
-) {
-    println!("DMX thread started");
+        let mut merged = *cue_data;
+        self.merge_intensities(&mut merged, &intensity_addresses);
+        self.set_dmx_buffer(&merged);
+    }
 
-    let mut last_dmx_send = Instant::now();
-    let dmx_interval = Duration::from_millis(25); // 40Hz DMX rate
+    /// Move the master of the cue live on a playback, rescaling only its
+    /// intensity contribution. Color and position are left where they are.
+    pub fn set_cue_level(&mut self, playback: &str, cue_idx: usize, level: u8) -> Result<()> {
+        let live = self
+            .playbacks
+            .get_mut(playback)
+            .filter(|live| live.cue_idx == cue_idx)
+            .ok_or_else(|| anyhow!("Cue {} is not live on playback {}", cue_idx, playback))?;
+        live.master = level;
 
-    loop {
-        // Check for shutdown
-        if shutdown_rx.try_recv().is_ok() {
-            println!("DMX thread shutting down");
-            break;
-        }
+        let intensity_addresses = self.intensity_addresses();
+        let mut buffer = self.cue_buffer;
+        self.merge_intensities(&mut buffer, &intensity_addresses);
+        self.cue_buffer = buffer;
+        self.merge_layers();
+        Ok(())
+    }
 
-        // Process pending commands
-        let mut commands_processed = 0;
-        while let Ok(command) = command_rx.try_recv() {
-            process_command(&mut universe, command);
-            commands_processed += 1;
+    /// Hold `address` at `value` until unparked. Cues, blackout, mirrors and
+    /// hand writes all leave it alone, and it stays up through mute, solo and
+    /// a latched blackout.
+    pub fn park(&mut self, address: usize, value: u8) -> Result<()> {
+        if !(1..DMX_BUFFER_LENGTH as usize).contains(&address) {
+            return Err(anyhow!("DMX address must be between 1 and 512"));
+        }
 
-            // Prevent command processing from blocking DMX too long
-            if commands_processed > 100 {
-                break; // Process remaining commands next iteration
-            }
+        self.parked.insert(address, value);
+        self.dmx_buffer[address] = value;
+        if let Some(fade) = &mut self.fade {
+            fade.snap(address, value);
         }
+        Ok(())
+    }
 
-        // Send DMX at regular intervals
-        #[cfg(not(feature = "no-dmx"))]
-        if last_dmx_send.elapsed() >= dmx_interval {
-            unsafe {
-                if let Err(error) = universe.send_buffer(fd) {
-                    eprintln!("DMX send error: {}", error);
-                    break;
-                }
-            }
-            last_dmx_send = Instant::now();
+    /// Release a parked address. It keeps its parked value until something
+    /// else writes to it, so nothing jumps.
+    pub fn unpark(&mut self, address: usize) -> Result<()> {
+        self.parked
+            .remove(&address)
+            .map(|_| ())
+            .ok_or_else(|| anyhow!("Address {} is not parked", address))
+    }
+
+    /// Parked addresses and their values, in address order
+    pub fn parked(&self) -> Vec<(usize, u8)> {
+        self.parked.iter().map(|(a, v)| (*a, *v)).collect()
+    }
+
+    /// Drop every hand-set level, leaving the playbacks alone in control
+    pub fn clear_manual(&mut self) {
+        self.manual.clear();
+        self.merge_layers();
+    }
+
+    /// Whether `address` is an intensity or dimmer channel of a patched fixture
+    fn is_intensity(&self, address: usize) -> bool {
+        self.fixtures.values().any(|fixture| {
+            fixture
+                .intensity_offsets()
+                .into_iter()
+                .any(|offset| fixture.buffer_index(offset) == address)
+        })
+    }
+
+    /// The level an address gets from both layers: the higher of the two for
+    /// intensity, otherwise the hand-set value if there is one
+    fn merged_level(&self, address: usize) -> u8 {
+        let cue = self.cue_buffer[address];
+        match self.manual.get(&address) {
+            Some(&manual) if self.is_intensity(address) => cue.max(manual),
+            Some(&manual) => manual,
+            None => cue,
         }
+    }
 
-        // 4. Small sleep to prevent busy-waiting
-        thread::sleep(Duration::from_millis(1));
+    /// Rebuild the levels from the playback and manual layers, with parked
+    /// values over both
+    fn merge_layers(&mut self) {
+        self.dmx_buffer = self.cue_buffer;
+        let merged: Vec<(usize, u8)> = self
+            .manual
+            .keys()
+            .map(|&address| (address, self.merged_level(address)))
+            .collect();
+        for (address, level) in merged {
+            self.dmx_buffer[address] = level;
+        }
+        for (&address, &value) in &self.parked {
+            self.dmx_buffer[address] = value;
+        }
     }
 
-    // Cleanup
-    unsafe {
-        dmx_close(fd);
+    /// Set each intensity address to the highest level any playback gives it
+    fn merge_intensities(&self, buffer: &mut [u8; 513], intensity_addresses: &[usize]) {
+        for &address in intensity_addresses {
+            buffer[address] = self
+                .playbacks
+                .values()
+                .map(|live| live.intensity(address))
+                .max()
+                .unwrap_or(0);
+        }
     }
-    println!("DMX thread stopped");
-}
 
-fn process_command(universe: &mut Universe, command: UniverseCommand) {
-    match command {
-        UniverseCommand::SetChannel { channel, value } => {
-            if let Err(e) = universe.set_dmx_address(channel, value) {
-                eprintln!("Failed to set channel {}: {}", channel, e);
-            }
+    /// What one playback alone puts out: its live cue, with intensity scaled
+    /// by the percent and master it is playing at. None if nothing is live on it.
+    pub fn playback_output(&self, playback: &str) -> Option<[u8; 513]> {
+        let live = self.playbacks.get(playback)?;
+        let mut output = live.channels;
+        for address in self.intensity_addresses() {
+            output[address] = live.intensity(address);
         }
-        UniverseCommand::SetMultiple { changes } => {
-            for (channel, value) in changes {
-                if let Err(e) = universe.set_dmx_address(channel, value) {
-                    eprintln!("Failed to set channel {}: {}", channel, e);
+        Some(output)
+    }
+
+    /// Addresses set by hand since the last cue recall, with the values they
+    /// were set to. Ones whose hand level has since been dropped are left out.
+    pub fn touched_values(&self) -> Vec<(usize, u8)> {
+        self.touched
+            .iter()
+            .filter_map(|&address| Some((address, *self.manual.get(&address)?)))
+            .collect()
+    }
+
+    /// Put every fixture out, muted or not. Fixtures without an intensity
+    /// channel have their whole footprint zeroed. Every hand-set level and
+    /// every playback's live cue is dropped too, so neither HTP nor moving a
+    /// fader afterwards can bring a light back. One bad fixture never stops
+    /// the rest; every failure is reported together at the end.
+    pub fn blackout(&mut self) -> Result<()> {
+        self.playbacks.clear();
+        self.manual.clear();
+
+        let targets: Vec<(usize, Vec<usize>)> = self
+            .fixtures
+            .values()
+            .map(|fixture| {
+                let addresses = fixture
+                    .dark_offsets()
+                    .into_iter()
+                    .map(|offset| fixture.buffer_index(offset))
+                    .collect();
+                (fixture.channel, addresses)
+            })
+            .collect();
+
+        let mut errors = Vec::new();
+        let mut cleared = Vec::new();
+        for (channel, addresses) in targets {
+            for address in addresses {
+                match self.cue_buffer.get_mut(address) {
+                    Some(level) if address > 0 => {
+                        *level = 0;
+                        cleared.push(address);
+                    }
+                    _ => errors.push(format!(
+                        "channel {}: DMX address must be between 1 and 512",
+                        channel
+                    )),
                 }
             }
         }
-        UniverseCommand::PlayCue {
-            cue_idx,
-            cue_data,
-            fade_time_ms,
-        } => {
-            println!("Playing cue {} with {} channels", cue_idx, cue_data.len());
 
-            if fade_time_ms == 0 {
-                // Instant cue - apply immediately
-                universe.set_dmx_buffer(&cue_data);
-            } else {
-                // TODO: Start fade process (would need fade engine)
-                eprintln!("Fade not implemented yet, applying instantly");
-                universe.set_dmx_buffer(&cue_data);
+        // Zeroed in place rather than through set_dmx_address, so nothing is
+        // marked touched and a later update leaves the cue as it was
+        self.merge_layers();
+        for address in cleared {
+            if self.parked.contains_key(&address) {
+                continue;
+            }
+            if let Some(fade) = &mut self.fade {
+                fade.snap(address, 0);
             }
         }
-        UniverseCommand::SetFixture {
-            fixture_channel,
-            intensity,
-            color,
-        } => {
-            // Find fixture and set its channels
-            if let Some(_fixture) = universe.get_fixture(fixture_channel) {
-                let mut updates = Vec::new();
 
-                if let Some(intensity_val) = intensity {
-                    updates.push((crate::fixture::patch::ChannelType::Intensity, intensity_val));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("Blackout incomplete: {}", errors.join("; ")))
+        }
+    }
+
+    /// Set a single DMX channel value, functions should use this to ensure that values aren't being set incorrectly.
+    /// The value goes into the manual layer, so on an intensity channel a cue
+    /// holding it higher still wins. The address is marked touched for `update`.
+    pub fn set_dmx_address(&mut self, dmx_address: usize, value: u8) -> Result<()> {
+        self.write_manual(dmx_address, value)?;
+        if !self.parked.contains_key(&dmx_address) {
+            self.touched.insert(dmx_address);
+        }
+        Ok(())
+    }
+
+    /// `set_dmx_address` without marking the address touched, for resets
+    /// that shouldn't end up in a cue on `update`
+    fn write_manual(&mut self, dmx_address: usize, value: u8) -> Result<()> {
+        if dmx_address == 0 {
+            return Err(anyhow!("DMX address 0 is reserved for start code"));
+        }
+        if dmx_address >= 513 {
+            return Err(anyhow!("DMX address must be between 1 and 512"));
+        }
+
+        // Parked addresses ignore writes rather than fail them, so a blackout
+        // or a cue still reaches everything else
+        if self.parked.contains_key(&dmx_address) {
+            return Ok(());
+        }
+
+        self.manual.insert(dmx_address, value);
+        let level = self.merged_level(dmx_address);
+        self.dmx_buffer[dmx_address] = level;
+        if let Some(fade) = &mut self.fade {
+            fade.snap(dmx_address, level);
+        }
+        Ok(())
+    }
+
+    /// Mute or unmute the fixture on a channel
+    pub fn set_muted(&mut self, channel: usize, muted: bool) -> Result<()> {
+        let fixture = self
+            .get_fixture_mut(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
+        fixture.muted = muted;
+
+        Ok(())
+    }
+
+    /// Contiguous DMX addresses (1-512, inclusive) that no patched fixture uses
+    pub fn free_address_ranges(&self) -> Vec<(u16, u16)> {
+        let mut used = [false; DMX_BUFFER_LENGTH as usize];
+        for fixture in self.fixtures.values() {
+            for offset in 0..fixture.profile.footprint {
+                if let Some(slot) = used.get_mut(fixture.buffer_index(offset)) {
+                    *slot = true;
                 }
+            }
+        }
 
-                if let Some((r, g, b)) = color {
-                    updates.push((crate::fixture::patch::ChannelType::Red, r));
-                    updates.push((crate::fixture::patch::ChannelType::Green, g));
-                    updates.push((crate::fixture::patch::ChannelType::Blue, b));
+        let mut ranges = Vec::new();
+        let mut start = None;
+        for address in 1..DMX_BUFFER_LENGTH as u16 {
+            match (used[address as usize], start) {
+                (false, None) => start = Some(address),
+                (true, Some(first)) => {
+                    ranges.push((first, address - 1));
+                    start = None;
                 }
+                _ => {}
+            }
+        }
+        if let Some(first) = start {
+            ranges.push((first, DMX_BUFFER_LENGTH as u16 - 1));
+        }
 
-                universe.set_fixture_values(fixture_channel, &updates).ok();
+        ranges
+    }
+
+    /// First free DMX address with room for a fixture of `footprint` channels
+    pub fn next_free_address(&self, footprint: u16) -> Option<u16> {
+        self.free_address_ranges()
+            .into_iter()
+            .find(|(first, last)| last - first + 1 >= footprint)
+            .map(|(first, _)| first)
+    }
+
+    /// Add a fixture to the solo set. While the set is non-empty every other
+    /// fixture is held dark at output.
+    pub fn add_solo(&mut self, channel: usize) -> Result<()> {
+        if self.get_fixture(channel).is_none() {
+            return Err(anyhow!("No fixture found on channel {}", channel));
+        }
+        self.solo.insert(channel);
+
+        Ok(())
+    }
+
+    pub fn clear_solo(&mut self) {
+        self.solo.clear();
+    }
+
+    /// Make the fixture on `channel` copy another fixture's output from now on,
+    /// or stop with `None`. Only channel types both fixtures have are copied.
+    pub fn set_mirror(&mut self, channel: usize, source: Option<usize>) -> Result<()> {
+        if self.get_fixture(channel).is_none() {
+            return Err(anyhow!("No fixture found on channel {}", channel));
+        }
+        let Some(source) = source else {
+            self.mirrors.remove(&channel);
+            return Ok(());
+        };
+
+        if self.get_fixture(source).is_none() {
+            return Err(anyhow!("No fixture found on channel {}", source));
+        }
+        if source == channel {
+            return Err(anyhow!("Fixture {} can't mirror itself", channel));
+        }
+        // One level only, so a sync pass never reads a value it hasn't copied yet
+        if let Some(original) = self.mirrors.get(&source) {
+            return Err(anyhow!(
+                "Fixture {} already mirrors {}, mirror that instead",
+                source,
+                original
+            ));
+        }
+        if self.mirrors.values().any(|mirrored| *mirrored == channel) {
+            return Err(anyhow!("Fixture {} is being mirrored itself", channel));
+        }
+
+        self.mirrors.insert(channel, source);
+        self.sync_mirrors();
+        Ok(())
+    }
+
+    /// Copy every mirrored fixture's values onto its mirrors, pairing the Nth
+    /// channel of each type so dual-dimmer style fixtures line up
+    pub fn sync_mirrors(&mut self) {
+        let mut copies = Vec::new();
+        for (target, source) in &self.mirrors {
+            let (Some(target), Some(source)) =
+                (self.fixtures.get(target), self.fixtures.get(source))
+            else {
+                continue;
+            };
+
+            let mut instances: HashMap<&ChannelType, usize> = HashMap::new();
+            for (ctype, offset) in &target.profile.channels {
+                let instance = instances.entry(ctype).or_default();
+                if let Some(source_offset) = source.profile.offset_of_instance(ctype, *instance) {
+                    let value = self.dmx_buffer[source.buffer_index(source_offset)];
+                    copies.push((target.buffer_index(*offset), value));
+                }
+                *instance += 1;
             }
         }
-        UniverseCommand::Blackout => {
-            println!("Blackout command received");
-            universe.blackout().ok();
+
+        for (index, value) in copies {
+            if index < DMX_BUFFER_LENGTH as usize && !self.parked.contains_key(&index) {
+                self.dmx_buffer[index] = value;
+            }
         }
-        UniverseCommand::GetChannelValue { channel, response } => {
-            let value = universe.dmx_buffer.get(channel).copied().unwrap_or(0);
-            response.send(value).ok(); // Send response back
+    }
+
+    /// Cap a color channel of a fixture, or remove the cap with `None`
+    pub fn set_color_limit(
+        &mut self,
+        channel: usize,
+        ctype: ChannelType,
+        limit: Option<u8>,
+    ) -> Result<()> {
+        if !ctype.is_color() {
+            return Err(anyhow!("{:?} is not a color channel", ctype));
         }
-        UniverseCommand::GetChannels {
-            fixture_channel,
-            response,
-        } => {
-            let channel_info = if let Some(fixture) = universe.get_fixture(fixture_channel) {
-                let mut channels = Vec::new();
 
-                for (channel_type, offset) in &fixture.profile.channels {
-                    let dmx_address = fixture.dmx_start as usize + *offset as usize;
-                    let type_name = format!("{:?}", channel_type); // Convert enum to string
-                    channels.push((type_name, dmx_address, *offset as usize));
+        let fixture = self
+            .get_fixture_mut(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
+        match limit {
+            Some(limit) => fixture.color_limits.insert(ctype, limit),
+            None => fixture.color_limits.remove(&ctype),
+        };
+
+        Ok(())
+    }
+
+    /// Set the curve a fixture's intensity goes through at output
+    pub fn set_dimmer_curve(&mut self, channel: usize, curve: DimmerCurve) -> Result<()> {
+        let fixture = self
+            .get_fixture_mut(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
+        fixture.curve = curve;
+
+        Ok(())
+    }
+
+    /// The frame that actually goes out: the DMX buffer, part way there if a
+    /// cue is fading, with color limits and dimmer curves applied and muted or
+    /// soloed-out fixtures forced dark, or every fixture while blackout is latched
+    pub fn output_buffer(&self) -> [u8; DMX_BUFFER_LENGTH as usize] {
+        self.render(self.levels_at(Instant::now()))
+    }
+
+    /// Run a buffer through every output stage: color limits and dimmer
+    /// curves, then mute, solo and a latched blackout, then parked addresses
+    fn render(
+        &self,
+        mut output: [u8; DMX_BUFFER_LENGTH as usize],
+    ) -> [u8; DMX_BUFFER_LENGTH as usize] {
+        // Limits also cover values that arrived through cues, not just manual sets
+        for fixture in self.fixtures.values() {
+            for (ctype, offset) in &fixture.profile.channels {
+                if let Some(limit) = fixture.color_limits.get(ctype) {
+                    if let Some(value) = output.get_mut(fixture.buffer_index(*offset)) {
+                        *value = (*value).min(*limit);
+                    }
                 }
+            }
+        }
 
-                Some(channels)
-            } else {
-                None // No fixture found at this channel
-            };
+        // Curves shape the level actually sent, so a fade interpolates linear
+        // levels and the buffer keeps what the operator asked for. A dimmer
+        // with a fine channel is curved as one 16-bit level.
+        for fixture in self.fixtures.values() {
+            if fixture.curve == DimmerCurve::Linear {
+                continue;
+            }
+            for offset in fixture.intensity_offsets() {
+                let coarse = fixture.buffer_index(offset);
+                let fine = fixture
+                    .profile
+                    .fine_channels
+                    .get(&offset)
+                    .map(|fine| fixture.buffer_index(*fine))
+                    .filter(|&fine| fine < output.len());
+                match (output.get(coarse).copied(), fine) {
+                    (Some(high), Some(fine)) => {
+                        let level = u16::from_be_bytes([high, output[fine]]);
+                        [output[coarse], output[fine]] =
+                            fixture.curve.apply_16bit(level).to_be_bytes();
+                    }
+                    (Some(level), None) => output[coarse] = fixture.curve.apply(level),
+                    (None, _) => {}
+                }
+            }
+        }
 
-            response.send(channel_info).ok();
+        let hidden = |fixture: &&PatchedFixture| {
+            self.blackout_latched
+                || fixture.muted
+                || (!self.solo.is_empty() && !self.solo.contains(&fixture.channel))
+        };
+        for fixture in self.fixtures.values().filter(hidden) {
+            for offset in fixture.dark_offsets() {
+                if let Some(value) = output.get_mut(fixture.buffer_index(offset)) {
+                    *value = 0;
+                }
+            }
         }
-        UniverseCommand::GetDMXState(response) => {
-            response.send(universe.dmx_buffer).ok();
+
+        // Parked addresses go out as parked, whatever hid them
+        for (&address, &value) in &self.parked {
+            output[address] = value;
+        }
+
+        output
+    }
+
+    /// Push a range of intensity percents through the same pipeline as a real
+    /// frame, without touching the live buffer, to show how a fixture's first
+    /// intensity channel will track. Each level is scaled as if recorded in
+    /// the live cues, by their percent and master, highest taking precedence.
+    pub fn calibrate(&self, channel: usize) -> Result<Vec<CalibrationPoint>> {
+        let fixture = self
+            .get_fixture(channel)
+            .ok_or_else(|| anyhow!("No fixture found on channel {}", channel))?;
+        let offset = *fixture
+            .intensity_offsets()
+            .first()
+            .ok_or_else(|| anyhow!("Channel {} has no intensity channel", channel))?;
+        let address = fixture.buffer_index(offset);
+
+        Ok(CALIBRATION_PERCENTS
+            .iter()
+            .map(|&percent| {
+                let requested = from_percent(percent);
+                let played = self
+                    .playbacks
+                    .values()
+                    .map(|live| live.scale(requested))
+                    .max()
+                    .unwrap_or(requested);
+                let mut buffer = self.dmx_buffer;
+                buffer[address] = played;
+
+                CalibrationPoint {
+                    percent,
+                    requested,
+                    output: self.render(buffer)[address],
+                }
+            })
+            .collect())
+    }
+
+    pub fn send_buffer(&self, sink: &mut dyn DmxSink) -> Result<()> {
+        sink.send(&self.output_buffer())
+    }
+
+    /// The output frame with each fixture's dark channels (its intensity, or
+    /// its whole footprint without one) scaled by `level`, from 0.0 to 1.0
+    pub fn faded_output(&self, level: f32) -> [u8; DMX_BUFFER_LENGTH as usize] {
+        let mut output = self.output_buffer();
+        for fixture in self.fixtures.values() {
+            for offset in fixture.dark_offsets() {
+                if let Some(value) = output.get_mut(fixture.buffer_index(offset)) {
+                    *value = (*value as f32 * level).round() as u8;
+                }
+            }
+        }
+        output
+    }
+
+    pub fn status(&self) -> UniverseStatus {
+        UniverseStatus {
+            id: self.id,
+            output_enabled: self.output_enabled,
+            blackout_latched: self.blackout_latched,
+            fixture_count: self.fixtures.len(),
+            mirrors: self.mirrors.iter().map(|(t, s)| (*t, *s)).collect(),
+            parked: self.parked(),
+        }
+    }
+}
+
+/// What `neutral` resets
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Neutral {
+    /// Color mixing to open white, or with `white: false` every color channel off
+    Color { white: bool },
+    /// Pan and tilt to center
+    Position,
+    /// Each fixture's recorded home, or pan and tilt to center without one
+    Home,
+}
+
+impl Neutral {
+    /// Neutral value for a channel type, or None if this reset leaves it alone
+    fn value_for(&self, ctype: &ChannelType) -> Option<u8> {
+        match (self, ctype) {
+            (
+                Neutral::Color { white: true },
+                ChannelType::Red
+                | ChannelType::Green
+                | ChannelType::Blue
+                | ChannelType::White
+                | ChannelType::WarmWhite
+                | ChannelType::CoolWhite,
+            ) => Some(255),
+            // Amber, lime and UV tint white; cyan, magenta and yellow flags are open at 0
+            (Neutral::Color { .. }, ctype) if ctype.is_color() => Some(0),
+            (Neutral::Position, ChannelType::Pan | ChannelType::Tilt) => Some(128),
+            (Neutral::Position, ChannelType::PanFine | ChannelType::TiltFine) => Some(0),
+            _ => None,
+        }
+    }
+}
+
+/// Percent levels `calibrate` pushes through the output pipeline
+const CALIBRATION_PERCENTS: [u8; 6] = [0, 10, 25, 50, 75, 100];
+
+/// One row of a fixture's calibration table
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationPoint {
+    pub percent: u8,
+    /// DMX value the percent asks for
+    pub requested: u8,
+    /// What actually goes out on the wire
+    pub output: u8,
+}
+
+/// Output state of a universe, for the `status` command
+#[derive(Debug, Clone)]
+pub struct UniverseStatus {
+    pub id: u8,
+    pub output_enabled: bool,
+    pub blackout_latched: bool,
+    pub fixture_count: usize,
+    /// (mirroring fixture, fixture it copies)
+    pub mirrors: Vec<(usize, usize)>,
+    /// (address, value it is parked at)
+    pub parked: Vec<(usize, u8)>,
+}
+
+/// The cue a playback last played and the levels it was played at
+struct LiveCue {
+    cue_idx: usize,
+    channels: [u8; 513],
+    percent: u8,
+    master: u8,
+}
+
+impl LiveCue {
+    /// Recorded level at an intensity address, scaled by percent and master
+    fn intensity(&self, address: usize) -> u8 {
+        self.scale(self.channels[address])
+    }
+
+    /// An intensity level as this cue plays it
+    fn scale(&self, level: u8) -> u8 {
+        let scale = self.percent.min(100) as u32 * self.master as u32;
+        ((level as u32 * scale + 12_750) / 25_500) as u8
+    }
+}
+
+/// Free DMX address ranges (inclusive) and the first address that fits a footprint
+pub type FreeAddresses = (Vec<(u16, u16)>, Option<u16>);
+
+/// The interface refused to send the break that starts a frame, so the frame
+/// was not written. Usually transient, unlike a failed write.
+#[derive(Debug)]
+pub struct BreakFailed;
+
+impl fmt::Display for BreakFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Dmx failed to send break")
+    }
+}
+
+impl std::error::Error for BreakFailed {}
+
+/// Summary of a patched fixture, sent back to front-ends that list the patch
+#[derive(Debug, Clone, Serialize)]
+pub struct FixtureInfo {
+    pub channel: usize,
+    pub label: String,
+    pub profile: String,
+    pub dmx_start: u16,
+    pub footprint: u8,
+    /// Type, offset in channel order
+    pub channels: Vec<(String, u8)>,
+    /// OFL categories, e.g. "Moving Head"
+    pub categories: Vec<String>,
+    pub muted: bool,
+}
+
+impl From<&PatchedFixture> for FixtureInfo {
+    fn from(fixture: &PatchedFixture) -> Self {
+        FixtureInfo {
+            channel: fixture.channel,
+            label: fixture.label.clone(),
+            profile: fixture.profile.name.clone(),
+            dmx_start: fixture.dmx_start,
+            footprint: fixture.profile.footprint,
+            channels: fixture
+                .profile
+                .channels
+                .iter()
+                .map(|(channel_type, offset)| (format!("{:?}", channel_type), *offset))
+                .collect(),
+            categories: fixture.profile.categories.clone(),
+            muted: fixture.muted,
+        }
+    }
+}
+
+/// What each DMX range of one fixture channel does, for building editors
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelCapabilities {
+    pub offset: u8,
+    pub channel_type: String,
+    pub capabilities: Vec<OflCapability>,
+    /// Offset of the channel whose value selects what this one does, if any
+    pub depends_on: Option<u8>,
+}
+
+impl ChannelCapabilities {
+    /// Capabilities of every channel of a fixture, in channel order
+    pub fn for_fixture(fixture: &PatchedFixture) -> Vec<Self> {
+        fixture
+            .profile
+            .channels
+            .iter()
+            .map(|(channel_type, offset)| ChannelCapabilities {
+                offset: *offset,
+                channel_type: format!("{:?}", channel_type),
+                capabilities: fixture
+                    .profile
+                    .capabilities
+                    .get(offset)
+                    .cloned()
+                    .unwrap_or_default(),
+                depends_on: fixture.profile.switches.get(offset).copied(),
+            })
+            .collect()
+    }
+}
+
+impl FixtureInfo {
+    /// DMX buffer index for a channel offset within this fixture
+    pub fn buffer_index(&self, offset: u8) -> usize {
+        self.dmx_start as usize + offset as usize + 1
+    }
+
+    /// Whether a DMX buffer index falls inside this fixture's footprint
+    pub fn covers(&self, address: usize) -> bool {
+        (self.buffer_index(0)..self.buffer_index(self.footprint)).contains(&address)
+    }
+
+    /// Name of the channel type driven by a DMX buffer index, if it lands on this fixture
+    pub fn channel_at(&self, address: usize) -> Option<&str> {
+        let offset = address.checked_sub(self.dmx_start as usize + 1)?;
+
+        self.channels
+            .iter()
+            .find(|(_, channel_offset)| *channel_offset as usize == offset)
+            .map(|(channel_type, _)| channel_type.as_str())
+    }
+
+    /// Writes that give `target` this fixture's values in `dmx_state`, for the
+    /// channel types both have. The Nth channel of a type pairs with the Nth,
+    /// like a live mirror.
+    pub fn copy_to(&self, target: &FixtureInfo, dmx_state: &[u8; 513]) -> Vec<(usize, u8)> {
+        let mut instances: HashMap<&str, usize> = HashMap::new();
+        let mut writes = Vec::new();
+        for (channel_type, offset) in &target.channels {
+            let instance = instances.entry(channel_type).or_default();
+            let source_offset = self
+                .channels
+                .iter()
+                .filter(|(source_type, _)| source_type == channel_type)
+                .nth(*instance)
+                .map(|(_, source_offset)| *source_offset);
+            *instance += 1;
+
+            let address = target.buffer_index(*offset);
+            if let Some(&value) = source_offset
+                .filter(|_| address < dmx_state.len())
+                .and_then(|source_offset| dmx_state.get(self.buffer_index(source_offset)))
+            {
+                writes.push((address, value));
+            }
         }
+        writes
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum UniverseCommand {
+    // Single channel update
+    SetChannel {
+        channel: usize,
+        value: u8,
+    },
+
+    // Multiple channels at once (for cues)
+    SetMultiple {
+        changes: Vec<(usize, u8)>,
+    },
+
+    // Complete cue with metadata
+    PlayCue {
+        /// Named playback the cue belongs to; its intensities merge HTP with the others
+        playback: String,
+        cue_idx: usize,
+        cue_data: [u8; 513],
+        fade_time_ms: u32,
+        /// Intensity channels are played at this percent of their recorded level
+        intensity_percent: u8,
+        /// The cue's own fader level, applied on top of `intensity_percent`
+        master: u8,
+    },
+
+    // Rescale the intensity of the cue live on a playback
+    SetCueLevel {
+        playback: String,
+        cue_idx: usize,
+        level: u8,
+    },
+
+    // Fixture-level commands
+    SetFixture {
+        fixture_channel: usize,
+        intensity: Option<u8>,
+        color: Option<(u8, u8, u8)>, // RGB
+    },
+
+    // Write raw bytes across a fixture's footprint
+    SetFixtureRaw {
+        fixture_channel: usize,
+        values: Vec<u8>,
+    },
+
+    // Set one channel of a fixture by its OFL channel name
+    SetChannelByName {
+        fixture_channel: usize,
+        name: String,
+        value: u8,
+    },
+
+    // Set the Nth (0-based) channel of a type, for fixtures that repeat a function
+    SetFixtureIndexed {
+        fixture_channel: usize,
+        ctype: ChannelType,
+        instance: usize,
+        value: u8,
+    },
+
+    // Set a 16-bit function across its coarse and fine channels
+    SetFixture16Bit {
+        fixture_channel: usize,
+        ctype: ChannelType,
+        value: u16,
+    },
+
+    // White point in Kelvin, by CCT channel or RGB mix
+    SetColorTemperature {
+        fixture_channel: usize,
+        kelvin: u16,
+    },
+
+    // Pan and tilt, 16-bit on axes with a fine channel
+    SetPosition {
+        fixture_channel: usize,
+        pan: u16,
+        tilt: u16,
+    },
+
+    // Gobo wheel slot, numbered from 1
+    SelectGobo {
+        fixture_channel: usize,
+        slot: usize,
+    },
+
+    // Strobe rate, failing for fixtures without a strobe channel
+    SetStrobe {
+        fixture_channel: usize,
+        rate: u8,
+    },
+
+    // Color by hue in degrees, saturation and value (0-1)
+    SetHsv {
+        fixture_channel: usize,
+        hue: f32,
+        saturation: f32,
+        value: f32,
+    },
+
+    // One channel function on every fixture that has it
+    SetAllFixtureValue {
+        ctype: ChannelType,
+        value: u8,
+    },
+
+    // Reset color or position on every fixture, answering how many changed
+    Neutralize {
+        target: Neutral,
+        response: std::sync::mpsc::Sender<Result<usize>>,
+    },
+
+    // Record current pan/tilt (and color) as home, for one fixture or all with None
+    SetHome {
+        fixture_channel: Option<usize>,
+        with_color: bool,
+        response: std::sync::mpsc::Sender<Result<usize>>,
+    },
+
+    // Channel functions on every fixture in an OFL category
+    SetCategoryValues {
+        category: String,
+        values: Vec<(ChannelType, u8)>,
+    },
+
+    // Cap (or uncap with None) one color channel of a fixture
+    SetColorLimit {
+        fixture_channel: usize,
+        ctype: ChannelType,
+        limit: Option<u8>,
+    },
+
+    // Change how a fixture's intensity responds at output
+    SetDimmerCurve {
+        fixture_channel: usize,
+        curve: DimmerCurve,
+    },
+
+    // Add a fixture to the solo set, hiding every fixture outside it
+    AddSolo(usize),
+    ClearSolo,
+
+    // Stop or resume transmitting a universe's frames without touching its data
+    SetOutputEnabled {
+        universe: u8,
+        enabled: bool,
+    },
+
+    // Change the sACN priority a universe (the first with None) goes out at.
+    // Answered by the DMX thread, which owns the outputs.
+    SetPriority {
+        universe: Option<u8>,
+        priority: u8,
+        response: std::sync::mpsc::Sender<Result<()>>,
+    },
+
+    // Keep a fixture dark without unpatching it
+    SetMuted {
+        fixture_channel: usize,
+        muted: bool,
+    },
+
+    // Copy another fixture's output onto this one after every command, or stop with None
+    SetMirror {
+        fixture_channel: usize,
+        source: Option<usize>,
+    },
+
+    // Show control
+    Blackout,
+
+    // Drop every hand-set level so only playbacks drive the output
+    ClearManual,
+
+    // Hold every fixture dark at the output until released
+    SetBlackoutLatch(bool),
+
+    // Hold an address at a value through cues, blackout and hand writes
+    Park {
+        address: usize,
+        value: u8,
+    },
+    Unpark {
+        address: usize,
+    },
+
+    // Query commands (with response channel)
+    GetChannelValue {
+        channel: usize,
+        response: std::sync::mpsc::Sender<u8>,
+    },
+
+    // Get fixture channel information
+    GetChannels {
+        fixture_channel: usize,
+        response: std::sync::mpsc::Sender<Option<Vec<(String, usize, usize)>>>, // (channel_type, dmx_address, offset)
+    },
+
+    GetDMXState(std::sync::mpsc::Sender<[u8; 513]>),
+
+    // One playback's contribution to the output, None if it has no live cue
+    GetPlaybackOutput {
+        playback: String,
+        response: std::sync::mpsc::Sender<Option<[u8; 513]>>,
+    },
+
+    // Addresses changed by hand since the last cue recall, as (address, value)
+    GetTouched(std::sync::mpsc::Sender<Vec<(usize, u8)>>),
+
+    // Unused DMX address ranges and the first one that fits `footprint`
+    GetFreeAddresses {
+        footprint: u16,
+        response: std::sync::mpsc::Sender<FreeAddresses>,
+    },
+
+    // List every patched fixture
+    GetFixtures(std::sync::mpsc::Sender<Vec<FixtureInfo>>),
+
+    // The patch itself, for saving a show
+    GetPatch(std::sync::mpsc::Sender<Vec<PatchedFixture>>),
+
+    // Replace the whole patch, as when a show is loaded
+    SetPatch(Vec<PatchedFixture>),
+
+    // Capability details for each channel of a fixture
+    GetFixtureCapabilities {
+        fixture_channel: usize,
+        response: std::sync::mpsc::Sender<Option<Vec<ChannelCapabilities>>>,
+    },
+
+    // Percent-to-output table for a fixture's intensity, for matching fixtures
+    GetCalibration {
+        fixture_channel: usize,
+        response: std::sync::mpsc::Sender<Result<Vec<CalibrationPoint>>>,
+    },
+
+    // Output performance, answered by the DMX thread itself
+    GetStats(std::sync::mpsc::Sender<DmxStats>),
+
+    // Change the refresh rate while running; replies with the clamped rate used
+    SetFrameRate {
+        hz: f32,
+        response: std::sync::mpsc::Sender<f32>,
+    },
+
+    // Addresses held at 255 for at least `longer_than`, also answered by the DMX thread
+    GetPinnedChannels {
+        longer_than: Duration,
+        response: std::sync::mpsc::Sender<Vec<PinnedChannel>>,
+    },
+
+    // Capture every following command with its timing until stopped
+    StartSessionRecording,
+    StopSessionRecording(std::sync::mpsc::Sender<Vec<SessionEvent>>),
+
+    // Run a command on the universe with this id; unwrapped commands go to the first
+    OnUniverse {
+        universe: u8,
+        command: Box<UniverseCommand>,
+    },
+
+    // Status of every universe the DMX thread drives, answered by the thread itself
+    GetUniverses(std::sync::mpsc::Sender<Vec<UniverseStatus>>),
+}
+
+impl UniverseCommand {
+    /// The universe a command names, if it names one
+    fn target_universe(&self) -> Option<u8> {
+        match self {
+            UniverseCommand::OnUniverse { universe, .. }
+            | UniverseCommand::SetOutputEnabled { universe, .. } => Some(*universe),
+            _ => None,
+        }
+    }
+}
+
+/// A universe and the sink its frames go out through
+pub struct UniverseOutput {
+    pub universe: Universe,
+    pub sink: Box<dyn DmxSink>,
+}
+
+/// Drive every universe in `outputs`, each through its own sink. Commands go
+/// to the first universe unless wrapped in `UniverseCommand::OnUniverse`.
+pub fn dmx_thread(
+    mut outputs: Vec<UniverseOutput>, // Now OWNED by this thread
+    command_rx: Receiver<UniverseCommand>,
+    shutdown_rx: Receiver<()>,
+    mut config: DmxConfig,
+) {
+    if outputs.is_empty() {
+        eprintln!("DMX thread has no universes to drive");
+        return;
+    }
+    println!("DMX thread started");
+
+    let mut last_dmx_send = Instant::now();
+    let mut stats = StatsTracker::new();
+    let mut pending: VecDeque<UniverseCommand> = VecDeque::new();
+    let mut recorder: Option<SessionRecorder> = None;
+    let mut writes = PendingWrites::default();
+    let mut health: Vec<HealthMonitor> = outputs
+        .iter()
+        .map(|output| HealthMonitor::new(output.universe.id))
+        .collect();
+
+    loop {
+        // Check for shutdown
+        if shutdown_rx.try_recv().is_ok() {
+            println!("DMX thread shutting down");
+            #[cfg(not(feature = "no-dmx"))]
+            if let Err(error) = fade_out(&mut outputs, config.shutdown_fade, config.frame_interval)
+            {
+                eprintln!("Fade out stopped early: {}", error);
+            }
+            break;
+        }
+
+        // Pull in everything waiting so the backlog can be measured
+        pending.extend(command_rx.try_iter());
+        stats.record_queue_depth(pending.len());
+
+        // When backed up, only the latest write to each address matters
+        if pending.len() > COALESCE_THRESHOLD {
+            stats.record_coalesced(coalesce_channel_writes(&mut pending));
+        }
+
+        // Process pending commands until the tick's budget is spent
+        let (commands_processed, overrun) =
+            drain_within(&mut pending, config.command_budget, |command| {
+                // Runs of channel writes are collected and applied once; anything
+                // else sees them applied first so ordering is kept
+                if !PendingWrites::is_write(&command) {
+                    stats.record_deduplicated(writes.flush(&mut outputs[0].universe));
+                }
+
+                match command {
+                    UniverseCommand::GetStats(response) => {
+                        let snapshot = DmxStats {
+                            frame_interval: config.frame_interval,
+                            command_budget: config.command_budget,
+                            ..stats.snapshot()
+                        };
+                        response.send(snapshot).ok();
+                    }
+                    UniverseCommand::SetFrameRate { hz, response } => {
+                        let (interval, hz) = frame_interval_for(hz);
+                        config.frame_interval = interval;
+                        response.send(hz).ok();
+                    }
+                    UniverseCommand::GetPinnedChannels {
+                        longer_than,
+                        response,
+                    } => {
+                        let now = Instant::now();
+                        let mut pinned: Vec<PinnedChannel> = health
+                            .iter()
+                            .flat_map(|monitor| monitor.pinned(now, longer_than))
+                            .collect();
+                        pinned.sort_by_key(|channel| std::cmp::Reverse(channel.duration));
+                        response.send(pinned).ok();
+                    }
+                    ref command @ UniverseCommand::SetPriority {
+                        universe,
+                        priority,
+                        ref response,
+                    } => {
+                        if let Some(recorder) = recorder.as_mut() {
+                            recorder.record(command);
+                        }
+                        let output = match universe {
+                            Some(id) => outputs.iter_mut().find(|output| output.universe.id == id),
+                            None => outputs.first_mut(),
+                        };
+                        let result = match output {
+                            Some(output) => output.sink.set_priority(priority),
+                            None => Err(anyhow!("No universe {}", universe.unwrap_or_default())),
+                        };
+                        response.send(result).ok();
+                    }
+                    UniverseCommand::GetUniverses(response) => {
+                        let statuses = outputs
+                            .iter()
+                            .map(|output| output.universe.status())
+                            .collect();
+                        response.send(statuses).ok();
+                    }
+                    UniverseCommand::StartSessionRecording => {
+                        recorder = Some(SessionRecorder::new());
+                    }
+                    UniverseCommand::StopSessionRecording(response) => {
+                        let events = recorder.take().map(SessionRecorder::finish);
+                        response.send(events.unwrap_or_default()).ok();
+                    }
+                    command => {
+                        if let Some(recorder) = recorder.as_mut() {
+                            recorder.record(&command);
+                        }
+                        if !writes.collect(&command) {
+                            let target = command.target_universe();
+                            let universe = match target {
+                                Some(id) => outputs
+                                    .iter_mut()
+                                    .map(|output| &mut output.universe)
+                                    .find(|universe| universe.id == id),
+                                None => Some(&mut outputs[0].universe),
+                            };
+                            match universe {
+                                Some(universe) => process_command(universe, command),
+                                None => eprintln!("No universe {}", target.unwrap_or_default()),
+                            }
+                        }
+                    }
+                }
+            });
+        stats.record_deduplicated(writes.flush(&mut outputs[0].universe));
+        if overrun {
+            stats.record_overrun();
+        }
+        stats.record_commands(Instant::now(), commands_processed);
+        for (monitor, output) in health.iter_mut().zip(&outputs) {
+            monitor.observe(Instant::now(), &output.universe.dmx_buffer);
+        }
+        for output in &mut outputs {
+            output.universe.settle_fade(Instant::now());
+        }
+
+        // Send DMX at regular intervals, one frame per universe
+        #[cfg(not(feature = "no-dmx"))]
+        if last_dmx_send.elapsed() >= config.frame_interval {
+            last_dmx_send = Instant::now();
+            let (mut sent, mut failed, mut stopped) = (false, false, false);
+            for output in outputs
+                .iter_mut()
+                .filter(|output| output.universe.output_enabled)
+            {
+                match output.universe.send_buffer(output.sink.as_mut()) {
+                    Ok(()) => sent = true,
+                    // Skip this frame and retry on the next tick rather than stopping output
+                    Err(error) if error.is::<BreakFailed>() => {
+                        failed = true;
+                        if stats.record_break(false) == 1 {
+                            eprintln!(
+                                "DMX send error on universe {}: {}",
+                                output.universe.id, error
+                            );
+                        }
+                    }
+                    Err(error) => {
+                        eprintln!(
+                            "DMX send error on universe {}: {}",
+                            output.universe.id, error
+                        );
+                        stopped = true;
+                        break;
+                    }
+                }
+            }
+            if stopped {
+                break;
+            }
+            if sent && !failed {
+                stats.record_break(true);
+                stats.record_frame(last_dmx_send, config.frame_interval);
+            }
+        }
+
+        // 4. Small sleep to prevent busy-waiting
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    // Cleanup; dropping the sinks closes the ports
+    drop(outputs);
+    println!("DMX thread stopped");
+}
+
+/// Fade every enabled universe to black together over `duration`, one frame
+/// each per `interval`
+fn fade_out(outputs: &mut [UniverseOutput], duration: Duration, interval: Duration) -> Result<()> {
+    let steps = (duration.as_millis() / interval.as_millis().max(1)).max(1) as u32;
+    for step in 1..=steps {
+        let level = 1.0 - step as f32 / steps as f32;
+        for output in outputs
+            .iter_mut()
+            .filter(|output| output.universe.output_enabled)
+        {
+            output.sink.send(&output.universe.faded_output(level))?;
+        }
+        thread::sleep(interval);
+    }
+
+    Ok(())
+}
+
+/// Channel writes collected during a tick, applied together so an address
+/// written several times is only set to its final value
+#[derive(Default)]
+struct PendingWrites {
+    values: BTreeMap<usize, u8>,
+    received: u64,
+}
+
+impl PendingWrites {
+    fn is_write(command: &UniverseCommand) -> bool {
+        matches!(
+            command,
+            UniverseCommand::SetChannel { .. } | UniverseCommand::SetMultiple { .. }
+        )
+    }
+
+    /// Take the values from a SetChannel or SetMultiple, returning false for
+    /// any other command
+    fn collect(&mut self, command: &UniverseCommand) -> bool {
+        match command {
+            UniverseCommand::SetChannel { channel, value } => {
+                self.values.insert(*channel, *value);
+                self.received += 1;
+            }
+            UniverseCommand::SetMultiple { changes } => {
+                self.values.extend(changes.iter().copied());
+                self.received += changes.len() as u64;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Apply the collected writes, returning how many were skipped because a
+    /// later write replaced them
+    fn flush(&mut self, universe: &mut Universe) -> u64 {
+        if self.values.is_empty() {
+            return 0;
+        }
+
+        let applied = self.values.len() as u64;
+        for (channel, value) in std::mem::take(&mut self.values) {
+            if let Err(e) = universe.set_dmx_address(channel, value) {
+                eprintln!("Failed to set channel {}: {}", channel, e);
+            }
+        }
+        universe.sync_mirrors();
+
+        let skipped = self.received - applied;
+        self.received = 0;
+        skipped
+    }
+}
+
+/// Hand queued commands to `handle` until `budget` has elapsed, leaving the rest
+/// for the next tick so a burst can't hold up output. Returns how many were
+/// handled and whether any had to wait.
+fn drain_within(
+    pending: &mut VecDeque<UniverseCommand>,
+    budget: Duration,
+    mut handle: impl FnMut(UniverseCommand),
+) -> (u32, bool) {
+    let started = Instant::now();
+    let mut handled = 0;
+
+    while let Some(command) = pending.pop_front() {
+        handle(command);
+        handled += 1;
+
+        if started.elapsed() >= budget {
+            break;
+        }
+    }
+
+    (handled, !pending.is_empty())
+}
+
+/// Drop SetChannel writes that a later SetChannel to the same address in the
+/// same run of writes overrides, returning how many were removed. Any other
+/// command ends the run, as a query, cue or recording may depend on every
+/// write before it.
+fn coalesce_channel_writes(pending: &mut VecDeque<UniverseCommand>) -> usize {
+    let before = pending.len();
+    let mut written = HashSet::new();
+    let mut kept = VecDeque::with_capacity(before);
+
+    // Walk newest to oldest so the first write seen for an address is the one kept
+    while let Some(command) = pending.pop_back() {
+        match &command {
+            UniverseCommand::SetChannel { channel, .. } => {
+                if !written.insert(*channel) {
+                    continue;
+                }
+            }
+            _ => written.clear(),
+        }
+        kept.push_front(command);
+    }
+
+    *pending = kept;
+    before - pending.len()
+}
+
+fn process_command(universe: &mut Universe, command: UniverseCommand) {
+    match command {
+        UniverseCommand::SetChannel { channel, value } => {
+            if let Err(e) = universe.set_dmx_address(channel, value) {
+                eprintln!("Failed to set channel {}: {}", channel, e);
+            }
+        }
+        UniverseCommand::SetMultiple { changes } => {
+            for (channel, value) in changes {
+                if let Err(e) = universe.set_dmx_address(channel, value) {
+                    eprintln!("Failed to set channel {}: {}", channel, e);
+                }
+            }
+        }
+        UniverseCommand::PlayCue {
+            playback,
+            cue_idx,
+            cue_data,
+            fade_time_ms,
+            intensity_percent,
+            master,
+        } => {
+            println!("Playing cue {} with {} channels", cue_idx, cue_data.len());
+
+            // A cue arriving mid-fade starts from wherever the output is now
+            let from = universe.levels_at(Instant::now());
+            universe.play_cue(&playback, cue_idx, &cue_data, intensity_percent, master);
+            universe.start_fade(from, Duration::from_millis(fade_time_ms as u64));
+        }
+        UniverseCommand::SetCueLevel {
+            playback,
+            cue_idx,
+            level,
+        } => {
+            if let Err(e) = universe.set_cue_level(&playback, cue_idx, level) {
+                eprintln!("Failed to set cue level: {}", e);
+            }
+        }
+        UniverseCommand::SetFixture {
+            fixture_channel,
+            intensity,
+            color,
+        } => {
+            // Find fixture and set its channels
+            if let Some(_fixture) = universe.get_fixture(fixture_channel) {
+                let mut updates = Vec::new();
+
+                if let Some(intensity_val) = intensity {
+                    updates.push((crate::fixture::patch::ChannelType::Intensity, intensity_val));
+                }
+
+                if let Some((r, g, b)) = color {
+                    updates.push((crate::fixture::patch::ChannelType::Red, r));
+                    updates.push((crate::fixture::patch::ChannelType::Green, g));
+                    updates.push((crate::fixture::patch::ChannelType::Blue, b));
+                }
+
+                universe.set_fixture_values(fixture_channel, &updates).ok();
+            }
+        }
+        UniverseCommand::SetFixtureRaw {
+            fixture_channel,
+            values,
+        } => {
+            if let Err(e) = universe.set_fixture_raw(fixture_channel, &values) {
+                eprintln!("Failed to set raw values on channel {}: {}", fixture_channel, e);
+            }
+        }
+        UniverseCommand::SetChannelByName {
+            fixture_channel,
+            name,
+            value,
+        } => {
+            if let Err(e) = universe.set_channel_by_name(fixture_channel, &name, value) {
+                eprintln!(
+                    "Failed to set '{}' on channel {}: {}",
+                    name, fixture_channel, e
+                );
+            }
+        }
+        UniverseCommand::SetFixtureIndexed {
+            fixture_channel,
+            ctype,
+            instance,
+            value,
+        } => {
+            if let Err(e) =
+                universe.set_fixture_values_indexed(fixture_channel, ctype, instance, value)
+            {
+                eprintln!("Failed to set channel {}: {}", fixture_channel, e);
+            }
+        }
+        UniverseCommand::SetFixture16Bit {
+            fixture_channel,
+            ctype,
+            value,
+        } => {
+            if let Err(e) = universe.set_fixture_value_16bit(fixture_channel, ctype, value) {
+                eprintln!("Failed to set channel {}: {}", fixture_channel, e);
+            }
+        }
+        UniverseCommand::SetColorTemperature {
+            fixture_channel,
+            kelvin,
+        } => {
+            if let Err(e) = universe.set_color_temperature(fixture_channel, kelvin) {
+                eprintln!(
+                    "Failed to set color temperature on channel {}: {}",
+                    fixture_channel, e
+                );
+            }
+        }
+        UniverseCommand::SetPosition {
+            fixture_channel,
+            pan,
+            tilt,
+        } => {
+            if let Err(e) = universe.set_position(fixture_channel, pan, tilt) {
+                eprintln!(
+                    "Failed to set position on channel {}: {}",
+                    fixture_channel, e
+                );
+            }
+        }
+        UniverseCommand::SelectGobo {
+            fixture_channel,
+            slot,
+        } => {
+            if let Err(e) = universe.select_gobo(fixture_channel, slot) {
+                eprintln!(
+                    "Failed to select gobo on channel {}: {}",
+                    fixture_channel, e
+                );
+            }
+        }
+        UniverseCommand::SetStrobe {
+            fixture_channel,
+            rate,
+        } => {
+            if let Err(e) = universe.set_strobe(fixture_channel, rate) {
+                eprintln!("Failed to set strobe on channel {}: {}", fixture_channel, e);
+            }
+        }
+        UniverseCommand::SetHsv {
+            fixture_channel,
+            hue,
+            saturation,
+            value,
+        } => {
+            if let Err(e) = universe.set_hsv(fixture_channel, hue, saturation, value) {
+                eprintln!("Failed to set HSV on channel {}: {}", fixture_channel, e);
+            }
+        }
+        UniverseCommand::SetAllFixtureValue { ctype, value } => {
+            if let Err(e) = universe.set_all_fixture_value(&ctype, value) {
+                eprintln!("Failed to set {:?} on all fixtures: {}", ctype, e);
+            }
+        }
+        UniverseCommand::SetHome {
+            fixture_channel,
+            with_color,
+            response,
+        } => {
+            let channels = match fixture_channel {
+                Some(channel) => vec![channel],
+                None => universe.fixtures.keys().copied().collect(),
+            };
+            let stored = channels.iter().try_fold(0, |stored, &channel| {
+                Ok(stored + universe.set_home_from_current(channel, with_color)?)
+            });
+            response.send(stored).ok();
+        }
+        UniverseCommand::Neutralize { target, response } => {
+            response.send(universe.neutralize(target)).ok();
+        }
+        UniverseCommand::SetCategoryValues { category, values } => {
+            if let Err(e) = universe.set_category_values(&category, &values) {
+                eprintln!("Failed to set category {}: {}", category, e);
+            }
+        }
+        UniverseCommand::SetColorLimit {
+            fixture_channel,
+            ctype,
+            limit,
+        } => {
+            if let Err(e) = universe.set_color_limit(fixture_channel, ctype, limit) {
+                eprintln!("Failed to limit channel {}: {}", fixture_channel, e);
+            }
+        }
+        UniverseCommand::SetDimmerCurve {
+            fixture_channel,
+            curve,
+        } => {
+            if let Err(e) = universe.set_dimmer_curve(fixture_channel, curve) {
+                eprintln!("Failed to set curve on channel {}: {}", fixture_channel, e);
+            }
+        }
+        UniverseCommand::AddSolo(fixture_channel) => {
+            if let Err(e) = universe.add_solo(fixture_channel) {
+                eprintln!("Failed to solo channel {}: {}", fixture_channel, e);
+            }
+        }
+        UniverseCommand::ClearSolo => universe.clear_solo(),
+        UniverseCommand::SetOutputEnabled {
+            universe: id,
+            enabled,
+        } => {
+            if id == universe.id {
+                universe.output_enabled = enabled;
+            } else {
+                eprintln!("No universe {}", id);
+            }
+        }
+        UniverseCommand::SetMuted {
+            fixture_channel,
+            muted,
+        } => {
+            if let Err(e) = universe.set_muted(fixture_channel, muted) {
+                eprintln!("Failed to mute channel {}: {}", fixture_channel, e);
+            }
+        }
+        UniverseCommand::SetMirror {
+            fixture_channel,
+            source,
+        } => {
+            if let Err(e) = universe.set_mirror(fixture_channel, source) {
+                eprintln!("Failed to mirror channel {}: {}", fixture_channel, e);
+            }
+        }
+        UniverseCommand::Blackout => {
+            println!("Blackout command received");
+            if let Err(e) = universe.blackout() {
+                eprintln!("{}", e);
+            }
+        }
+        UniverseCommand::SetBlackoutLatch(latched) => {
+            universe.blackout_latched = latched;
+        }
+        UniverseCommand::ClearManual => universe.clear_manual(),
+        UniverseCommand::Park { address, value } => {
+            if let Err(e) = universe.park(address, value) {
+                eprintln!("Failed to park address {}: {}", address, e);
+            }
+        }
+        UniverseCommand::Unpark { address } => {
+            if let Err(e) = universe.unpark(address) {
+                eprintln!("Failed to unpark: {}", e);
+            }
+        }
+        UniverseCommand::GetChannelValue { channel, response } => {
+            let value = universe.dmx_buffer.get(channel).copied().unwrap_or(0);
+            response.send(value).ok(); // Send response back
+        }
+        UniverseCommand::GetChannels {
+            fixture_channel,
+            response,
+        } => {
+            let channel_info = if let Some(fixture) = universe.get_fixture(fixture_channel) {
+                let mut channels = Vec::new();
+
+                // The address a write to this channel lands on, as `a <address>` takes it
+                for (channel_type, offset) in &fixture.profile.channels {
+                    let dmx_address = fixture.buffer_index(*offset);
+                    let type_name = format!("{:?}", channel_type); // Convert enum to string
+                    channels.push((type_name, dmx_address, *offset as usize));
+                }
+
+                Some(channels)
+            } else {
+                None // No fixture found at this channel
+            };
+
+            response.send(channel_info).ok();
+        }
+        UniverseCommand::GetDMXState(response) => {
+            response.send(universe.dmx_buffer).ok();
+        }
+        UniverseCommand::GetPlaybackOutput { playback, response } => {
+            response.send(universe.playback_output(&playback)).ok();
+        }
+        UniverseCommand::GetTouched(response) => {
+            response.send(universe.touched_values()).ok();
+        }
+        UniverseCommand::GetFixtures(response) => {
+            let fixtures = universe.fixtures.values().map(FixtureInfo::from).collect();
+            response.send(fixtures).ok();
+        }
+        UniverseCommand::GetPatch(response) => {
+            response
+                .send(universe.fixtures.values().cloned().collect())
+                .ok();
+        }
+        UniverseCommand::SetPatch(fixtures) => universe.replace_patch(fixtures),
+        UniverseCommand::GetFreeAddresses {
+            footprint,
+            response,
+        } => {
+            let suggestion = universe.next_free_address(footprint);
+            response
+                .send((universe.free_address_ranges(), suggestion))
+                .ok();
+        }
+        UniverseCommand::GetFixtureCapabilities {
+            fixture_channel,
+            response,
+        } => {
+            let capabilities = universe
+                .get_fixture(fixture_channel)
+                .map(ChannelCapabilities::for_fixture);
+            response.send(capabilities).ok();
+        }
+        UniverseCommand::GetCalibration {
+            fixture_channel,
+            response,
+        } => {
+            response.send(universe.calibrate(fixture_channel)).ok();
+        }
+        UniverseCommand::OnUniverse {
+            universe: id,
+            command,
+        } => {
+            if id == universe.id {
+                process_command(universe, *command);
+            } else {
+                eprintln!("No universe {}", id);
+            }
+        }
+        UniverseCommand::GetStats(_)
+        | UniverseCommand::SetFrameRate { .. }
+        | UniverseCommand::GetPinnedChannels { .. }
+        | UniverseCommand::GetUniverses(_)
+        | UniverseCommand::SetPriority { .. }
+        | UniverseCommand::StartSessionRecording
+        | UniverseCommand::StopSessionRecording(_) => {
+            // Stats, health, outputs and recording live in dmx_thread, which handles these before they get here
+        }
+    }
+
+    // Mirrors follow whatever the command did to their sources
+    universe.sync_mirrors();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fixture::patch::FixtureProfile;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn dual_dimmer_fixture() -> PatchedFixture {
+        PatchedFixture {
+            id: "test/dual-dimmer".to_string(),
+            mode: "3 Channel".to_string(),
+            channel: 1,
+            profile: Arc::new(FixtureProfile {
+                name: "Dual Dimmer".to_string(),
+                footprint: 3,
+                channels: vec![
+                    (ChannelType::Dimmer, 0),
+                    (ChannelType::Strobe, 1),
+                    (ChannelType::Dimmer, 2),
+                ],
+                channel_names: vec![
+                    "Dimmer 1".to_string(),
+                    "Shutter / Strobe".to_string(),
+                    "Dimmer 2".to_string(),
+                ],
+                capabilities: HashMap::new(),
+                categories: Vec::new(),
+                switches: HashMap::new(),
+                fine_channels: HashMap::new(),
+            }),
+            dmx_start: 10,
+            label: "Dual".to_string(),
+            muted: false,
+            color_limits: HashMap::new(),
+            home: HashMap::new(),
+            curve: DimmerCurve::Linear,
+        }
+    }
+
+    fn rgb_fixture() -> PatchedFixture {
+        PatchedFixture {
+            id: "test/rgb".to_string(),
+            mode: "3 Channel".to_string(),
+            channel: 2,
+            profile: Arc::new(FixtureProfile {
+                name: "RGB".to_string(),
+                footprint: 3,
+                channels: vec![
+                    (ChannelType::Red, 0),
+                    (ChannelType::Green, 1),
+                    (ChannelType::Blue, 2),
+                ],
+                channel_names: vec!["Red".to_string(), "Green".to_string(), "Blue".to_string()],
+                capabilities: HashMap::new(),
+                categories: Vec::new(),
+                switches: HashMap::new(),
+                fine_channels: HashMap::new(),
+            }),
+            dmx_start: 20,
+            label: "RGB".to_string(),
+            muted: false,
+            color_limits: HashMap::new(),
+            home: HashMap::new(),
+            curve: DimmerCurve::Linear,
+        }
+    }
+
+    /// A profile with one channel per `(type, name)` at consecutive offsets
+    /// and nothing else; tests fill in capabilities or fine channels as needed
+    fn test_profile(channels: &[(ChannelType, &str)]) -> FixtureProfile {
+        FixtureProfile {
+            name: "Test".to_string(),
+            footprint: channels.len() as u8,
+            channels: channels
+                .iter()
+                .zip(0..)
+                .map(|((ctype, _), offset)| (ctype.clone(), offset))
+                .collect(),
+            channel_names: channels.iter().map(|(_, name)| name.to_string()).collect(),
+            capabilities: HashMap::new(),
+            categories: Vec::new(),
+            switches: HashMap::new(),
+            fine_channels: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_solo_masks_output_without_touching_levels() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+        universe
+            .set_fixture_values(1, &[(ChannelType::Dimmer, 255)])
+            .unwrap();
+        universe.set_rgb(2, 10, 20, 30).unwrap();
+
+        universe.add_solo(2).unwrap();
+        let output = universe.output_buffer();
+        assert_eq!(output[11], 0);
+        assert_eq!(output[21..24], [10, 20, 30]);
+        assert_eq!(universe.dmx_buffer[11], 255);
+        assert!(universe.add_solo(9).is_err());
+
+        universe.clear_solo();
+        assert_eq!(universe.output_buffer()[11], 255);
+    }
+
+    #[test]
+    fn test_mirror_copies_shared_functions_after_each_command() {
+        let mut copy = dual_dimmer_fixture();
+        copy.channel = 3;
+        copy.dmx_start = 30;
+        let mut universe =
+            Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture(), copy]);
+
+        universe.set_mirror(3, Some(1)).unwrap();
+        // An RGB fixture shares no functions with a dimmer, so nothing is copied
+        universe.set_mirror(2, Some(1)).unwrap();
+        process_command(
+            &mut universe,
+            UniverseCommand::SetChannel {
+                channel: 13,
+                value: 200,
+            },
+        );
+        assert_eq!(universe.dmx_buffer[31..34], [0, 0, 200]);
+        // Still the white it was patched with
+        assert_eq!(universe.dmx_buffer[21..24], [255, 255, 255]);
+
+        // A mirror can't be the source of another one
+        assert!(universe.set_mirror(1, Some(3)).is_err());
+        assert!(universe.set_mirror(3, Some(3)).is_err());
+
+        universe.set_mirror(3, None).unwrap();
+        process_command(
+            &mut universe,
+            UniverseCommand::SetChannel {
+                channel: 13,
+                value: 50,
+            },
+        );
+        assert_eq!(universe.dmx_buffer[33], 200);
+    }
+
+    #[test]
+    fn test_faded_output_scales_only_dark_channels() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+        universe.set_fixture_raw(1, &[200, 100, 50]).unwrap();
+        universe.set_rgb(2, 255, 128, 0).unwrap();
+
+        let half = universe.faded_output(0.5);
+        // Both dimmers fade but the strobe channel between them holds
+        assert_eq!(half[11..14], [100, 100, 25]);
+        // Without an intensity channel the whole fixture fades
+        assert_eq!(half[21..24], [128, 64, 0]);
+
+        let dark = universe.faded_output(0.0);
+        assert_eq!(dark[11..14], [0, 100, 0]);
+        assert_eq!(universe.output_buffer()[11], 200);
+    }
+
+    #[test]
+    fn test_blackout_covers_fixtures_without_intensity() {
+        let mut overflowing = rgb_fixture();
+        overflowing.channel = 3;
+        overflowing.dmx_start = 510; // Blue lands past address 512
+        let mut universe = Universe::from_fixtures(
+            0,
+            vec![dual_dimmer_fixture(), rgb_fixture(), overflowing],
+        );
+        universe.get_fixture_mut(1).unwrap().muted = true;
+        let mut look = [0u8; 513];
+        look[11..14].copy_from_slice(&[255, 128, 255]);
+        look[21..24].copy_from_slice(&[10, 20, 30]);
+        look[511..513].copy_from_slice(&[40, 50]);
+        universe.set_dmx_buffer(&look);
+
+        // The broken fixture is reported, but everything else still goes dark
+        assert!(universe.blackout().is_err());
+        assert_eq!(universe.dmx_buffer[11..14], [0, 128, 0]);
+        assert_eq!(universe.dmx_buffer[21..24], [0, 0, 0]);
+        assert_eq!(universe.dmx_buffer[511..513], [0, 0]);
+    }
+
+    #[test]
+    fn test_blackout_then_update_leaves_the_cue_alone() {
+        let (command_tx, command_rx) = std::sync::mpsc::channel();
+        let mut universe = Universe::from_fixtures(0, vec![rgb_fixture()]);
+        let dmx_thread = std::thread::spawn(move || {
+            for command in command_rx {
+                process_command(&mut universe, command);
+            }
+        });
+        let mut engine = cue::CueEngine::new(command_tx.clone());
+        let snap = cue::CueTime::Fixed(Duration::ZERO);
+        engine
+            .record_cue("1", snap, &cue::RecordSource::Live)
+            .unwrap();
+        engine.go_in(cue::MAIN_PLAYBACK, None).unwrap();
+
+        // The RGB fixture has no intensity, so blackout zeroes its footprint
+        command_tx.send(UniverseCommand::Blackout).unwrap();
+        assert_eq!(engine.update_cue("1").unwrap(), 0);
+        let recorded = engine.current_cue().unwrap().channels();
+        assert_eq!(recorded[21..24], [255, 255, 255]);
+
+        drop((engine, command_tx));
+        dmx_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_calibrate_follows_output_pipeline() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture()]);
+        universe.set_dmx_address(11, 30).unwrap();
+
+        let table = universe.calibrate(1).unwrap();
+        let points: Vec<(u8, u8, u8)> = table
+            .iter()
+            .map(|point| (point.percent, point.requested, point.output))
+            .collect();
+        assert_eq!(points[3], (50, 128, 128));
+        assert_eq!(points[5], (100, 255, 255));
+        // The live level is left alone
+        assert_eq!(universe.dmx_buffer[11], 30);
+
+        // A live cue's master scales the table, the same as its recorded levels
+        universe.play_cue(cue::MAIN_PLAYBACK, 0, &[0; 513], 100, 128);
+        let mastered = universe.calibrate(1).unwrap();
+        assert_eq!(mastered[3].output, 64);
+        assert_eq!(mastered[5].output, 128);
+
+        universe.fixtures.get_mut(&1).unwrap().muted = true;
+        let muted = universe.calibrate(1).unwrap();
+        assert!(muted.iter().all(|point| point.output == 0));
+        assert!(universe.calibrate(2).is_err());
+    }
+
+    #[test]
+    fn test_output_toggle_keeps_programming() {
+        let mut universe = Universe::from_fixtures(0, vec![rgb_fixture()]);
+
+        process_command(
+            &mut universe,
+            UniverseCommand::SetOutputEnabled {
+                universe: 1,
+                enabled: false,
+            },
+        );
+        assert!(universe.output_enabled);
+
+        process_command(
+            &mut universe,
+            UniverseCommand::SetOutputEnabled {
+                universe: 0,
+                enabled: false,
+            },
+        );
+        assert!(!universe.output_enabled);
+
+        // The buffer still follows commands while output is off
+        universe.set_rgb(2, 1, 2, 3).unwrap();
+        assert_eq!(universe.output_buffer()[21..24], [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_play_cue_scales_only_intensity_channels() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+        let mut cue = [0u8; 513];
+        cue[11] = 200; // Dimmer
+        cue[12] = 200; // Strobe
+        cue[13] = 101; // Second dimmer
+        cue[21] = 200; // Red
+
+        process_command(
+            &mut universe,
+            UniverseCommand::PlayCue {
+                playback: "main".to_string(),
+                cue_idx: 0,
+                cue_data: cue,
+                fade_time_ms: 0,
+                intensity_percent: 50,
+                master: 255,
+            },
+        );
+        assert_eq!(universe.dmx_buffer[11], 100);
+        assert_eq!(universe.dmx_buffer[12], 200);
+        assert_eq!(universe.dmx_buffer[13], 51);
+        assert_eq!(universe.dmx_buffer[21], 200);
+    }
+
+    #[test]
+    fn test_playbacks_merge_intensity_htp_and_color_ltp() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+        let mut main = [0u8; 513];
+        main[11] = 200;
+        main[13] = 40;
+        main[21] = 255;
+        let mut house = [0u8; 513];
+        house[11] = 100;
+        house[13] = 90;
+        house[22] = 255;
+
+        universe.play_cue("main", 0, &main, 100, 255);
+        universe.play_cue("house", 0, &house, 100, 255);
+        assert_eq!(universe.dmx_buffer[11], 200);
+        assert_eq!(universe.dmx_buffer[13], 90);
+        assert_eq!(universe.dmx_buffer[21..23], [0, 255]);
+
+        // Pulling main down lets the house levels through
+        universe.play_cue("main", 1, &[0; 513], 100, 255);
+        assert_eq!(universe.dmx_buffer[11], 100);
+        assert_eq!(universe.dmx_buffer[13], 90);
+    }
+
+    #[test]
+    fn test_cue_level_rescales_live_intensity_only() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+        let mut main = [0u8; 513];
+        main[11] = 200;
+        main[21] = 255;
+        let mut house = [0u8; 513];
+        house[11] = 60;
+
+        universe.play_cue("house", 0, &house, 100, 255);
+        universe.play_cue("main", 4, &main, 100, 255);
+        universe.set_cue_level("main", 4, 128).unwrap();
+        assert_eq!(universe.dmx_buffer[11], 100);
+        assert_eq!(universe.dmx_buffer[21], 255);
+
+        // Below the house level, HTP lets house win
+        universe.set_cue_level("main", 4, 0).unwrap();
+        assert_eq!(universe.dmx_buffer[11], 60);
+
+        assert!(universe.set_cue_level("main", 3, 255).is_err());
+        assert!(universe.set_cue_level("side", 0, 255).is_err());
+    }
+
+    #[test]
+    fn test_free_address_ranges_skip_patched_fixtures() {
+        // Dual dimmer uses addresses 11-13, RGB uses 21-23
+        let universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+        assert_eq!(
+            universe.free_address_ranges(),
+            vec![(1, 10), (14, 20), (24, 512)]
+        );
+
+        assert_eq!(universe.next_free_address(10), Some(1));
+        assert_eq!(universe.next_free_address(11), Some(24));
+        assert_eq!(universe.next_free_address(600), None);
+        assert_eq!(Universe::new(0).free_address_ranges(), vec![(1, 512)]);
+    }
+
+    #[test]
+    fn test_touched_addresses_reset_on_cue_recall() {
+        let mut universe = Universe::from_fixtures(0, vec![rgb_fixture()]);
+        universe.set_rgb(2, 10, 20, 30).unwrap();
+        universe.set_dmx_address(100, 50).unwrap();
+        assert_eq!(
+            universe.touched_values(),
+            vec![(21, 10), (22, 20), (23, 30), (100, 50)]
+        );
+
+        universe.set_dmx_buffer(&[0; 513]);
+        assert!(universe.touched_values().is_empty());
+
+        // Resets aren't hand edits
+        let changed = universe.neutralize(Neutral::Color { white: true });
+        assert_eq!(changed.unwrap(), 1);
+        assert_eq!(universe.dmx_buffer[21..24], [255, 255, 255]);
+        assert!(universe.touched_values().is_empty());
+    }
+
+    #[test]
+    fn test_set_fixture_raw_bounded_by_footprint() {
+        let mut universe = Universe::from_fixtures(0, vec![rgb_fixture()]);
+
+        universe.set_fixture_raw(2, &[0, 128]).unwrap();
+        // Slots past the raw values keep the white the fixture was patched with
+        assert_eq!(universe.dmx_buffer[21..24], [0, 128, 255]);
+
+        assert!(universe.set_fixture_raw(2, &[1, 2, 3, 4]).is_err());
+        assert_eq!(universe.dmx_buffer[24], 0);
+    }
+
+    #[test]
+    fn test_category_values_only_reach_matching_fixtures() {
+        let mut mover = rgb_fixture();
+        Arc::get_mut(&mut mover.profile).unwrap().categories = vec!["Moving Head".to_string()];
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), mover]);
+
+        assert_eq!(universe.fixtures_by_category("moving head"), vec![2]);
+        assert!(universe.fixtures_by_category("Dimmer").is_empty());
+
+        let values = [(ChannelType::Red, 255), (ChannelType::Pan, 128)];
+        let matched = universe.set_category_values("Moving Head", &values);
+        assert_eq!(matched.unwrap(), 1);
+        assert_eq!(universe.dmx_buffer[21], 255);
+    }
+
+    #[test]
+    fn test_neutralize_color_leaves_intensity() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+        universe.set_fixture_values(1, &[(ChannelType::Dimmer, 200)]).unwrap();
+
+        let changed = universe.neutralize(Neutral::Color { white: true });
+        assert_eq!(changed.unwrap(), 1);
+        assert_eq!(universe.dmx_buffer[21..24], [255, 255, 255]);
+        assert_eq!(universe.dmx_buffer[11], 200);
+
+        universe.neutralize(Neutral::Color { white: false }).unwrap();
+        assert_eq!(universe.dmx_buffer[21..24], [0, 0, 0]);
+        assert_eq!(universe.neutralize(Neutral::Position).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_set_all_fixture_value_skips_fixtures_without_function() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+
+        let affected = universe.set_all_fixture_value(&ChannelType::Red, 128);
+        assert_eq!(affected.unwrap(), 1);
+        assert_eq!(universe.dmx_buffer[21], 128);
+        assert_eq!(universe.dmx_buffer[11..14], [0, 0, 0]);
+
+        let affected = universe.set_all_fixture_value(&ChannelType::Pan, 128);
+        assert_eq!(affected.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_color_limit_applies_to_manual_and_cue_values() {
+        let mut universe = Universe::from_fixtures(0, vec![rgb_fixture()]);
+        universe
+            .set_color_limit(2, ChannelType::Red, Some(200))
+            .unwrap();
+
+        universe.set_rgb(2, 255, 255, 0).unwrap();
+        assert_eq!(universe.dmx_buffer[21], 200);
+        assert_eq!(universe.dmx_buffer[22], 255);
+
+        // A recalled cue bypasses set_fixture_values, so the cap holds at output
+        let mut cue = [0u8; 513];
+        cue[21] = 255;
+        universe.set_dmx_buffer(&cue);
+        assert_eq!(universe.output_buffer()[21], 200);
+
+        assert!(universe
+            .set_color_limit(2, ChannelType::Pan, Some(10))
+            .is_err());
+    }
+
+    #[test]
+    fn test_set_channel_by_ofl_name() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture()]);
+
+        universe
+            .set_channel_by_name(1, "shutter / strobe", 200)
+            .unwrap();
+        assert_eq!(universe.dmx_buffer[12], 200);
+        // Names that aren't in the mode resolve through the channel type
+        universe.set_channel_by_name(1, "Dimmer", 100).unwrap();
+        assert_eq!(universe.dmx_buffer[11], 100);
+
+        let error = universe
+            .set_channel_by_name(1, "Color Wheel", 10)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Dimmer 1, Shutter / Strobe, Dimmer 2"));
+    }
+
+    #[test]
+    fn test_set_fixture_values_indexed() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture()]);
+
+        universe
+            .set_fixture_values_indexed(1, ChannelType::Dimmer, 1, 200)
+            .unwrap();
+        universe
+            .set_fixture_values_indexed(1, ChannelType::Dimmer, 0, 100)
+            .unwrap();
+        assert_eq!(universe.dmx_buffer[11], 100);
+        assert_eq!(universe.dmx_buffer[13], 200);
+
+        assert!(universe
+            .set_fixture_values_indexed(1, ChannelType::Dimmer, 2, 255)
+            .is_err());
+    }
+
+    #[test]
+    fn test_drain_within_stops_when_budget_is_spent() {
+        let mut pending: VecDeque<UniverseCommand> =
+            (0..5).map(|_| UniverseCommand::Blackout).collect();
+
+        // An empty budget still handles one command so the queue always moves
+        let mut handled = 0;
+        assert_eq!(
+            drain_within(&mut pending, Duration::ZERO, |_| handled += 1),
+            (1, true)
+        );
+        assert_eq!(handled, 1);
+        assert_eq!(pending.len(), 4);
+
+        assert_eq!(
+            drain_within(&mut pending, Duration::from_secs(1), |_| {}),
+            (4, false)
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_pending_writes_apply_final_value_once() {
+        let mut universe = Universe::new(0);
+        let mut writes = PendingWrites::default();
+
+        assert!(writes.collect(&UniverseCommand::SetChannel {
+            channel: 1,
+            value: 10
+        }));
+        assert!(writes.collect(&UniverseCommand::SetMultiple {
+            changes: vec![(1, 20), (2, 30), (1, 40)],
+        }));
+        assert!(!writes.collect(&UniverseCommand::Blackout));
+
+        assert_eq!(writes.flush(&mut universe), 2);
+        assert_eq!(universe.dmx_buffer[1..3], [40, 30]);
+        assert_eq!(writes.flush(&mut universe), 0);
+    }
+
+    #[test]
+    fn test_coalesce_keeps_latest_write_per_address() {
+        let mut pending: VecDeque<UniverseCommand> = VecDeque::from(vec![
+            UniverseCommand::SetChannel { channel: 1, value: 10 },
+            UniverseCommand::SetChannel { channel: 2, value: 20 },
+            UniverseCommand::SetChannel { channel: 1, value: 15 },
+            UniverseCommand::Blackout,
+            UniverseCommand::SetChannel { channel: 1, value: 30 },
+        ]);
+
+        assert_eq!(coalesce_channel_writes(&mut pending), 1);
+
+        let writes: Vec<(usize, u8)> = pending
+            .iter()
+            .filter_map(|command| match command {
+                UniverseCommand::SetChannel { channel, value } => Some((*channel, *value)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(writes, vec![(2, 20), (1, 15), (1, 30)]);
+        assert!(matches!(pending[2], UniverseCommand::Blackout));
+    }
+
+    #[test]
+    fn test_coalesce_keeps_writes_a_query_sees() {
+        let (response_tx, response_rx) = std::sync::mpsc::channel();
+        let mut pending: VecDeque<UniverseCommand> = VecDeque::from(vec![
+            UniverseCommand::SetChannel {
+                channel: 5,
+                value: 10,
+            },
+            UniverseCommand::GetChannelValue {
+                channel: 5,
+                response: response_tx,
+            },
+            UniverseCommand::SetChannel {
+                channel: 5,
+                value: 20,
+            },
+        ]);
+
+        assert_eq!(coalesce_channel_writes(&mut pending), 0);
+
+        let mut universe = Universe::new(0);
+        for command in pending {
+            process_command(&mut universe, command);
+        }
+        assert_eq!(response_rx.recv().unwrap(), 10);
+        assert_eq!(universe.dmx_buffer[5], 20);
+    }
+
+    #[test]
+    fn test_playback_output_is_only_that_playback() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+        let mut main = [0u8; 513];
+        main[11] = 200;
+        main[21] = 255;
+        let mut house = [0u8; 513];
+        house[11] = 100;
+        house[22] = 255;
+
+        universe.play_cue("house", 0, &house, 100, 255);
+        universe.play_cue("main", 0, &main, 50, 255);
+
+        let output = universe.playback_output("house").unwrap();
+        assert_eq!(output[11], 100);
+        assert_eq!(output[21], 0);
+        assert_eq!(output[22], 255);
+        assert_eq!(universe.playback_output("main").unwrap()[11], 100);
+        assert!(universe.playback_output("side").is_none());
+    }
+
+    #[test]
+    fn test_send_buffer_writes_start_code_and_full_frame() {
+        use crate::fake_dmx::{breaks_sent, fail_breaks, take_writes};
+        use crate::universe::sink::SerialSink;
+
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+        universe.set_fixture_raw(1, &[200, 100, 50]).unwrap();
+        universe.set_dmx_address(512, 7).unwrap();
+
+        let mut sink = SerialSink::open("fake").unwrap();
+        universe.send_buffer(&mut sink).unwrap();
+        let frames = take_writes();
+        assert_eq!(breaks_sent(), 1);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].len(), DMX_BUFFER_LENGTH as usize);
+        assert_eq!(frames[0][0], 0, "start code");
+        assert_eq!(frames[0][11..14], [200, 100, 50]);
+        assert_eq!(frames[0][512], 7);
+
+        // A failed break leaves the frame unwritten
+        fail_breaks(1);
+        let error = universe.send_buffer(&mut sink).unwrap_err();
+        assert!(error.is::<BreakFailed>());
+        assert!(take_writes().is_empty());
+
+        // Fading out sends one full frame per step, ending dark
+        let step = Duration::from_millis(25);
+        let mut outputs = [UniverseOutput {
+            universe,
+            sink: Box::new(sink),
+        }];
+        fade_out(&mut outputs, step * 4, step).unwrap();
+        let frames = take_writes();
+        assert_eq!(frames.len(), 4);
+        assert!(frames.iter().all(|frame| frame.len() == 513));
+        assert!(frames.iter().all(|frame| frame[0] == 0));
+        assert_eq!(frames[3][11..14], [0, 100, 0]);
+    }
+
+    #[test]
+    fn test_copy_to_writes_only_shared_types() {
+        let mut state = [0u8; 513];
+        state[11..14].copy_from_slice(&[200, 100, 50]);
+        state[21..24].copy_from_slice(&[1, 2, 3]);
+
+        let dual = FixtureInfo::from(&dual_dimmer_fixture());
+        let rgb = FixtureInfo::from(&rgb_fixture());
+        let mut copy = dual_dimmer_fixture();
+        copy.dmx_start = 30;
+        let copy = FixtureInfo::from(&copy);
+
+        assert_eq!(
+            dual.copy_to(&copy, &state),
+            vec![(31, 200), (32, 100), (33, 50)]
+        );
+        assert!(dual.copy_to(&rgb, &state).is_empty());
+
+        // Channels that would land past address 512 are left out
+        let mut overflowing = rgb_fixture();
+        overflowing.dmx_start = 510;
+        let overflowing = FixtureInfo::from(&overflowing);
+        assert_eq!(rgb.copy_to(&overflowing, &state), vec![(511, 1), (512, 2)]);
+    }
+
+    #[test]
+    fn test_blackout_drops_playbacks_and_latch_holds_output_dark() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+        let mut cue = [0u8; 513];
+        cue[11] = 200;
+        cue[21] = 255;
+        universe.play_cue("main", 0, &cue, 100, 255);
+
+        universe.blackout().unwrap();
+        assert_eq!(universe.dmx_buffer[11], 0);
+        // A fader move after blackout has no live cue to bring back
+        assert!(universe.set_cue_level("main", 0, 255).is_err());
+        assert_eq!(universe.dmx_buffer[11], 0);
+
+        universe.play_cue("main", 0, &cue, 100, 255);
+        process_command(&mut universe, UniverseCommand::SetBlackoutLatch(true));
+        universe.set_dmx_address(13, 90).unwrap();
+        let output = universe.output_buffer();
+        assert_eq!(output[11..14], [0, 0, 0]);
+        assert_eq!(output[21], 0);
+
+        // Releasing brings back whatever is underneath
+        process_command(&mut universe, UniverseCommand::SetBlackoutLatch(false));
+        let output = universe.output_buffer();
+        assert_eq!(output[11..14], [200, 0, 90]);
+        assert_eq!(output[21], 255);
+    }
+
+    #[test]
+    fn test_frame_interval_for_clamps_to_legal_rates() {
+        assert_eq!(frame_interval_for(40.0), (Duration::from_millis(25), 40.0));
+        assert_eq!(frame_interval_for(100.0).1, MAX_FRAME_RATE);
+        assert_eq!(frame_interval_for(0.0).0, Duration::from_secs(1));
+        assert_eq!(frame_interval_for(f32::NAN).1, MAX_FRAME_RATE);
+    }
+
+    #[test]
+    fn test_neutral_home_returns_to_recorded_values() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+        universe.set_rgb(2, 255, 120, 0).unwrap();
+
+        // Without color there is nothing on an RGB fixture to record
+        assert_eq!(universe.set_home_from_current(2, false).unwrap(), 0);
+        assert_eq!(universe.set_home_from_current(2, true).unwrap(), 3);
+        assert!(universe.set_home_from_current(9, true).is_err());
+
+        universe.set_rgb(2, 0, 0, 255).unwrap();
+        assert_eq!(universe.neutralize(Neutral::Home).unwrap(), 1);
+        assert_eq!(universe.dmx_buffer[21..24], [255, 120, 0]);
+    }
+
+    #[test]
+    fn test_color_fixtures_are_patched_on_white() {
+        let mut limited = rgb_fixture();
+        limited.channel = 3;
+        limited.dmx_start = 30;
+        limited.color_limits.insert(ChannelType::Blue, 180);
+        let universe =
+            Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture(), limited]);
+
+        assert_eq!(universe.dmx_buffer[21..24], [255, 255, 255]);
+        assert_eq!(universe.dmx_buffer[31..34], [255, 255, 180]);
+        // Fixtures without all three colors are left alone
+        assert_eq!(universe.dmx_buffer[11..14], [0, 0, 0]);
+        assert!(universe.touched_values().is_empty());
+
+        let mut universe = Universe::new(0);
+        universe.default_white = false;
+        universe.add_fixture(rgb_fixture());
+        assert_eq!(universe.dmx_buffer[21..24], [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_cue_buffer_keeps_the_configured_start_code() {
+        use crate::fake_dmx::take_writes;
+        use crate::universe::sink::SerialSink;
+
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture()]);
+        let mut cue = [0u8; 513];
+        cue[0] = 0xCC;
+        cue[11] = 200;
+        let mut sink = SerialSink::open("fake").unwrap();
+
+        universe.play_cue("main", 0, &cue, 100, 255);
+        universe.send_buffer(&mut sink).unwrap();
+        universe.start_code = 0x17;
+        universe.set_dmx_buffer(&cue);
+        universe.send_buffer(&mut sink).unwrap();
+
+        let frames = take_writes();
+        assert_eq!(frames[0][0], NULL_START_CODE);
+        assert_eq!(frames[0][11], 200);
+        assert_eq!(frames[1][0], 0x17);
+    }
+
+    #[test]
+    fn test_play_cue_fades_from_the_current_output() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture()]);
+        let play = |universe: &mut Universe, level: u8, fade_time_ms: u32| {
+            let mut cue = [0u8; 513];
+            cue[11] = level;
+            cue[12] = level;
+            process_command(
+                universe,
+                UniverseCommand::PlayCue {
+                    playback: "main".to_string(),
+                    cue_idx: 0,
+                    cue_data: cue,
+                    fade_time_ms,
+                    intensity_percent: 100,
+                    master: 255,
+                },
+            );
+        };
+
+        play(&mut universe, 200, 10_000);
+        let halfway = universe.levels_at(Instant::now() + Duration::from_secs(5));
+        assert_eq!(halfway[11], 100);
+        // The buffer already holds the target, so queries and `update` see the cue
+        assert_eq!(universe.dmx_buffer[11], 200);
+
+        // A hand write lands at once instead of being faded into
+        universe.set_dmx_address(12, 50).unwrap();
+        assert_eq!(universe.output_buffer()[12], 50);
+
+        // A cue with no fade time snaps and ends the fade
+        play(&mut universe, 80, 0);
+        assert_eq!(universe.output_buffer()[11], 80);
+        universe.settle_fade(Instant::now());
+        assert!(universe.fade.is_none());
+    }
+
+    #[test]
+    fn test_commands_reach_the_universe_they_name() {
+        use crate::universe::sink::SerialSink;
+        use std::sync::mpsc;
+
+        let outputs = [0, 2]
+            .map(|id| UniverseOutput {
+                universe: Universe::new(id),
+                sink: Box::new(SerialSink::open("fake").unwrap()),
+            })
+            .into();
+        let (command_tx, command_rx) = mpsc::channel();
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let config = DmxConfig {
+            shutdown_fade: Duration::ZERO,
+            ..Default::default()
+        };
+        let handle = thread::spawn(move || dmx_thread(outputs, command_rx, shutdown_rx, config));
+
+        let on = |universe, command| UniverseCommand::OnUniverse {
+            universe,
+            command: Box::new(command),
+        };
+        let state = |universe| {
+            let (response_tx, response_rx) = mpsc::channel();
+            command_tx
+                .send(on(universe, UniverseCommand::GetDMXState(response_tx)))
+                .unwrap();
+            response_rx.recv_timeout(Duration::from_secs(1))
+        };
+
+        // Bare commands go to the first universe
+        command_tx
+            .send(UniverseCommand::SetChannel {
+                channel: 1,
+                value: 10,
+            })
+            .unwrap();
+        command_tx
+            .send(on(
+                2,
+                UniverseCommand::SetChannel {
+                    channel: 1,
+                    value: 20,
+                },
+            ))
+            .unwrap();
+        assert_eq!(state(0).unwrap()[1], 10);
+        assert_eq!(state(2).unwrap()[1], 20);
+        // A universe that isn't driven drops the command, and with it the reply
+        assert!(state(1).is_err());
+
+        let (response_tx, response_rx) = mpsc::channel();
+        command_tx
+            .send(UniverseCommand::GetUniverses(response_tx))
+            .unwrap();
+        let ids: Vec<u8> = response_rx
+            .recv_timeout(Duration::from_secs(1))
+            .unwrap()
+            .iter()
+            .map(|status| status.id)
+            .collect();
+        assert_eq!(ids, [0, 2]);
+
+        shutdown_tx.send(()).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_reported_channel_address_is_the_one_written() {
+        use std::sync::mpsc;
+
+        let mut universe = Universe::from_fixtures(0, vec![rgb_fixture()]);
+        universe
+            .set_fixture_values(2, &[(ChannelType::Green, 77)])
+            .unwrap();
+
+        let (response_tx, response_rx) = mpsc::channel();
+        process_command(
+            &mut universe,
+            UniverseCommand::GetChannels {
+                fixture_channel: 2,
+                response: response_tx,
+            },
+        );
+        let channels = response_rx.recv().unwrap().unwrap();
+        let (_, address, _) = channels
+            .iter()
+            .find(|(channel_type, _, _)| channel_type == "Green")
+            .unwrap();
+        assert_eq!(*address, 22);
+
+        let (response_tx, response_rx) = mpsc::channel();
+        process_command(
+            &mut universe,
+            UniverseCommand::GetChannelValue {
+                channel: *address,
+                response: response_tx,
+            },
+        );
+        assert_eq!(response_rx.recv().unwrap(), 77);
+    }
+
+    #[test]
+    fn test_parked_address_survives_cues_blackout_and_hand_sets() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture()]);
+        universe.park(11, 180).unwrap();
+
+        universe.set_dmx_address(11, 20).unwrap();
+        assert_eq!(universe.dmx_buffer[11], 180);
+
+        let mut cue = [0u8; 513];
+        cue[11] = 255;
+        cue[13] = 255;
+        universe.play_cue("main", 0, &cue, 100, 255);
+        assert_eq!(universe.dmx_buffer[11], 180);
+        assert_eq!(universe.dmx_buffer[13], 255);
+
+        universe.blackout().unwrap();
+        universe.blackout_latched = true;
+        assert_eq!(universe.dmx_buffer[13], 0);
+        assert_eq!(universe.output_buffer()[11], 180);
+        assert_eq!(universe.status().parked, [(11, 180)]);
+
+        // Unparking leaves the value in place until something else writes it
+        universe.blackout_latched = false;
+        universe.unpark(11).unwrap();
+        assert_eq!(universe.output_buffer()[11], 180);
+        universe.set_dmx_address(11, 20).unwrap();
+        assert_eq!(universe.dmx_buffer[11], 20);
+        assert!(universe.unpark(11).is_err());
+        assert!(universe.park(0, 10).is_err());
+    }
+
+    #[test]
+    fn test_manual_intensity_merges_htp_with_cues() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+        universe.set_dmx_address(11, 200).unwrap();
+        universe.set_rgb(2, 0, 0, 255).unwrap();
+
+        let mut cue = [0u8; 513];
+        cue[11] = 100;
+        cue[21] = 255;
+        universe.play_cue("main", 0, &cue, 100, 255);
+        assert_eq!(universe.output_buffer()[11], 200);
+        // Color is latest-takes-precedence, so the cue takes it back
+        assert_eq!(universe.dmx_buffer[21..24], [255, 0, 0]);
+
+        // Hand-set intensity can't pull a cue down, only add to it
+        universe.set_dmx_address(11, 20).unwrap();
+        assert_eq!(universe.dmx_buffer[11], 100);
+        universe.set_dmx_address(11, 200).unwrap();
+        universe.clear_manual();
+        assert_eq!(universe.dmx_buffer[11], 100);
+
+        universe.set_dmx_address(11, 200).unwrap();
+        universe.blackout().unwrap();
+        assert_eq!(universe.dmx_buffer[11], 0);
+        cue[11] = 50;
+        universe.play_cue("main", 1, &cue, 100, 255);
+        assert_eq!(universe.dmx_buffer[11], 50);
+    }
+
+    #[test]
+    fn test_16bit_values_split_across_coarse_and_fine() {
+        let mut mover = rgb_fixture();
+        mover.channel = 3;
+        mover.dmx_start = 30;
+        mover.profile = Arc::new(FixtureProfile {
+            fine_channels: HashMap::from([(0, 1)]),
+            ..test_profile(&[
+                (ChannelType::Pan, "Pan"),
+                (ChannelType::PanFine, "Pan fine"),
+                (ChannelType::Tilt, "Tilt"),
+            ])
+        });
+        let mut universe = Universe::from_fixtures(0, vec![mover]);
+
+        universe
+            .set_fixture_value_16bit(3, ChannelType::Pan, 0x1234)
+            .unwrap();
+        assert_eq!(universe.dmx_buffer[31..33], [0x12, 0x34]);
+
+        // Without a fine channel only the high byte goes out
+        universe
+            .set_fixture_value_16bit(3, ChannelType::Tilt, 0xABCD)
+            .unwrap();
+        assert_eq!(universe.dmx_buffer[33], 0xAB);
+        assert_eq!(universe.dmx_buffer[34], 0);
+
+        assert!(universe
+            .set_fixture_value_16bit(3, ChannelType::Zoom, 1)
+            .is_err());
+    }
+
+    #[test]
+    fn test_16bit_color_respects_the_color_limit() {
+        let mut wash = rgb_fixture();
+        wash.channel = 3;
+        wash.dmx_start = 30;
+        wash.profile = Arc::new(FixtureProfile {
+            fine_channels: HashMap::from([(0, 1)]),
+            ..test_profile(&[
+                (ChannelType::Red, "Red"),
+                (ChannelType::Custom("Red fine".to_string()), "Red fine"),
+            ])
+        });
+        wash.color_limits.insert(ChannelType::Red, 200);
+        let mut universe = Universe::from_fixtures(0, vec![wash]);
+
+        universe
+            .set_fixture_value_16bit(3, ChannelType::Red, 0xFFFF)
+            .unwrap();
+        assert_eq!(universe.dmx_buffer[31..33], [200, 0]);
+        assert_eq!(universe.output_buffer()[31..33], [200, 0]);
+
+        // Under the limit the value goes out whole
+        universe
+            .set_fixture_value_16bit(3, ChannelType::Red, 0x6480)
+            .unwrap();
+        assert_eq!(universe.dmx_buffer[31..33], [0x64, 0x80]);
+    }
+
+    #[test]
+    fn test_color_temperature_by_rgb_mix_or_cct_channel() {
+        let mut cct = rgb_fixture();
+        cct.channel = 4;
+        cct.dmx_start = 40;
+        let range: OflCapability = serde_json::from_str(
+            r#"{"type": "ColorTemperature", "colorTemperatureStart": "6500K", "colorTemperatureEnd": "2700K"}"#,
+        )
+        .unwrap();
+        cct.profile = Arc::new(FixtureProfile {
+            capabilities: HashMap::from([(0, vec![range])]),
+            fine_channels: HashMap::from([(0, 1)]),
+            ..test_profile(&[
+                (ChannelType::ColorTemperature, "CCT"),
+                (ChannelType::Custom("CCT fine".to_string()), "CCT fine"),
+            ])
+        });
+        let mut universe =
+            Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture(), cct]);
+
+        universe.set_color_temperature(2, 6600).unwrap();
+        assert_eq!(universe.dmx_buffer[21..24], [255, 255, 255]);
+        universe.set_color_temperature(2, 2700).unwrap();
+        assert_eq!(universe.dmx_buffer[21..24], [255, 167, 87]);
+        // Below the supported range it clamps rather than going dark
+        universe.set_color_temperature(2, 500).unwrap();
+        assert_eq!(universe.dmx_buffer[21..24], [255, 68, 0]);
+
+        universe.set_color_temperature(4, 3650).unwrap();
+        assert_eq!(universe.dmx_buffer[41..43], [191, 255]);
+        // Cooler than the fixture goes lands on its coolest end
+        universe.set_color_temperature(4, 10000).unwrap();
+        assert_eq!(universe.dmx_buffer[41..43], [0, 0]);
+
+        assert!(universe.set_color_temperature(1, 3200).is_err());
+    }
+
+    #[test]
+    fn test_position_splits_only_axes_with_a_fine_channel() {
+        let mut mover = rgb_fixture();
+        mover.channel = 3;
+        mover.dmx_start = 30;
+        mover.profile = Arc::new(FixtureProfile {
+            fine_channels: HashMap::from([(0, 1)]),
+            ..test_profile(&[
+                (ChannelType::Pan, "Pan"),
+                (ChannelType::PanFine, "Pan fine"),
+                (ChannelType::Tilt, "Tilt"),
+            ])
+        });
+        let mut universe = Universe::from_fixtures(0, vec![rgb_fixture(), mover]);
+
+        universe.set_position(3, 0x1234, 200).unwrap();
+        assert_eq!(universe.dmx_buffer[31..34], [0x12, 0x34, 200]);
+
+        // Tilt has no fine channel, so a 16-bit tilt is refused and pan is left alone
+        assert!(universe.set_position(3, 0xFFFF, 300).is_err());
+        assert_eq!(universe.dmx_buffer[31..34], [0x12, 0x34, 200]);
+
+        let error = universe.set_position(2, 0, 0).unwrap_err().to_string();
+        assert!(error.contains("no Pan channel"));
+    }
+
+    #[test]
+    fn test_gobo_slots_select_the_middle_of_their_range() {
+        let wheel = |slots: &str| -> Vec<OflCapability> { serde_json::from_str(slots).unwrap() };
+        let mut spot = rgb_fixture();
+        spot.channel = 6;
+        spot.dmx_start = 60;
+        let slot = ChannelType::Custom("WheelSlot".to_string());
+        spot.profile = Arc::new(FixtureProfile {
+            capabilities: HashMap::from([
+                (
+                    0,
+                    wheel(
+                        r#"[{"dmxRange": [0, 127], "type": "WheelSlot"}, {"dmxRange": [128, 255], "type": "WheelSlot"}]"#,
+                    ),
+                ),
+                (
+                    1,
+                    wheel(
+                        r#"[{"dmxRange": [0, 9], "type": "WheelSlot", "comment": "Open"},
+                            {"dmxRange": [10, 19], "type": "WheelSlot", "comment": "Gobo 1"},
+                            {"dmxRange": [20, 29], "type": "WheelSlot", "comment": "Gobo 2"},
+                            {"dmxRange": [30, 255], "type": "WheelShake"}]"#,
+                    ),
+                ),
+            ]),
+            ..test_profile(&[(slot.clone(), "Color Wheel"), (slot, "Gobo Wheel")])
+        });
+        let mut universe = Universe::from_fixtures(0, vec![rgb_fixture(), spot]);
+
+        // The color wheel lists slots too but isn't the gobo wheel
+        universe.select_gobo(6, 3).unwrap();
+        assert_eq!(universe.dmx_buffer[61..63], [0, 24]);
+        universe.select_gobo(6, 1).unwrap();
+        assert_eq!(universe.dmx_buffer[62], 4);
+
+        // Shake ranges aren't slots
+        let error = universe.select_gobo(6, 4).unwrap_err().to_string();
+        assert!(error.contains("slots 1-3"));
+        assert!(universe.select_gobo(6, 0).is_err());
+        assert!(universe.select_gobo(2, 1).is_err());
+    }
+
+    #[test]
+    fn test_strobe_errors_for_fixtures_without_one() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture()]);
+
+        universe.set_strobe(1, 200).unwrap();
+        assert_eq!(universe.dmx_buffer[12], 200);
+
+        let error = universe.set_strobe(2, 200).unwrap_err().to_string();
+        assert!(error.contains("no strobe channel"));
+    }
+
+    #[test]
+    fn test_hsv_prefers_native_hue_and_saturation() {
+        let mut native = rgb_fixture();
+        native.channel = 5;
+        native.dmx_start = 50;
+        native.profile = Arc::new(test_profile(&[
+            (ChannelType::Hue, "Hue"),
+            (ChannelType::Saturation, "Saturation"),
+            (ChannelType::Dimmer, "Dimmer"),
+        ]));
+        let mut universe =
+            Universe::from_fixtures(0, vec![dual_dimmer_fixture(), rgb_fixture(), native]);
+
+        universe.set_hsv(2, 120.0, 1.0, 1.0).unwrap();
+        assert_eq!(universe.dmx_buffer[21..24], [0, 255, 0]);
+        // Hue wraps, so -120 is blue
+        universe.set_hsv(2, -120.0, 1.0, 0.5).unwrap();
+        assert_eq!(universe.dmx_buffer[21..24], [0, 0, 128]);
+        // Saturation past 1 clamps rather than overshooting
+        universe.set_hsv(2, 30.0, 2.0, 1.0).unwrap();
+        assert_eq!(universe.dmx_buffer[21..24], [255, 128, 0]);
+
+        universe.set_hsv(5, 540.0, 0.5, 1.0).unwrap();
+        assert_eq!(universe.dmx_buffer[51..54], [128, 128, 255]);
+
+        assert!(universe.set_hsv(1, 0.0, 1.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_dimmer_curve_shapes_the_output_after_fading() {
+        let mut universe = Universe::from_fixtures(0, vec![dual_dimmer_fixture()]);
+        universe
+            .set_dimmer_curve(1, DimmerCurve::SquareLaw)
+            .unwrap();
+        universe
+            .set_fixture_values(1, &[(ChannelType::Dimmer, 128), (ChannelType::Strobe, 128)])
+            .unwrap();
+        assert_eq!(universe.dmx_buffer[11], 128);
+        assert_eq!(universe.output_buffer()[11..13], [64, 128]);
+
+        // Halfway through a fade the linear level is curved, not the endpoints
+        let mut cue = [0u8; 513];
+        cue[11] = 255;
+        universe.play_cue("main", 0, &cue, 100, 255);
+        universe.start_fade([0; 513], Duration::from_secs(10));
+        let halfway = universe.levels_at(Instant::now() + Duration::from_secs(5));
+        let output = universe.render(halfway);
+        assert_eq!(output[11], DimmerCurve::SquareLaw.apply(halfway[11]));
+        assert!(output[11] < halfway[11]);
+
+        for curve in [
+            DimmerCurve::Linear,
+            DimmerCurve::SquareLaw,
+            DimmerCurve::InvSquareLaw,
+            DimmerCurve::SCurve,
+        ] {
+            assert_eq!(curve.apply(0), 0);
+            assert_eq!(curve.apply(255), 255);
+        }
+        assert_eq!(DimmerCurve::InvSquareLaw.apply(64), 128);
+        assert_eq!(DimmerCurve::SCurve.apply(128), 128);
+        assert!(universe.set_dimmer_curve(9, DimmerCurve::SCurve).is_err());
+    }
+
+    #[test]
+    fn test_dimmer_curve_covers_the_fine_byte() {
+        let mut dimmer = rgb_fixture();
+        dimmer.channel = 3;
+        dimmer.dmx_start = 30;
+        dimmer.curve = DimmerCurve::SquareLaw;
+        dimmer.profile = Arc::new(FixtureProfile {
+            fine_channels: HashMap::from([(0, 1)]),
+            ..test_profile(&[
+                (ChannelType::Dimmer, "Dimmer"),
+                (ChannelType::Custom("Fine".to_string()), "Dimmer fine"),
+            ])
+        });
+        let mut universe = Universe::from_fixtures(0, vec![dimmer]);
+        let mut output_at = |level| {
+            universe
+                .set_fixture_value_16bit(3, ChannelType::Dimmer, level)
+                .unwrap();
+            let output = universe.output_buffer();
+            u16::from_be_bytes([output[31], output[32]])
+        };
+
+        // Crossing a coarse step never drops the output
+        assert!(output_at(0x0200) >= output_at(0x01FF));
+        assert_eq!(output_at(0x8000), 0x4000);
+        assert_eq!(output_at(0xFFFF), 0xFFFF);
     }
 }
@@ -0,0 +1,895 @@
+use std::{
+    fs,
+    path::Path,
+    sync::mpsc::{self, Sender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fixture::{
+        patch::{ChannelType, DimmerCurve},
+        registry::FixtureRegistry,
+    },
+    universe::{cue::MAIN_PLAYBACK, show::ShowFixture, Neutral, UniverseCommand},
+};
+
+/// The replayable subset of `UniverseCommand`. Queries carry response channels
+/// and change nothing, so they are never recorded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionCommand {
+    SetChannel {
+        channel: usize,
+        value: u8,
+    },
+    SetMultiple {
+        changes: Vec<(usize, u8)>,
+    },
+    PlayCue {
+        // Sessions recorded before named playbacks all ran on the main one
+        #[serde(default = "main_playback")]
+        playback: String,
+        cue_idx: usize,
+        cue_data: Vec<u8>,
+        fade_time_ms: u32,
+        // Sessions recorded before cues could be scaled played at full level
+        #[serde(default = "full_intensity")]
+        intensity_percent: u8,
+        // Sessions recorded before cue masters played every cue at full
+        #[serde(default = "full_master")]
+        master: u8,
+    },
+    SetCueLevel {
+        playback: String,
+        cue_idx: usize,
+        level: u8,
+    },
+    SetFixture {
+        fixture_channel: usize,
+        intensity: Option<u8>,
+        color: Option<(u8, u8, u8)>,
+    },
+    SetFixtureRaw {
+        fixture_channel: usize,
+        values: Vec<u8>,
+    },
+    SetChannelByName {
+        fixture_channel: usize,
+        name: String,
+        value: u8,
+    },
+    SetFixtureIndexed {
+        fixture_channel: usize,
+        ctype: ChannelType,
+        instance: usize,
+        value: u8,
+    },
+    SetFixture16Bit {
+        fixture_channel: usize,
+        ctype: ChannelType,
+        value: u16,
+    },
+    SetColorTemperature {
+        fixture_channel: usize,
+        kelvin: u16,
+    },
+    SetPosition {
+        fixture_channel: usize,
+        pan: u16,
+        tilt: u16,
+    },
+    SelectGobo {
+        fixture_channel: usize,
+        slot: usize,
+    },
+    SetStrobe {
+        fixture_channel: usize,
+        rate: u8,
+    },
+    SetHsv {
+        fixture_channel: usize,
+        hue: f32,
+        saturation: f32,
+        value: f32,
+    },
+    SetAllFixtureValue {
+        ctype: ChannelType,
+        value: u8,
+    },
+    SetCategoryValues {
+        category: String,
+        values: Vec<(ChannelType, u8)>,
+    },
+    SetColorLimit {
+        fixture_channel: usize,
+        ctype: ChannelType,
+        limit: Option<u8>,
+    },
+    SetDimmerCurve {
+        fixture_channel: usize,
+        curve: DimmerCurve,
+    },
+    Neutralize {
+        target: Neutral,
+    },
+    SetHome {
+        fixture_channel: Option<usize>,
+        with_color: bool,
+    },
+    SetOutputEnabled {
+        universe: u8,
+        enabled: bool,
+    },
+    SetPriority {
+        universe: Option<u8>,
+        priority: u8,
+    },
+    AddSolo {
+        fixture_channel: usize,
+    },
+    ClearSolo,
+    SetMuted {
+        fixture_channel: usize,
+        muted: bool,
+    },
+    SetMirror {
+        fixture_channel: usize,
+        source: Option<usize>,
+    },
+    Blackout,
+    ClearManual,
+    SetBlackoutLatch {
+        latched: bool,
+    },
+    Park {
+        address: usize,
+        value: u8,
+    },
+    Unpark {
+        address: usize,
+    },
+    // Saved by library key, like a show, and rebuilt from the registry on replay
+    SetPatch {
+        fixtures: Vec<ShowFixture>,
+    },
+    OnUniverse {
+        universe: u8,
+        command: Box<SessionCommand>,
+    },
+}
+
+impl SessionCommand {
+    /// Convert a live command, returning None for queries and other commands
+    /// that change nothing a replay should reproduce
+    pub fn from_command(command: &UniverseCommand) -> Option<Self> {
+        match command {
+            UniverseCommand::SetChannel { channel, value } => Some(SessionCommand::SetChannel {
+                channel: *channel,
+                value: *value,
+            }),
+            UniverseCommand::SetMultiple { changes } => Some(SessionCommand::SetMultiple {
+                changes: changes.clone(),
+            }),
+            UniverseCommand::PlayCue {
+                playback,
+                cue_idx,
+                cue_data,
+                fade_time_ms,
+                intensity_percent,
+                master,
+            } => Some(SessionCommand::PlayCue {
+                playback: playback.clone(),
+                cue_idx: *cue_idx,
+                cue_data: cue_data.to_vec(),
+                fade_time_ms: *fade_time_ms,
+                intensity_percent: *intensity_percent,
+                master: *master,
+            }),
+            UniverseCommand::SetCueLevel {
+                playback,
+                cue_idx,
+                level,
+            } => Some(SessionCommand::SetCueLevel {
+                playback: playback.clone(),
+                cue_idx: *cue_idx,
+                level: *level,
+            }),
+            UniverseCommand::SetFixture {
+                fixture_channel,
+                intensity,
+                color,
+            } => Some(SessionCommand::SetFixture {
+                fixture_channel: *fixture_channel,
+                intensity: *intensity,
+                color: *color,
+            }),
+            UniverseCommand::SetFixtureRaw {
+                fixture_channel,
+                values,
+            } => Some(SessionCommand::SetFixtureRaw {
+                fixture_channel: *fixture_channel,
+                values: values.clone(),
+            }),
+            UniverseCommand::SetChannelByName {
+                fixture_channel,
+                name,
+                value,
+            } => Some(SessionCommand::SetChannelByName {
+                fixture_channel: *fixture_channel,
+                name: name.clone(),
+                value: *value,
+            }),
+            UniverseCommand::SetFixtureIndexed {
+                fixture_channel,
+                ctype,
+                instance,
+                value,
+            } => Some(SessionCommand::SetFixtureIndexed {
+                fixture_channel: *fixture_channel,
+                ctype: ctype.clone(),
+                instance: *instance,
+                value: *value,
+            }),
+            UniverseCommand::SetFixture16Bit {
+                fixture_channel,
+                ctype,
+                value,
+            } => Some(SessionCommand::SetFixture16Bit {
+                fixture_channel: *fixture_channel,
+                ctype: ctype.clone(),
+                value: *value,
+            }),
+            UniverseCommand::SetColorTemperature {
+                fixture_channel,
+                kelvin,
+            } => Some(SessionCommand::SetColorTemperature {
+                fixture_channel: *fixture_channel,
+                kelvin: *kelvin,
+            }),
+            UniverseCommand::SetPosition {
+                fixture_channel,
+                pan,
+                tilt,
+            } => Some(SessionCommand::SetPosition {
+                fixture_channel: *fixture_channel,
+                pan: *pan,
+                tilt: *tilt,
+            }),
+            UniverseCommand::SelectGobo {
+                fixture_channel,
+                slot,
+            } => Some(SessionCommand::SelectGobo {
+                fixture_channel: *fixture_channel,
+                slot: *slot,
+            }),
+            UniverseCommand::SetStrobe {
+                fixture_channel,
+                rate,
+            } => Some(SessionCommand::SetStrobe {
+                fixture_channel: *fixture_channel,
+                rate: *rate,
+            }),
+            UniverseCommand::SetHsv {
+                fixture_channel,
+                hue,
+                saturation,
+                value,
+            } => Some(SessionCommand::SetHsv {
+                fixture_channel: *fixture_channel,
+                hue: *hue,
+                saturation: *saturation,
+                value: *value,
+            }),
+            UniverseCommand::SetAllFixtureValue { ctype, value } => {
+                Some(SessionCommand::SetAllFixtureValue {
+                    ctype: ctype.clone(),
+                    value: *value,
+                })
+            }
+            UniverseCommand::SetCategoryValues { category, values } => {
+                Some(SessionCommand::SetCategoryValues {
+                    category: category.clone(),
+                    values: values.clone(),
+                })
+            }
+            UniverseCommand::SetColorLimit {
+                fixture_channel,
+                ctype,
+                limit,
+            } => Some(SessionCommand::SetColorLimit {
+                fixture_channel: *fixture_channel,
+                ctype: ctype.clone(),
+                limit: *limit,
+            }),
+            UniverseCommand::SetDimmerCurve {
+                fixture_channel,
+                curve,
+            } => Some(SessionCommand::SetDimmerCurve {
+                fixture_channel: *fixture_channel,
+                curve: *curve,
+            }),
+            UniverseCommand::Neutralize { target, .. } => {
+                Some(SessionCommand::Neutralize { target: *target })
+            }
+            UniverseCommand::SetHome {
+                fixture_channel,
+                with_color,
+                ..
+            } => Some(SessionCommand::SetHome {
+                fixture_channel: *fixture_channel,
+                with_color: *with_color,
+            }),
+            UniverseCommand::SetOutputEnabled { universe, enabled } => {
+                Some(SessionCommand::SetOutputEnabled {
+                    universe: *universe,
+                    enabled: *enabled,
+                })
+            }
+            UniverseCommand::SetPriority {
+                universe, priority, ..
+            } => Some(SessionCommand::SetPriority {
+                universe: *universe,
+                priority: *priority,
+            }),
+            UniverseCommand::AddSolo(fixture_channel) => Some(SessionCommand::AddSolo {
+                fixture_channel: *fixture_channel,
+            }),
+            UniverseCommand::ClearSolo => Some(SessionCommand::ClearSolo),
+            UniverseCommand::SetMuted {
+                fixture_channel,
+                muted,
+            } => Some(SessionCommand::SetMuted {
+                fixture_channel: *fixture_channel,
+                muted: *muted,
+            }),
+            UniverseCommand::SetMirror {
+                fixture_channel,
+                source,
+            } => Some(SessionCommand::SetMirror {
+                fixture_channel: *fixture_channel,
+                source: *source,
+            }),
+            UniverseCommand::Blackout => Some(SessionCommand::Blackout),
+            UniverseCommand::ClearManual => Some(SessionCommand::ClearManual),
+            UniverseCommand::SetBlackoutLatch(latched) => {
+                Some(SessionCommand::SetBlackoutLatch { latched: *latched })
+            }
+            UniverseCommand::Park { address, value } => Some(SessionCommand::Park {
+                address: *address,
+                value: *value,
+            }),
+            UniverseCommand::Unpark { address } => {
+                Some(SessionCommand::Unpark { address: *address })
+            }
+            // Every fixture patched from the library has a manufacturer in its id
+            UniverseCommand::SetPatch(fixtures) => Some(SessionCommand::SetPatch {
+                fixtures: fixtures
+                    .iter()
+                    .map(ShowFixture::from_patched)
+                    .collect::<Result<_>>()
+                    .ok()?,
+            }),
+            UniverseCommand::OnUniverse { universe, command } => Some(SessionCommand::OnUniverse {
+                universe: *universe,
+                command: Box::new(Self::from_command(command)?),
+            }),
+            // Queries, output timing and the recorder's own controls change no
+            // show state. Listed by name so a new command must pick a side.
+            UniverseCommand::GetChannelValue { .. }
+            | UniverseCommand::GetChannels { .. }
+            | UniverseCommand::GetDMXState(_)
+            | UniverseCommand::GetPlaybackOutput { .. }
+            | UniverseCommand::GetTouched(_)
+            | UniverseCommand::GetFreeAddresses { .. }
+            | UniverseCommand::GetFixtures(_)
+            | UniverseCommand::GetPatch(_)
+            | UniverseCommand::GetFixtureCapabilities { .. }
+            | UniverseCommand::GetCalibration { .. }
+            | UniverseCommand::GetStats(_)
+            | UniverseCommand::SetFrameRate { .. }
+            | UniverseCommand::GetPinnedChannels { .. }
+            | UniverseCommand::StartSessionRecording
+            | UniverseCommand::StopSessionRecording(_)
+            | UniverseCommand::GetUniverses(_) => None,
+        }
+    }
+
+    pub fn into_command(self, registry: &mut FixtureRegistry) -> Result<UniverseCommand> {
+        Ok(match self {
+            SessionCommand::SetChannel { channel, value } => {
+                UniverseCommand::SetChannel { channel, value }
+            }
+            SessionCommand::SetMultiple { changes } => UniverseCommand::SetMultiple { changes },
+            SessionCommand::PlayCue {
+                playback,
+                cue_idx,
+                cue_data,
+                fade_time_ms,
+                intensity_percent,
+                master,
+            } => UniverseCommand::PlayCue {
+                playback,
+                cue_idx,
+                cue_data: cue_data
+                    .try_into()
+                    .map_err(|_| anyhow!("Recorded cue {} is not a 513-byte buffer", cue_idx))?,
+                fade_time_ms,
+                intensity_percent,
+                master,
+            },
+            SessionCommand::SetCueLevel {
+                playback,
+                cue_idx,
+                level,
+            } => UniverseCommand::SetCueLevel {
+                playback,
+                cue_idx,
+                level,
+            },
+            SessionCommand::SetFixture {
+                fixture_channel,
+                intensity,
+                color,
+            } => UniverseCommand::SetFixture {
+                fixture_channel,
+                intensity,
+                color,
+            },
+            SessionCommand::SetFixtureRaw {
+                fixture_channel,
+                values,
+            } => UniverseCommand::SetFixtureRaw {
+                fixture_channel,
+                values,
+            },
+            SessionCommand::SetChannelByName {
+                fixture_channel,
+                name,
+                value,
+            } => UniverseCommand::SetChannelByName {
+                fixture_channel,
+                name,
+                value,
+            },
+            SessionCommand::SetFixtureIndexed {
+                fixture_channel,
+                ctype,
+                instance,
+                value,
+            } => UniverseCommand::SetFixtureIndexed {
+                fixture_channel,
+                ctype,
+                instance,
+                value,
+            },
+            SessionCommand::SetFixture16Bit {
+                fixture_channel,
+                ctype,
+                value,
+            } => UniverseCommand::SetFixture16Bit {
+                fixture_channel,
+                ctype,
+                value,
+            },
+            SessionCommand::SetColorTemperature {
+                fixture_channel,
+                kelvin,
+            } => UniverseCommand::SetColorTemperature {
+                fixture_channel,
+                kelvin,
+            },
+            SessionCommand::SetPosition {
+                fixture_channel,
+                pan,
+                tilt,
+            } => UniverseCommand::SetPosition {
+                fixture_channel,
+                pan,
+                tilt,
+            },
+            SessionCommand::SelectGobo {
+                fixture_channel,
+                slot,
+            } => UniverseCommand::SelectGobo {
+                fixture_channel,
+                slot,
+            },
+            SessionCommand::SetStrobe {
+                fixture_channel,
+                rate,
+            } => UniverseCommand::SetStrobe {
+                fixture_channel,
+                rate,
+            },
+            SessionCommand::SetHsv {
+                fixture_channel,
+                hue,
+                saturation,
+                value,
+            } => UniverseCommand::SetHsv {
+                fixture_channel,
+                hue,
+                saturation,
+                value,
+            },
+            SessionCommand::SetAllFixtureValue { ctype, value } => {
+                UniverseCommand::SetAllFixtureValue { ctype, value }
+            }
+            SessionCommand::SetCategoryValues { category, values } => {
+                UniverseCommand::SetCategoryValues { category, values }
+            }
+            SessionCommand::SetColorLimit {
+                fixture_channel,
+                ctype,
+                limit,
+            } => UniverseCommand::SetColorLimit {
+                fixture_channel,
+                ctype,
+                limit,
+            },
+            SessionCommand::SetDimmerCurve {
+                fixture_channel,
+                curve,
+            } => UniverseCommand::SetDimmerCurve {
+                fixture_channel,
+                curve,
+            },
+            // Nobody is waiting on a replayed count, so the replies go nowhere
+            SessionCommand::Neutralize { target } => UniverseCommand::Neutralize {
+                target,
+                response: mpsc::channel().0,
+            },
+            SessionCommand::SetHome {
+                fixture_channel,
+                with_color,
+            } => UniverseCommand::SetHome {
+                fixture_channel,
+                with_color,
+                response: mpsc::channel().0,
+            },
+            SessionCommand::SetOutputEnabled { universe, enabled } => {
+                UniverseCommand::SetOutputEnabled { universe, enabled }
+            }
+            SessionCommand::SetPriority { universe, priority } => UniverseCommand::SetPriority {
+                universe,
+                priority,
+                response: mpsc::channel().0,
+            },
+            SessionCommand::AddSolo { fixture_channel } => {
+                UniverseCommand::AddSolo(fixture_channel)
+            }
+            SessionCommand::ClearSolo => UniverseCommand::ClearSolo,
+            SessionCommand::SetMuted {
+                fixture_channel,
+                muted,
+            } => UniverseCommand::SetMuted {
+                fixture_channel,
+                muted,
+            },
+            SessionCommand::SetMirror {
+                fixture_channel,
+                source,
+            } => UniverseCommand::SetMirror {
+                fixture_channel,
+                source,
+            },
+            SessionCommand::Blackout => UniverseCommand::Blackout,
+            SessionCommand::ClearManual => UniverseCommand::ClearManual,
+            SessionCommand::SetBlackoutLatch { latched } => {
+                UniverseCommand::SetBlackoutLatch(latched)
+            }
+            SessionCommand::Park { address, value } => UniverseCommand::Park { address, value },
+            SessionCommand::Unpark { address } => UniverseCommand::Unpark { address },
+            SessionCommand::SetPatch { fixtures } => UniverseCommand::SetPatch(
+                fixtures
+                    .into_iter()
+                    .map(|fixture| fixture.into_patched(registry))
+                    .collect::<Result<_>>()?,
+            ),
+            SessionCommand::OnUniverse { universe, command } => UniverseCommand::OnUniverse {
+                universe,
+                command: Box::new(command.into_command(registry)?),
+            },
+        })
+    }
+}
+
+fn full_intensity() -> u8 {
+    100
+}
+
+fn full_master() -> u8 {
+    255
+}
+
+fn main_playback() -> String {
+    MAIN_PLAYBACK.to_string()
+}
+
+/// One recorded command and when it happened, relative to the start of recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEvent {
+    pub offset_ms: u64,
+    pub command: SessionCommand,
+}
+
+/// Captures every command the DMX thread processes while recording
+pub struct SessionRecorder {
+    started: Instant,
+    events: Vec<SessionEvent>,
+}
+
+impl SessionRecorder {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, command: &UniverseCommand) {
+        if let Some(command) = SessionCommand::from_command(command) {
+            self.events.push(SessionEvent {
+                offset_ms: self.started.elapsed().as_millis() as u64,
+                command,
+            });
+        }
+    }
+
+    pub fn finish(self) -> Vec<SessionEvent> {
+        self.events
+    }
+}
+
+impl Default for SessionRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn save_session<P: AsRef<Path>>(path: P, events: &[SessionEvent]) -> Result<()> {
+    let content = serde_json::to_string_pretty(events)?;
+    fs::write(&path, content)
+        .with_context(|| format!("Failed to write session to {}", path.as_ref().display()))
+}
+
+pub fn load_session<P: AsRef<Path>>(path: P) -> Result<Vec<SessionEvent>> {
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read session from {}", path.as_ref().display()))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Replays recorded events against the command channel at their original timing
+pub struct SessionPlayer;
+
+impl SessionPlayer {
+    /// Start playback on a background thread. Commands are rebuilt first, so
+    /// a patch whose fixtures have left the library fails before anything is sent.
+    pub fn play(
+        events: Vec<SessionEvent>,
+        registry: &mut FixtureRegistry,
+        command_tx: Sender<UniverseCommand>,
+    ) -> Result<JoinHandle<Result<()>>> {
+        let commands = events
+            .into_iter()
+            .map(|event| Ok((event.offset_ms, event.command.into_command(registry)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(thread::spawn(move || {
+            let started = Instant::now();
+
+            for (offset_ms, command) in commands {
+                let due = started + Duration::from_millis(offset_ms);
+                if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                    thread::sleep(wait);
+                }
+
+                command_tx
+                    .send(command)
+                    .with_context(|| "Failed to send session command")?;
+            }
+
+            Ok(())
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_queries_are_not_recorded() {
+        let mut recorder = SessionRecorder::new();
+        let (response_tx, _response_rx) = mpsc::channel();
+
+        recorder.record(&UniverseCommand::SetChannel {
+            channel: 1,
+            value: 255,
+        });
+        recorder.record(&UniverseCommand::GetDMXState(response_tx));
+        recorder.record(&UniverseCommand::Blackout);
+
+        let events = recorder.finish();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[1].command, SessionCommand::Blackout));
+    }
+
+    /// Record `commands`, save and reload them as JSON, and convert them back
+    fn round_trip(commands: &[UniverseCommand]) -> Vec<UniverseCommand> {
+        let mut recorder = SessionRecorder::new();
+        for command in commands {
+            recorder.record(command);
+        }
+
+        let json = serde_json::to_string(&recorder.finish()).unwrap();
+        let events: Vec<SessionEvent> = serde_json::from_str(&json).unwrap();
+        events
+            .into_iter()
+            .map(|event| {
+                event
+                    .command
+                    .into_command(&mut FixtureRegistry::empty())
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fixture_wide_commands_round_trip() {
+        let replayed = round_trip(&[
+            UniverseCommand::SetAllFixtureValue {
+                ctype: ChannelType::Intensity,
+                value: 128,
+            },
+            UniverseCommand::SetCategoryValues {
+                category: "Moving Head".to_string(),
+                values: vec![(ChannelType::Pan, 64)],
+            },
+            UniverseCommand::Neutralize {
+                target: Neutral::Color { white: false },
+                response: mpsc::channel().0,
+            },
+        ]);
+
+        assert!(matches!(
+            replayed[0],
+            UniverseCommand::SetAllFixtureValue {
+                ctype: ChannelType::Intensity,
+                value: 128
+            }
+        ));
+        assert!(matches!(
+            &replayed[1],
+            UniverseCommand::SetCategoryValues { category, values }
+                if category == "Moving Head" && values == &[(ChannelType::Pan, 64)]
+        ));
+        assert!(matches!(
+            replayed[2],
+            UniverseCommand::Neutralize {
+                target: Neutral::Color { white: false },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_fixture_settings_round_trip() {
+        let replayed = round_trip(&[
+            UniverseCommand::SetColorLimit {
+                fixture_channel: 2,
+                ctype: ChannelType::Blue,
+                limit: Some(180),
+            },
+            UniverseCommand::SetDimmerCurve {
+                fixture_channel: 2,
+                curve: DimmerCurve::SquareLaw,
+            },
+            UniverseCommand::SetHome {
+                fixture_channel: Some(3),
+                with_color: true,
+                response: mpsc::channel().0,
+            },
+            UniverseCommand::SetPatch(Vec::new()),
+        ]);
+
+        assert!(matches!(
+            replayed[0],
+            UniverseCommand::SetColorLimit {
+                fixture_channel: 2,
+                ctype: ChannelType::Blue,
+                limit: Some(180)
+            }
+        ));
+        assert!(matches!(
+            replayed[1],
+            UniverseCommand::SetDimmerCurve {
+                fixture_channel: 2,
+                curve: DimmerCurve::SquareLaw
+            }
+        ));
+        assert!(matches!(
+            replayed[2],
+            UniverseCommand::SetHome {
+                fixture_channel: Some(3),
+                with_color: true,
+                ..
+            }
+        ));
+        assert!(matches!(&replayed[3], UniverseCommand::SetPatch(fixtures) if fixtures.is_empty()));
+    }
+
+    #[test]
+    fn test_replay_sends_commands_in_order() {
+        let events = vec![
+            SessionEvent {
+                offset_ms: 0,
+                command: SessionCommand::SetChannel {
+                    channel: 1,
+                    value: 10,
+                },
+            },
+            SessionEvent {
+                offset_ms: 20,
+                command: SessionCommand::Blackout,
+            },
+        ];
+        let json = serde_json::to_string(&events).unwrap();
+        let events: Vec<SessionEvent> = serde_json::from_str(&json).unwrap();
+
+        let (command_tx, command_rx) = mpsc::channel();
+        let started = Instant::now();
+        SessionPlayer::play(events, &mut FixtureRegistry::empty(), command_tx)
+            .unwrap()
+            .join()
+            .unwrap()
+            .unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        let replayed: Vec<UniverseCommand> = command_rx.try_iter().collect();
+        assert!(matches!(
+            replayed[0],
+            UniverseCommand::SetChannel {
+                channel: 1,
+                value: 10
+            }
+        ));
+        assert!(matches!(replayed[1], UniverseCommand::Blackout));
+    }
+
+    #[test]
+    fn test_patch_missing_from_the_library_fails_before_replay() {
+        let events = vec![
+            SessionEvent {
+                offset_ms: 0,
+                command: SessionCommand::Blackout,
+            },
+            SessionEvent {
+                offset_ms: 10,
+                command: SessionCommand::SetPatch {
+                    fixtures: vec![ShowFixture {
+                        manufacturer: "generic".to_string(),
+                        fixture: "rgb-fader".to_string(),
+                        mode: "3-channel".to_string(),
+                        channel: 1,
+                        dmx_start: 0,
+                        label: "Wash".to_string(),
+                        muted: false,
+                        color_limits: Vec::new(),
+                        home: Default::default(),
+                        curve: DimmerCurve::Linear,
+                    }],
+                },
+            },
+        ];
+
+        let (command_tx, command_rx) = mpsc::channel();
+        assert!(SessionPlayer::play(events, &mut FixtureRegistry::empty(), command_tx).is_err());
+        assert!(command_rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,406 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fixture::{
+        patch::{ChannelType, DimmerCurve, PatchedFixture},
+        registry::FixtureRegistry,
+    },
+    universe::cue::{Cue, CueEngine},
+};
+
+/// One patch entry as saved. The profile is stored by its library key and
+/// rebuilt from the fixture registry on load, so a show picks up fixes to
+/// the library.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShowFixture {
+    pub manufacturer: String,
+    pub fixture: String,
+    pub mode: String,
+    pub channel: usize,
+    pub dmx_start: u16,
+    pub label: String,
+    pub muted: bool,
+    pub color_limits: Vec<(ChannelType, u8)>,
+    pub home: BTreeMap<u8, u8>,
+    // Shows saved before dimmer curves were all linear
+    #[serde(default)]
+    pub curve: DimmerCurve,
+}
+
+impl ShowFixture {
+    pub fn from_patched(fixture: &PatchedFixture) -> Result<Self> {
+        let (manufacturer, name) = fixture.id.split_once('/').ok_or_else(|| {
+            anyhow!(
+                "Fixture {} has no manufacturer in its id '{}'",
+                fixture.channel,
+                fixture.id
+            )
+        })?;
+
+        Ok(ShowFixture {
+            manufacturer: manufacturer.to_string(),
+            fixture: name.to_string(),
+            mode: fixture.mode.clone(),
+            channel: fixture.channel,
+            dmx_start: fixture.dmx_start,
+            label: fixture.label.clone(),
+            muted: fixture.muted,
+            color_limits: fixture
+                .color_limits
+                .iter()
+                .map(|(color, limit)| (color.clone(), *limit))
+                .collect(),
+            home: fixture.home.iter().map(|(k, v)| (*k, *v)).collect(),
+            curve: fixture.curve,
+        })
+    }
+
+    pub fn into_patched(self, registry: &mut FixtureRegistry) -> Result<PatchedFixture> {
+        let mut fixture = registry
+            .create_patched_fixture(
+                &self.manufacturer,
+                &self.fixture,
+                &self.mode,
+                self.channel,
+                self.dmx_start,
+                self.label,
+            )
+            .with_context(|| format!("Failed to patch fixture {}", self.channel))?;
+        fixture.muted = self.muted;
+        fixture.color_limits = self.color_limits.into_iter().collect();
+        fixture.home = self.home.into_iter().collect();
+        fixture.curve = self.curve;
+        Ok(fixture)
+    }
+}
+
+/// The newest show file format this build reads, and the one it writes.
+/// Files saved before the format was versioned read as version 0.
+pub const SHOW_VERSION: u32 = 2;
+
+/// Everything needed to pick a show back up: the patch of every universe,
+/// every playback's cues and the fixture groups
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Show {
+    #[serde(default)]
+    pub version: u32,
+    /// Each universe's patch, by universe id
+    #[serde(default)]
+    pub universes: BTreeMap<u8, Vec<ShowFixture>>,
+    // Before version 2 only the first universe's patch was saved, here
+    #[serde(default, skip_serializing)]
+    pub fixtures: Vec<ShowFixture>,
+    pub playbacks: BTreeMap<String, Vec<Cue>>,
+    // Shows saved before groups existed have none
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<usize>>,
+}
+
+impl Show {
+    pub fn capture(
+        universes: &BTreeMap<u8, Vec<PatchedFixture>>,
+        engine: &CueEngine,
+    ) -> Result<Self> {
+        let universes = universes
+            .iter()
+            .map(|(id, fixtures)| {
+                let fixtures = fixtures
+                    .iter()
+                    .map(ShowFixture::from_patched)
+                    .collect::<Result<_>>()?;
+                Ok((*id, fixtures))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Show {
+            version: SHOW_VERSION,
+            universes,
+            fixtures: Vec::new(),
+            playbacks: engine.cue_lists(),
+            groups: engine.groups().clone(),
+        })
+    }
+
+    /// Rebuild each universe's patch from the fixture library, failing if
+    /// any fixture or mode is no longer in it. A show from before version 2
+    /// only has a patch for `first_universe`.
+    pub fn patch(
+        &self,
+        registry: &mut FixtureRegistry,
+        first_universe: u8,
+    ) -> Result<BTreeMap<u8, Vec<PatchedFixture>>> {
+        let mut universes = self.universes.clone();
+        if self.version < 2 {
+            universes.insert(first_universe, self.fixtures.clone());
+        }
+
+        universes
+            .into_iter()
+            .map(|(id, fixtures)| {
+                let fixtures = fixtures
+                    .into_iter()
+                    .map(|fixture| fixture.into_patched(registry))
+                    .collect::<Result<_>>()?;
+                Ok((id, fixtures))
+            })
+            .collect()
+    }
+
+    pub fn fixture_count(&self) -> usize {
+        self.fixtures.len() + self.universes.values().map(Vec::len).sum::<usize>()
+    }
+
+    pub fn cue_count(&self) -> usize {
+        self.playbacks.values().map(Vec::len).sum()
+    }
+}
+
+pub fn save_show<P: AsRef<Path>>(path: P, show: &Show) -> Result<()> {
+    let content = serde_json::to_string_pretty(show)?;
+    fs::write(&path, content)
+        .with_context(|| format!("Failed to write show to {}", path.as_ref().display()))
+}
+
+pub fn load_show<P: AsRef<Path>>(path: P) -> Result<Show> {
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read show from {}", path.as_ref().display()))?;
+
+    // Checked on its own first, as a newer file may not parse as this format
+    #[derive(Deserialize)]
+    struct Versioned {
+        #[serde(default)]
+        version: u32,
+    }
+    let Versioned { version } = serde_json::from_str(&content)?;
+    if version > SHOW_VERSION {
+        return Err(anyhow!(
+            "{} is a version {} show file, but this build only reads up to version {}",
+            path.as_ref().display(),
+            version,
+            SHOW_VERSION
+        ));
+    }
+
+    Ok(serde_json::from_str(&content)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixture::test_library::write_library,
+        universe::{
+            cue::{CueTime, RecordSource, MAIN_PLAYBACK},
+            process_command, Universe, UniverseCommand,
+        },
+    };
+    use std::{
+        sync::mpsc::{self, Sender},
+        thread::{self, JoinHandle},
+        time::Duration,
+    };
+
+    const PAR: &str = r#"{
+        "name": "Par",
+        "categories": ["Color Changer"],
+        "meta": {"authors": [], "createDate": "2024-01-01", "lastModifyDate": "2024-01-01"},
+        "availableChannels": {
+            "Intensity": {"capability": {"type": "Intensity"}},
+            "Red": {"capability": {"type": "ColorIntensity", "color": "Red"}},
+            "Green": {"capability": {"type": "ColorIntensity", "color": "Green"}},
+            "Blue": {"capability": {"type": "ColorIntensity", "color": "Blue"}}
+        },
+        "modes": [{"name": "4ch", "shortName": "4ch", "channels": ["Intensity", "Red", "Green", "Blue"]}],
+        "fixtureKey": "par",
+        "manufacturerKey": "acme"
+    }"#;
+
+    /// Run a universe on its own thread, as the DMX thread does, until every
+    /// sender is dropped
+    fn serve(mut universe: Universe) -> (Sender<UniverseCommand>, JoinHandle<Universe>) {
+        let (command_tx, command_rx) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            for command in command_rx {
+                process_command(&mut universe, command);
+            }
+            universe
+        });
+        (command_tx, handle)
+    }
+
+    fn dmx_state(command_tx: &Sender<UniverseCommand>) -> [u8; 513] {
+        let (response_tx, response_rx) = mpsc::channel();
+        command_tx
+            .send(UniverseCommand::GetDMXState(response_tx))
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    /// Step through the main playback, then make one hand change, returning
+    /// the levels after each step and the final output frame
+    fn run_show(
+        mut engine: CueEngine,
+        command_tx: Sender<UniverseCommand>,
+        handle: JoinHandle<Universe>,
+    ) -> (Vec<[u8; 513]>, [u8; 513]) {
+        let mut states = Vec::new();
+        while engine.go_in(MAIN_PLAYBACK, None).is_ok() {
+            states.push(dmx_state(&command_tx));
+        }
+        command_tx
+            .send(UniverseCommand::SetFixture {
+                fixture_channel: 1,
+                intensity: None,
+                color: Some((255, 255, 255)),
+            })
+            .unwrap();
+        states.push(dmx_state(&command_tx));
+
+        // The thread hands the universe back once every sender is gone
+        drop((engine, command_tx));
+        (states, handle.join().unwrap().output_buffer())
+    }
+
+    #[test]
+    fn test_saved_show_reproduces_the_same_output() {
+        let dir = write_library("show", &[("par", PAR)]);
+        let mut registry = FixtureRegistry::new(&dir).unwrap();
+
+        let mut limited = registry
+            .create_patched_fixture("acme", "par", "4ch", 1, 0, "Left".to_string())
+            .unwrap();
+        limited.color_limits.insert(ChannelType::Red, 100);
+        let mut muted = registry
+            .create_patched_fixture("acme", "par", "4ch", 2, 4, "Right".to_string())
+            .unwrap();
+        muted.home.insert(0, 50);
+        let (command_tx, handle) = serve(Universe::from_fixtures(0, vec![limited, muted]));
+
+        let mut engine = CueEngine::new(command_tx.clone());
+        let record = |engine: &mut CueEngine, name: &str, fixture_channel, color| {
+            command_tx
+                .send(UniverseCommand::SetFixture {
+                    fixture_channel,
+                    intensity: Some(255),
+                    color: Some(color),
+                })
+                .unwrap();
+            engine
+                .record_cue(name, CueTime::Fixed(Duration::ZERO), &RecordSource::Live)
+                .unwrap();
+        };
+        record(&mut engine, "1", 1, (255, 0, 0));
+        record(&mut engine, "2", 2, (0, 0, 255));
+        record(&mut engine, "3", 1, (0, 255, 0));
+        engine.select_playback("side");
+        record(&mut engine, "1", 2, (10, 20, 30));
+        engine.define_group("both", vec![1, 2]).unwrap();
+        command_tx
+            .send(UniverseCommand::SetMuted {
+                fixture_channel: 2,
+                muted: true,
+            })
+            .unwrap();
+
+        let (patch_tx, patch_rx) = mpsc::channel();
+        command_tx
+            .send(UniverseCommand::GetPatch(patch_tx))
+            .unwrap();
+        let patch = BTreeMap::from([(0, patch_rx.recv().unwrap())]);
+        let show = Show::capture(&patch, &engine).unwrap();
+        let path = dir.join("show.json");
+        save_show(&path, &show).unwrap();
+        // Hand levels from programming would otherwise stay up under the cues
+        command_tx.send(UniverseCommand::ClearManual).unwrap();
+        let expected = run_show(engine, command_tx, handle);
+
+        let loaded = load_show(&path).unwrap();
+        let mut patch = loaded.patch(&mut registry, 0).unwrap();
+
+        let (command_tx, handle) = serve(Universe::new(0));
+        command_tx
+            .send(UniverseCommand::SetPatch(patch.remove(&0).unwrap()))
+            .unwrap();
+        let mut engine = CueEngine::new(command_tx.clone());
+        engine.replace_cues(loaded.playbacks.clone());
+        engine.replace_groups(loaded.groups.clone());
+        assert_eq!(engine.group("both").unwrap(), &[1, 2]);
+        assert_eq!(loaded.cue_count(), 4);
+        assert_eq!(engine.playbacks().count(), 2);
+
+        let actual = run_show(engine, command_tx, handle);
+        assert_eq!(actual.0.len(), 4);
+        assert_eq!(actual, expected);
+        // The muted fixture's intensity is held dark at output, the limited red to 100
+        assert_eq!(loaded.playbacks[MAIN_PLAYBACK][2].channels()[5], 255);
+        assert_eq!(actual.1[5], 0);
+        assert_eq!(actual.1[2], 100);
+    }
+
+    #[test]
+    fn test_show_saves_and_loads_every_universe() {
+        let dir = write_library("show-universes", &[("par", PAR)]);
+        let mut registry = FixtureRegistry::new(&dir).unwrap();
+        let mut patch_on = |dmx_start, label: &str| {
+            let fixture = registry
+                .create_patched_fixture("acme", "par", "4ch", 1, dmx_start, label.to_string())
+                .unwrap();
+            vec![fixture]
+        };
+        let patch = BTreeMap::from([(0, patch_on(0, "Front")), (2, patch_on(10, "Back"))]);
+
+        let engine = CueEngine::new(mpsc::channel().0);
+        let path = dir.join("show.json");
+        save_show(&path, &Show::capture(&patch, &engine).unwrap()).unwrap();
+
+        let loaded = load_show(&path).unwrap();
+        assert_eq!(loaded.fixture_count(), 2);
+        let patch = loaded.patch(&mut registry, 0).unwrap();
+        assert_eq!(patch.keys().copied().collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(patch[&0][0].label, "Front");
+        assert_eq!(patch[&2][0].label, "Back");
+        assert_eq!(patch[&2][0].dmx_start, 10);
+    }
+
+    #[test]
+    fn test_unversioned_show_loads_with_defaults() {
+        let dir = write_library("show-v0", &[("par", PAR)]);
+        let path = dir.join("show.json");
+        // As saved before versions, groups and dimmer curves
+        fs::write(
+            &path,
+            r#"{
+                "fixtures": [{
+                    "manufacturer": "acme", "fixture": "par", "mode": "4ch",
+                    "channel": 1, "dmx_start": 0, "label": "Left", "muted": false,
+                    "color_limits": [], "home": {}
+                }],
+                "playbacks": {}
+            }"#,
+        )
+        .unwrap();
+
+        let show = load_show(&path).unwrap();
+        assert_eq!(show.version, 0);
+        assert_eq!(show.fixtures[0].curve, DimmerCurve::Linear);
+        assert!(show.groups.is_empty());
+        // The one saved patch was the first universe's
+        let mut registry = FixtureRegistry::new(&dir).unwrap();
+        let patch = show.patch(&mut registry, 3).unwrap();
+        assert_eq!(patch.keys().copied().collect::<Vec<_>>(), vec![3]);
+        assert_eq!(patch[&3][0].label, "Left");
+    }
+
+    #[test]
+    fn test_newer_show_version_is_rejected() {
+        let dir = write_library("show-next", &[]);
+        let path = dir.join("show.json");
+        fs::write(&path, format!(r#"{{"version": {}}}"#, SHOW_VERSION + 1)).unwrap();
+
+        let error = load_show(&path).err().unwrap().to_string();
+        assert!(error.contains("only reads up to version"), "{}", error);
+    }
+}
@@ -0,0 +1,441 @@
+use std::{
+    collections::hash_map::RandomState,
+    ffi::CString,
+    hash::{BuildHasher, Hasher},
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    sync::OnceLock,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{
+    dmx_close, dmx_open, dmx_send_break, dmx_write,
+    universe::{BreakFailed, DMX_BUFFER_LENGTH},
+};
+
+/// Where the DMX thread sends each frame
+pub trait DmxSink: Send {
+    /// Send one frame: the start code, then 512 slots
+    fn send(&mut self, frame: &[u8; 513]) -> Result<()>;
+
+    /// Change the priority receivers merge this output at
+    fn set_priority(&mut self, _priority: u8) -> Result<()> {
+        Err(anyhow!("Only sACN output has a priority"))
+    }
+}
+
+/// A USB or serial DMX interface, driven through the C layer
+pub struct SerialSink {
+    fd: i32,
+}
+
+impl SerialSink {
+    /// Open a serial port, e.g. "COM3" or "/dev/ttyUSB0". Check `is_open`
+    /// before sending; with the `no-dmx` feature a closed port is fine.
+    pub fn open(port: &str) -> Result<Self> {
+        let port = CString::new(port).context("Port name contains a NUL byte")?;
+        let fd = unsafe { dmx_open(port.as_ptr()) };
+        Ok(Self { fd })
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.fd >= 0
+    }
+}
+
+impl DmxSink for SerialSink {
+    fn send(&mut self, frame: &[u8; 513]) -> Result<()> {
+        if unsafe { dmx_send_break(self.fd) } < 0 {
+            return Err(BreakFailed.into());
+        }
+        if unsafe { dmx_write(self.fd, frame.as_ptr(), DMX_BUFFER_LENGTH) } < 0 {
+            return Err(anyhow!("Dmx failed to write"));
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for SerialSink {
+    fn drop(&mut self) {
+        unsafe { dmx_close(self.fd) };
+    }
+}
+
+/// UDP port Art-Net nodes listen on
+pub const ARTNET_PORT: u16 = 6454;
+
+/// Highest Art-Net port-address: 7 bits of net, 4 of sub-net, 4 of universe
+pub const MAX_ARTNET_UNIVERSE: u16 = 0x7FFF;
+
+/// Art-Net output: every frame goes out as one ArtDMX packet over UDP, to a
+/// node, a broadcast address, or another console such as QLC+
+pub struct ArtNetSink {
+    socket: UdpSocket,
+    target: SocketAddr,
+    universe: u16,
+    sequence: u8,
+}
+
+impl ArtNetSink {
+    /// Send to `target` on Art-Net port-address `universe`
+    pub fn new(target: SocketAddr, universe: u16) -> Result<Self> {
+        if universe > MAX_ARTNET_UNIVERSE {
+            return Err(anyhow!(
+                "Art-Net universe {} is above the highest port-address, {}",
+                universe,
+                MAX_ARTNET_UNIVERSE
+            ));
+        }
+
+        let socket = UdpSocket::bind(("0.0.0.0", 0)).context("Failed to open Art-Net socket")?;
+        // Nodes are often reached through the subnet broadcast address
+        socket.set_broadcast(true)?;
+
+        Ok(Self {
+            socket,
+            target,
+            universe,
+            sequence: 0,
+        })
+    }
+
+    /// Parse `<ip>` or `<ip>:<port>`, defaulting to the Art-Net port
+    pub fn parse_target(target: &str) -> Result<SocketAddr> {
+        target
+            .parse::<SocketAddr>()
+            .or_else(|_| {
+                target
+                    .parse::<IpAddr>()
+                    .map(|ip| SocketAddr::new(ip, ARTNET_PORT))
+            })
+            .map_err(|_| anyhow!("'{}' is not an IP address or <ip>:<port>", target))
+    }
+
+    /// One ArtDMX packet carrying the 512 slots of `frame`. Art-Net has no
+    /// start code; every ArtDMX packet is dimmer data.
+    fn packet(&mut self, frame: &[u8; 513]) -> Vec<u8> {
+        // Sequence 0 tells receivers not to reorder, so counting runs 1..=255
+        self.sequence = self.sequence.checked_add(1).unwrap_or(1);
+        let slots = &frame[1..];
+
+        let mut packet = Vec::with_capacity(18 + slots.len());
+        packet.extend_from_slice(b"Art-Net\0");
+        packet.extend_from_slice(&0x5000u16.to_le_bytes()); // OpDmx
+        packet.extend_from_slice(&14u16.to_be_bytes()); // Protocol version
+        packet.push(self.sequence);
+        packet.push(0); // Physical input port, informational only
+        packet.extend_from_slice(&self.universe.to_le_bytes()); // SubUni, then Net
+        packet.extend_from_slice(&(slots.len() as u16).to_be_bytes());
+        packet.extend_from_slice(slots);
+        packet
+    }
+}
+
+impl DmxSink for ArtNetSink {
+    fn send(&mut self, frame: &[u8; 513]) -> Result<()> {
+        let packet = self.packet(frame);
+        self.socket
+            .send_to(&packet, self.target)
+            .with_context(|| format!("Failed to send Art-Net to {}", self.target))?;
+
+        Ok(())
+    }
+}
+
+/// UDP port sACN receivers listen on
+pub const SACN_PORT: u16 = 5568;
+
+/// Priority receivers assume when a source doesn't need to win or lose a merge
+pub const DEFAULT_SACN_PRIORITY: u8 = 100;
+
+/// Highest priority E1.31 allows
+pub const MAX_SACN_PRIORITY: u8 = 200;
+
+/// Size of an E1.31 data packet carrying a start code and 512 slots
+const SACN_PACKET_LENGTH: usize = 638;
+
+/// Source name sent when none is given
+pub const DEFAULT_SACN_SOURCE_NAME: &str = "lights";
+
+/// Longest source name E1.31 carries, leaving room for its NUL terminator
+pub const MAX_SACN_SOURCE_NAME: usize = 63;
+
+fn check_priority(priority: u8) -> Result<()> {
+    if priority > MAX_SACN_PRIORITY {
+        return Err(anyhow!(
+            "sACN priority {} is above the highest, {}",
+            priority,
+            MAX_SACN_PRIORITY
+        ));
+    }
+    Ok(())
+}
+
+/// Component ID identifying this process as a source. Receivers track
+/// sequence numbers and priority per CID, so it must not change while running.
+fn source_cid() -> [u8; 16] {
+    static CID: OnceLock<[u8; 16]> = OnceLock::new();
+    *CID.get_or_init(|| {
+        // RandomState is seeded randomly for each process
+        let half = |salt: u64| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u64(salt ^ std::process::id() as u64);
+            hasher.finish().to_be_bytes()
+        };
+        let mut cid = [0u8; 16];
+        cid[..8].copy_from_slice(&half(0));
+        cid[8..].copy_from_slice(&half(1));
+        // Mark it as a random (version 4) UUID
+        cid[6] = (cid[6] & 0x0F) | 0x40;
+        cid[8] = (cid[8] & 0x3F) | 0x80;
+        cid
+    })
+}
+
+/// How this board names itself to sACN receivers
+#[derive(Clone)]
+pub struct SacnSource {
+    name: [u8; 64],
+    cid: [u8; 16],
+}
+
+impl SacnSource {
+    /// Send as `name`, with `cid` as the component ID if given: a UUID such
+    /// as 6ba7b810-9dad-11d1-80b4-00c04fd430c8. Without one, a random ID is
+    /// used for the life of the process.
+    pub fn new(name: &str, cid: Option<&str>) -> Result<Self> {
+        if name.len() > MAX_SACN_SOURCE_NAME {
+            return Err(anyhow!(
+                "sACN source name '{}' is longer than {} bytes",
+                name,
+                MAX_SACN_SOURCE_NAME
+            ));
+        }
+        let mut padded = [0u8; 64];
+        padded[..name.len()].copy_from_slice(name.as_bytes());
+
+        Ok(Self {
+            name: padded,
+            cid: match cid {
+                Some(cid) => Self::parse_cid(cid)?,
+                None => source_cid(),
+            },
+        })
+    }
+
+    /// Parse a UUID's 32 hex digits, ignoring dashes
+    pub fn parse_cid(cid: &str) -> Result<[u8; 16]> {
+        let digits: String = cid.chars().filter(|&c| c != '-').collect();
+        if digits.len() != 32 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow!("'{}' is not a UUID", cid));
+        }
+
+        let mut bytes = [0u8; 16];
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&digits[index * 2..index * 2 + 2], 16)?;
+        }
+        Ok(bytes)
+    }
+}
+
+impl Default for SacnSource {
+    fn default() -> Self {
+        Self::new(DEFAULT_SACN_SOURCE_NAME, None).expect("default source name fits")
+    }
+}
+
+/// Streaming ACN (E1.31) output, multicast to the group for its universe
+pub struct SacnSink {
+    socket: UdpSocket,
+    universe: u16,
+    priority: u8,
+    source: SacnSource,
+    sequence: u8,
+}
+
+impl SacnSink {
+    /// Send universe `universe`, which must be 1 to 255, at `priority`
+    pub fn new(universe: u16, priority: u8, source: SacnSource) -> Result<Self> {
+        if !(1..=u8::MAX as u16).contains(&universe) {
+            return Err(anyhow!(
+                "sACN universe {} is out of range; this board drives universes 1 to 255",
+                universe
+            ));
+        }
+        check_priority(priority)?;
+
+        let socket = UdpSocket::bind(("0.0.0.0", 0)).context("Failed to open sACN socket")?;
+
+        Ok(Self {
+            socket,
+            universe,
+            priority,
+            source,
+            sequence: 0,
+        })
+    }
+
+    /// Multicast group for a universe: 239.255.<high byte>.<low byte>
+    pub fn multicast_group(universe: u16) -> Ipv4Addr {
+        let [high, low] = universe.to_be_bytes();
+        Ipv4Addr::new(239, 255, high, low)
+    }
+
+    /// One E1.31 data packet: root, framing and DMP layers around the whole
+    /// frame, start code included
+    fn packet(&mut self, frame: &[u8; 513]) -> Vec<u8> {
+        let flags_and_length =
+            |from: usize| (0x7000 | (SACN_PACKET_LENGTH - from) as u16).to_be_bytes();
+
+        let mut packet = Vec::with_capacity(SACN_PACKET_LENGTH);
+        // Root layer
+        packet.extend_from_slice(&0x0010u16.to_be_bytes()); // Preamble size
+        packet.extend_from_slice(&0u16.to_be_bytes()); // Postamble size
+        packet.extend_from_slice(b"ASC-E1.17\0\0\0");
+        packet.extend_from_slice(&flags_and_length(16));
+        packet.extend_from_slice(&4u32.to_be_bytes()); // VECTOR_ROOT_E131_DATA
+        packet.extend_from_slice(&self.source.cid);
+
+        // Framing layer
+        packet.extend_from_slice(&flags_and_length(38));
+        packet.extend_from_slice(&2u32.to_be_bytes()); // VECTOR_E131_DATA_PACKET
+        packet.extend_from_slice(&self.source.name);
+        packet.push(self.priority);
+        packet.extend_from_slice(&0u16.to_be_bytes()); // No synchronization
+        packet.push(self.sequence);
+        packet.push(0); // Options
+        packet.extend_from_slice(&self.universe.to_be_bytes());
+        self.sequence = self.sequence.wrapping_add(1);
+
+        // DMP layer
+        packet.extend_from_slice(&flags_and_length(115));
+        packet.push(0x02); // VECTOR_DMP_SET_PROPERTY
+        packet.push(0xA1); // Address and data type
+        packet.extend_from_slice(&0u16.to_be_bytes()); // First property address
+        packet.extend_from_slice(&1u16.to_be_bytes()); // Address increment
+        packet.extend_from_slice(&(frame.len() as u16).to_be_bytes());
+        packet.extend_from_slice(frame);
+        packet
+    }
+}
+
+impl DmxSink for SacnSink {
+    fn send(&mut self, frame: &[u8; 513]) -> Result<()> {
+        let packet = self.packet(frame);
+        let group = Self::multicast_group(self.universe);
+        self.socket
+            .send_to(&packet, (group, SACN_PORT))
+            .with_context(|| format!("Failed to send sACN to {}", group))?;
+
+        Ok(())
+    }
+
+    fn set_priority(&mut self, priority: u8) -> Result<()> {
+        check_priority(priority)?;
+        self.priority = priority;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_artnet_sends_artdmx_with_sequence_numbers() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        let mut sink = ArtNetSink::new(receiver.local_addr().unwrap(), 0x0123).unwrap();
+
+        let mut frame = [0u8; 513];
+        frame[1] = 255;
+        frame[512] = 7;
+        sink.send(&frame).unwrap();
+        sink.send(&frame).unwrap();
+
+        let mut packet = [0u8; 600];
+        let length = receiver.recv(&mut packet).unwrap();
+        assert_eq!(length, 18 + 512);
+        assert_eq!(&packet[..8], b"Art-Net\0");
+        assert_eq!(packet[8..12], [0x00, 0x50, 0x00, 14]);
+        assert_eq!(packet[12], 1, "sequence");
+        assert_eq!(packet[14..16], [0x23, 0x01], "SubUni, Net");
+        assert_eq!(packet[16..18], [0x02, 0x00], "length");
+        assert_eq!(packet[18], 255);
+        assert_eq!(packet[length - 1], 7);
+
+        receiver.recv(&mut packet).unwrap();
+        assert_eq!(packet[12], 2);
+
+        assert!(ArtNetSink::new(receiver.local_addr().unwrap(), 0x8000).is_err());
+        assert_eq!(
+            ArtNetSink::parse_target("10.0.0.5").unwrap(),
+            "10.0.0.5:6454".parse().unwrap()
+        );
+        assert!(ArtNetSink::parse_target("node.local").is_err());
+    }
+
+    #[test]
+    fn test_sacn_packet_layout_and_sequence() {
+        let mut sink = SacnSink::new(7, DEFAULT_SACN_PRIORITY, SacnSource::default()).unwrap();
+        let mut frame = [0u8; 513];
+        frame[1] = 255;
+        frame[512] = 9;
+
+        let first = sink.packet(&frame);
+        let second = sink.packet(&frame);
+        assert_eq!(first.len(), SACN_PACKET_LENGTH);
+        assert_eq!(&first[4..16], b"ASC-E1.17\0\0\0");
+        assert_eq!(first[16..18], [0x72, 0x6E]);
+        assert_eq!(first[38..40], [0x72, 0x58]);
+        assert_eq!(first[115..117], [0x72, 0x0B]);
+        assert_eq!(first[108], 100, "priority");
+        assert_eq!(first[113..115], [0x00, 0x07], "universe");
+        assert_eq!(first[123..125], [0x02, 0x01], "property count");
+        assert_eq!(first[125], 0, "start code");
+        assert_eq!(first[126], 255);
+        assert_eq!(first[637], 9);
+        assert_eq!(second[111], first[111].wrapping_add(1));
+        // The CID stays put for the life of the process
+        assert_eq!(first[22..38], second[22..38]);
+        assert_eq!(
+            first[22..38],
+            SacnSink::new(2, 100, SacnSource::default())
+                .unwrap()
+                .packet(&frame)[22..38]
+        );
+
+        assert_eq!(SacnSink::multicast_group(7), Ipv4Addr::new(239, 255, 0, 7));
+        let sink = |universe, priority| SacnSink::new(universe, priority, SacnSource::default());
+        let error = sink(256, 100).err().unwrap().to_string();
+        assert!(error.contains("1 to 255"), "{}", error);
+        assert!(sink(0, 100).is_err());
+        assert!(sink(1, 201).is_err());
+    }
+
+    #[test]
+    fn test_sacn_source_and_priority_are_configurable() {
+        let cid = "6ba7b810-9dad-11d1-80b4-00c04fd430c8";
+        let source = SacnSource::new("Backup desk", Some(cid)).unwrap();
+        let mut sink = SacnSink::new(1, DEFAULT_SACN_PRIORITY, source).unwrap();
+        let frame = [0u8; 513];
+
+        let packet = sink.packet(&frame);
+        assert_eq!(packet[22..38], SacnSource::parse_cid(cid).unwrap());
+        assert_eq!(packet[22..24], [0x6B, 0xA7]);
+        assert_eq!(&packet[44..56], b"Backup desk\0");
+
+        sink.set_priority(150).unwrap();
+        assert_eq!(sink.packet(&frame)[108], 150);
+        assert!(sink.set_priority(201).is_err());
+        assert_eq!(sink.packet(&frame)[108], 150);
+
+        assert!(SacnSource::parse_cid("6ba7b810-9dad").is_err());
+        assert!(SacnSource::parse_cid("zba7b810-9dad-11d1-80b4-00c04fd430c8").is_err());
+        assert!(SacnSource::new(&"x".repeat(64), None).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Number of recent frame intervals averaged for the achieved frame rate
+const FRAME_WINDOW: usize = 40;
+
+/// Snapshot of DMX output performance, returned by `UniverseCommand::GetStats`
+#[derive(Debug, Clone, Default)]
+pub struct DmxStats {
+    /// Achieved frame rate, averaged over the last few frames
+    pub fps: f32,
+    /// Commands processed during the last full second
+    pub commands_per_second: u32,
+    pub frames_sent: u64,
+    /// Frames sent at least a whole interval later than scheduled
+    pub late_frames: u64,
+    /// Ticks that used up the command budget and deferred the rest of the queue
+    pub overruns: u64,
+    /// Time between frames the thread is aiming for, from `DmxConfig`
+    pub frame_interval: Duration,
+    /// Time each tick may spend on commands, from `DmxConfig`
+    pub command_budget: Duration,
+    /// Deepest the command queue has been at the start of a tick
+    pub queue_high_water: usize,
+    /// Redundant SetChannel writes dropped while the queue was backed up
+    pub coalesced_writes: u64,
+    /// Channel writes skipped because a later write in the same tick replaced them
+    pub deduplicated_writes: u64,
+    /// Frames dropped because the interface failed to send the break
+    pub break_failures: u64,
+    /// Break failures since the last frame that went out; non-zero means the link is down
+    pub consecutive_break_failures: u32,
+}
+
+/// Tracks output timing inside the DMX thread
+pub struct StatsTracker {
+    frame_intervals: VecDeque<Duration>,
+    last_frame: Option<Instant>,
+    second_start: Instant,
+    commands_this_second: u32,
+    stats: DmxStats,
+}
+
+impl StatsTracker {
+    pub fn new() -> Self {
+        Self {
+            frame_intervals: VecDeque::with_capacity(FRAME_WINDOW),
+            last_frame: None,
+            second_start: Instant::now(),
+            commands_this_second: 0,
+            stats: DmxStats::default(),
+        }
+    }
+
+    /// Record a frame sent at `now`, with `target` being the intended frame interval
+    pub fn record_frame(&mut self, now: Instant, target: Duration) {
+        if let Some(last_frame) = self.last_frame {
+            let interval = now.duration_since(last_frame);
+            if interval >= target * 2 {
+                self.stats.late_frames += 1;
+            }
+
+            if self.frame_intervals.len() == FRAME_WINDOW {
+                self.frame_intervals.pop_front();
+            }
+            self.frame_intervals.push_back(interval);
+        }
+
+        self.last_frame = Some(now);
+        self.stats.frames_sent += 1;
+    }
+
+    /// Record commands processed during one tick
+    pub fn record_commands(&mut self, now: Instant, count: u32) {
+        if now.duration_since(self.second_start) >= Duration::from_secs(1) {
+            self.stats.commands_per_second = self.commands_this_second;
+            self.commands_this_second = 0;
+            self.second_start = now;
+        }
+        self.commands_this_second += count;
+    }
+
+    pub fn record_overrun(&mut self) {
+        self.stats.overruns += 1;
+    }
+
+    pub fn record_queue_depth(&mut self, depth: usize) {
+        self.stats.queue_high_water = self.stats.queue_high_water.max(depth);
+    }
+
+    /// Record whether a frame's break went out, returning the current run of failures
+    pub fn record_break(&mut self, ok: bool) -> u32 {
+        if ok {
+            self.stats.consecutive_break_failures = 0;
+        } else {
+            self.stats.break_failures += 1;
+            self.stats.consecutive_break_failures += 1;
+        }
+        self.stats.consecutive_break_failures
+    }
+
+    pub fn record_coalesced(&mut self, count: usize) {
+        self.stats.coalesced_writes += count as u64;
+    }
+
+    pub fn record_deduplicated(&mut self, count: u64) {
+        self.stats.deduplicated_writes += count;
+    }
+
+    pub fn snapshot(&self) -> DmxStats {
+        let total: Duration = self.frame_intervals.iter().sum();
+        let fps = if total.is_zero() {
+            0.0
+        } else {
+            self.frame_intervals.len() as f32 / total.as_secs_f32()
+        };
+
+        DmxStats {
+            fps,
+            ..self.stats.clone()
+        }
+    }
+}
+
+impl Default for StatsTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_rate_and_late_frames() {
+        let mut tracker = StatsTracker::new();
+        let target = Duration::from_millis(25);
+        let start = Instant::now();
+
+        for frame in 0..10 {
+            tracker.record_frame(start + target * frame, target);
+        }
+        let stats = tracker.snapshot();
+        assert_eq!(stats.frames_sent, 10);
+        assert_eq!(stats.late_frames, 0);
+        assert!((stats.fps - 40.0).abs() < 0.1);
+
+        // A stalled tick shows up as a late frame
+        tracker.record_frame(start + target * 12, target);
+        assert_eq!(tracker.snapshot().late_frames, 1);
+    }
+
+    #[test]
+    fn test_consecutive_break_failures_reset_on_success() {
+        let mut tracker = StatsTracker::new();
+
+        assert_eq!(tracker.record_break(false), 1);
+        assert_eq!(tracker.record_break(false), 2);
+        assert_eq!(tracker.record_break(true), 0);
+        tracker.record_break(false);
+
+        let stats = tracker.snapshot();
+        assert_eq!(stats.break_failures, 3);
+        assert_eq!(stats.consecutive_break_failures, 1);
+    }
+}
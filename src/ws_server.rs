@@ -0,0 +1,269 @@
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    net::{TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use tungstenite::{Message, WebSocket};
+
+use crate::universe::{
+    cue::{CueEngine, MAIN_PLAYBACK},
+    ChannelCapabilities, FixtureInfo, UniverseCommand,
+};
+
+/// How often the broadcaster polls the DMX thread for state changes
+const STATE_POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+/// How long a client read blocks before checking for pending broadcasts
+const CLIENT_READ_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Messages a WebSocket client can send, tagged by `type`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    GetFixtures,
+    GetFixtureCapabilities { channel: usize },
+    SetIntensity { channel: usize, intensity: u8 },
+    SetRgb { channel: usize, r: u8, g: u8, b: u8 },
+    Blackout,
+    Go,
+    Back,
+}
+
+/// Messages sent back to clients, tagged by `type`
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    Fixtures { fixtures: Vec<FixtureInfo> },
+    FixtureCapabilities {
+        channel: usize,
+        channels: Vec<ChannelCapabilities>,
+    },
+    DmxState { universe: u8, channels: Vec<u8> },
+    Ok,
+    Error { message: String },
+}
+
+type ClientList = Arc<Mutex<Vec<Sender<String>>>>;
+
+/// Start the WebSocket API on `address`, returning once the listener is bound
+pub fn start_ws_server(
+    address: &str,
+    command_tx: Sender<UniverseCommand>,
+    show: Arc<Mutex<CueEngine>>,
+) -> Result<()> {
+    let listener = TcpListener::bind(address)
+        .with_context(|| format!("Failed to bind WebSocket server to {}", address))?;
+    let clients: ClientList = Arc::new(Mutex::new(Vec::new()));
+
+    let broadcast_tx = command_tx.clone();
+    let broadcast_clients = clients.clone();
+    thread::spawn(move || broadcast_dmx_state(broadcast_tx, broadcast_clients));
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("WebSocket connection failed: {}", e);
+                    continue;
+                }
+            };
+
+            let (client_tx, client_rx) = mpsc::channel();
+            clients.lock().unwrap().push(client_tx);
+
+            let command_tx = command_tx.clone();
+            let show = show.clone();
+            thread::spawn(move || {
+                if let Err(e) = handle_client(stream, client_rx, command_tx, show) {
+                    eprintln!("WebSocket client error: {}", e);
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+/// Poll the DMX thread and push each universe's buffer to every client when
+/// it changes
+fn broadcast_dmx_state(command_tx: Sender<UniverseCommand>, clients: ClientList) {
+    let mut last_states: HashMap<u8, [u8; 513]> = HashMap::new();
+
+    loop {
+        thread::sleep(STATE_POLL_INTERVAL);
+
+        if clients.lock().unwrap().is_empty() {
+            continue;
+        }
+
+        let (response_tx, response_rx) = mpsc::channel();
+        if command_tx
+            .send(UniverseCommand::GetUniverses(response_tx))
+            .is_err()
+        {
+            break; // DMX thread is gone
+        }
+        let Ok(universes) = response_rx.recv_timeout(Duration::from_millis(100)) else {
+            continue;
+        };
+
+        for universe in universes.iter().map(|status| status.id) {
+            let (response_tx, response_rx) = mpsc::channel();
+            if command_tx
+                .send(UniverseCommand::OnUniverse {
+                    universe,
+                    command: Box::new(UniverseCommand::GetDMXState(response_tx)),
+                })
+                .is_err()
+            {
+                return;
+            }
+
+            let Ok(state) = response_rx.recv_timeout(Duration::from_millis(100)) else {
+                continue;
+            };
+            if last_states.insert(universe, state) == Some(state) {
+                continue;
+            }
+
+            let message = match serde_json::to_string(&ServerMessage::DmxState {
+                universe,
+                channels: state[1..].to_vec(),
+            }) {
+                Ok(message) => message,
+                Err(_) => continue,
+            };
+
+            // Drop any clients whose connection has closed
+            clients
+                .lock()
+                .unwrap()
+                .retain(|client| client.send(message.clone()).is_ok());
+        }
+    }
+}
+
+fn handle_client(
+    stream: TcpStream,
+    broadcasts: Receiver<String>,
+    command_tx: Sender<UniverseCommand>,
+    show: Arc<Mutex<CueEngine>>,
+) -> Result<()> {
+    stream.set_read_timeout(Some(CLIENT_READ_TIMEOUT))?;
+    let mut socket = tungstenite::accept(stream).map_err(|e| anyhow!("Handshake failed: {}", e))?;
+
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let reply = match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(message) => execute_message(message, &command_tx, &show)
+                        .unwrap_or_else(|e| ServerMessage::Error {
+                            message: e.to_string(),
+                        }),
+                    Err(e) => ServerMessage::Error {
+                        message: format!("Invalid message: {}", e),
+                    },
+                };
+                send_message(&mut socket, &reply)?;
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                break
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        while let Ok(state) = broadcasts.try_recv() {
+            socket.send(Message::Text(state))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn send_message(socket: &mut WebSocket<TcpStream>, message: &ServerMessage) -> Result<()> {
+    socket.send(Message::Text(serde_json::to_string(message)?))?;
+    Ok(())
+}
+
+fn execute_message(
+    message: ClientMessage,
+    command_tx: &Sender<UniverseCommand>,
+    show: &Mutex<CueEngine>,
+) -> Result<ServerMessage> {
+    match message {
+        ClientMessage::GetFixtures => {
+            let (response_tx, response_rx) = mpsc::channel();
+            command_tx
+                .send(UniverseCommand::GetFixtures(response_tx))
+                .with_context(|| "Failed to send GetFixtures command")?;
+
+            let fixtures = response_rx
+                .recv_timeout(Duration::from_millis(100))
+                .with_context(|| "Timeout receiving fixtures")?;
+            Ok(ServerMessage::Fixtures { fixtures })
+        }
+        ClientMessage::GetFixtureCapabilities { channel } => {
+            let (response_tx, response_rx) = mpsc::channel();
+            command_tx
+                .send(UniverseCommand::GetFixtureCapabilities {
+                    fixture_channel: channel,
+                    response: response_tx,
+                })
+                .with_context(|| "Failed to send GetFixtureCapabilities command")?;
+
+            let channels = response_rx
+                .recv_timeout(Duration::from_millis(100))
+                .with_context(|| "Timeout receiving fixture capabilities")?
+                .ok_or_else(|| anyhow!("No fixture found at channel {}", channel))?;
+            Ok(ServerMessage::FixtureCapabilities { channel, channels })
+        }
+        ClientMessage::SetIntensity { channel, intensity } => {
+            command_tx
+                .send(UniverseCommand::SetFixture {
+                    fixture_channel: channel,
+                    intensity: Some(intensity),
+                    color: None,
+                })
+                .with_context(|| "Failed to send fixture command")?;
+            Ok(ServerMessage::Ok)
+        }
+        ClientMessage::SetRgb { channel, r, g, b } => {
+            command_tx
+                .send(UniverseCommand::SetFixture {
+                    fixture_channel: channel,
+                    intensity: None,
+                    color: Some((r, g, b)),
+                })
+                .with_context(|| "Failed to send fixture command")?;
+            Ok(ServerMessage::Ok)
+        }
+        ClientMessage::Blackout => {
+            command_tx
+                .send(UniverseCommand::Blackout)
+                .with_context(|| "Failed to send blackout command")?;
+            Ok(ServerMessage::Ok)
+        }
+        ClientMessage::Go => {
+            show.lock().unwrap().go_in(MAIN_PLAYBACK, None)?;
+            Ok(ServerMessage::Ok)
+        }
+        ClientMessage::Back => {
+            show.lock().unwrap().back(MAIN_PLAYBACK)?;
+            Ok(ServerMessage::Ok)
+        }
+    }
+}